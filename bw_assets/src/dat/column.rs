@@ -0,0 +1,79 @@
+use std::convert::TryInto;
+
+/// Start of a column of a dat file, in bytes from the start of the file.
+///
+/// Dat files store every field of every entry in its own column, so a column
+/// can be read or replaced without decoding the rest of the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DatColumn(usize);
+
+impl DatColumn {
+    pub fn at(offset: usize) -> DatColumn {
+        DatColumn(offset)
+    }
+
+    pub fn offset(&self) -> usize {
+        self.0
+    }
+}
+
+/// Bytes of a column of `count` entries that are `entry_size` bytes each, or
+/// `None` if the file is too short to hold the column, including when the end
+/// of the column does not fit in a `usize`.
+pub fn dat_column(
+    bytes: &[u8],
+    column: DatColumn,
+    entry_size: usize,
+    count: usize,
+) -> Option<&[u8]> {
+    let end = entry_size
+        .checked_mul(count)
+        .and_then(|len| column.offset().checked_add(len))?;
+
+    bytes.get(column.offset()..end)
+}
+
+/// Reads a column of `u8` entries.
+pub fn read_u8_column(column: &[u8]) -> Vec<u8> {
+    column.to_vec()
+}
+
+/// Reads a column of little endian `u16` entries.
+pub fn read_u16_column(column: &[u8]) -> Vec<u16> {
+    column
+        .chunks_exact(2)
+        .map(|entry| u16::from_le_bytes(entry.try_into().expect("2 byte entry")))
+        .collect()
+}
+
+/// Reads a column of little endian `u32` entries.
+pub fn read_u32_column(column: &[u8]) -> Vec<u32> {
+    column
+        .chunks_exact(4)
+        .map(|entry| u32::from_le_bytes(entry.try_into().expect("4 byte entry")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_slices_a_column() {
+        let bytes = [0, 0, 1, 0, 2, 0, 9];
+
+        let column = dat_column(&bytes, DatColumn::at(2), 2, 2).unwrap();
+
+        assert_that(&read_u16_column(column)).is_equal_to(vec![1, 2]);
+        assert_that(&dat_column(&bytes, DatColumn::at(2), 2, 3)).is_none();
+    }
+
+    #[test]
+    fn it_does_not_overflow_on_huge_columns() {
+        let bytes = [0; 4];
+
+        assert_that(&dat_column(&bytes, DatColumn::at(1), usize::MAX, 2)).is_none();
+        assert_that(&dat_column(&bytes, DatColumn::at(usize::MAX), 1, 1)).is_none();
+    }
+}
@@ -0,0 +1,255 @@
+//! Brood War damage rules
+//!
+//! Damage is calculated in fixed point with 8 fractional bits, the same as
+//! the game does internally, so a value of 256 is one hit point.
+
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+
+use super::{Unit, UnitSize, Weapon};
+
+/// Fixed point scale of damage values. One hit point is 256.
+pub const DAMAGE_SCALE: u32 = 256;
+
+/// Least damage dealt to hit points by a hit, no matter how much armor the
+/// target has: half a hit point.
+pub const MIN_DAMAGE: u32 = DAMAGE_SCALE / 2;
+
+/// Type of damage dealt by a weapon.
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum DamageType {
+    Independent = 0,
+    Explosive = 1,
+    Concussive = 2,
+    Normal = 3,
+    IgnoreArmor = 4,
+}
+
+impl TryFrom<u8> for DamageType {
+    type Error = u8;
+
+    /// Fails with the original value if it is not a damage type.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FromPrimitive::from_u8(value).ok_or(value)
+    }
+}
+
+impl DamageType {
+    /// Fraction of the damage dealt to a unit of `size`, out of 256.
+    pub fn multiplier(self, size: UnitSize) -> u32 {
+        match (self, size) {
+            (_, UnitSize::Independent) | (DamageType::Independent, _) => 0,
+            (DamageType::Explosive, UnitSize::Small) => 128,
+            (DamageType::Explosive, UnitSize::Medium) => 192,
+            (DamageType::Explosive, UnitSize::Large) => 256,
+            (DamageType::Concussive, UnitSize::Small) => 256,
+            (DamageType::Concussive, UnitSize::Medium) => 128,
+            (DamageType::Concussive, UnitSize::Large) => 64,
+            (DamageType::Normal, _) | (DamageType::IgnoreArmor, _) => 256,
+        }
+    }
+}
+
+/// Scales `damage` by the multiplier of the damage type against a unit size.
+pub fn scale_damage(damage: u32, damage_type: DamageType, size: UnitSize) -> u32 {
+    damage * damage_type.multiplier(size) / DAMAGE_SCALE
+}
+
+/// Damage of a single hit of `weapon` against `target` in whole hit points,
+/// rounded down, after the damage type has been applied.
+///
+/// Armor, shields and upgrades are ignored; see [`final_damage`]. `None` if
+/// the damage type of the weapon or the size of the target is not one the
+/// game knows.
+pub fn damage_against(weapon: &Weapon, target: &Unit) -> Option<u32> {
+    let damage = scale_damage(
+        weapon.damage_amount() as u32 * DAMAGE_SCALE,
+        weapon.damage_type().ok()?,
+        target.size().ok()?,
+    );
+
+    Some(damage / DAMAGE_SCALE)
+}
+
+/// Total damage dealt to shields and hit points by a single hit of `damage`.
+///
+/// Shields absorb damage first, reduced only by the shield upgrade level and
+/// regardless of the damage type. Whatever the shields cannot absorb is
+/// reduced by `armor`, then scaled by the damage type against the target's
+/// size, and finally raised to [`MIN_DAMAGE`]. Weapons that ignore armor
+/// skip both the shield upgrade and armor reductions.
+pub fn mitigate_damage(
+    damage: u32,
+    damage_type: DamageType,
+    size: UnitSize,
+    armor: u16,
+    shields: u32,
+    shield_upgrade: u16,
+) -> u32 {
+    let reduce = |damage: u32, armor: u16| match damage_type {
+        DamageType::IgnoreArmor => damage,
+        _ => damage.saturating_sub(armor as u32 * DAMAGE_SCALE),
+    };
+
+    let mut damage = damage;
+    let mut shield_damage = 0;
+    if shields > 0 {
+        damage = reduce(damage, shield_upgrade);
+        if damage <= shields {
+            return damage.max(MIN_DAMAGE);
+        }
+
+        shield_damage = shields;
+        damage -= shields;
+    }
+
+    let hit_point_damage = scale_damage(reduce(damage, armor), damage_type, size).max(MIN_DAMAGE);
+
+    shield_damage + hit_point_damage
+}
+
+/// Damage of a single hit of `weapon` against a `target` with full shields,
+/// in fixed point like [`mitigate_damage`]: 256 is one hit point.
+///
+/// `target_armor` is the total armor of the target, including upgrades.
+/// `None` if the damage type of the weapon or the size of the target is not
+/// one the game knows.
+pub fn final_damage(
+    weapon: &Weapon,
+    target: &Unit,
+    target_armor: u16,
+    shield_upgrade: u16,
+) -> Option<u32> {
+    let shields = target.shield_amount().unwrap_or(0) as u32 * DAMAGE_SCALE;
+
+    Some(mitigate_damage(
+        weapon.damage_amount() as u32 * DAMAGE_SCALE,
+        weapon.damage_type().ok()?,
+        target.size().ok()?,
+        target_armor,
+        shields,
+        shield_upgrade,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{UnitsDat, WeaponsDat};
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_deals_damage_in_whole_hit_points() {
+        // weapon 0 deals 5 concussive damage
+        let mut weapons = vec![0; 130 * 42];
+        weapons[130 * 18] = DamageType::Concussive as u8;
+        weapons[130 * 28] = 5;
+        let weapons_dat = WeaponsDat::from_bytes(&weapons).unwrap();
+        let weapon = weapons_dat.iter().next().unwrap();
+
+        // units 0, 1 and 2 are medium, small and of a size the game does not
+        // know
+        const UNIT_SIZE_OFFSET: usize = 8628;
+        let mut units = vec![0; 19876];
+        units[UNIT_SIZE_OFFSET..UNIT_SIZE_OFFSET + 3].copy_from_slice(&[2, 1, 9]);
+        let units_dat = UnitsDat::from_bytes(&units).unwrap();
+        let target = |id| units_dat.iter().nth(id).unwrap();
+
+        assert_that(&damage_against(weapon, target(0))).is_equal_to(Some(2));
+        assert_that(&damage_against(weapon, target(1))).is_equal_to(Some(5));
+        assert_that(&damage_against(weapon, target(2))).is_none();
+    }
+
+    #[test]
+    fn it_scales_explosive_damage_by_size() {
+        let explosive = |size| scale_damage(20 * DAMAGE_SCALE, DamageType::Explosive, size);
+
+        assert_that(&explosive(UnitSize::Small)).is_equal_to(10 * DAMAGE_SCALE);
+        assert_that(&explosive(UnitSize::Medium)).is_equal_to(15 * DAMAGE_SCALE);
+        assert_that(&explosive(UnitSize::Large)).is_equal_to(20 * DAMAGE_SCALE);
+    }
+
+    #[test]
+    fn it_scales_concussive_damage_by_size() {
+        let concussive = |size| scale_damage(20 * DAMAGE_SCALE, DamageType::Concussive, size);
+
+        assert_that(&concussive(UnitSize::Small)).is_equal_to(20 * DAMAGE_SCALE);
+        assert_that(&concussive(UnitSize::Medium)).is_equal_to(10 * DAMAGE_SCALE);
+        assert_that(&concussive(UnitSize::Large)).is_equal_to(5 * DAMAGE_SCALE);
+    }
+
+    #[test]
+    fn it_subtracts_armor_before_scaling() {
+        // marine against an ultralisk with 1 armor
+        assert_that(&mitigate_damage(
+            6 * DAMAGE_SCALE,
+            DamageType::Normal,
+            UnitSize::Large,
+            1,
+            0,
+            0,
+        ))
+        .is_equal_to(5 * DAMAGE_SCALE);
+
+        // firebat against an ultralisk with 1 armor
+        assert_that(&mitigate_damage(
+            8 * DAMAGE_SCALE,
+            DamageType::Concussive,
+            UnitSize::Large,
+            1,
+            0,
+            0,
+        ))
+        .is_equal_to(448);
+    }
+
+    #[test]
+    fn it_deals_at_least_half_a_hit_point() {
+        assert_that(&mitigate_damage(
+            6 * DAMAGE_SCALE,
+            DamageType::Normal,
+            UnitSize::Small,
+            10,
+            0,
+            0,
+        ))
+        .is_equal_to(MIN_DAMAGE);
+    }
+
+    #[test]
+    fn it_absorbs_damage_with_shields_first() {
+        // zealot against a zealot with full shields
+        assert_that(&mitigate_damage(
+            8 * DAMAGE_SCALE,
+            DamageType::Normal,
+            UnitSize::Small,
+            1,
+            60 * DAMAGE_SCALE,
+            0,
+        ))
+        .is_equal_to(8 * DAMAGE_SCALE);
+
+        // zealot against a zealot with 4 shields left
+        assert_that(&mitigate_damage(
+            8 * DAMAGE_SCALE,
+            DamageType::Normal,
+            UnitSize::Small,
+            1,
+            4 * DAMAGE_SCALE,
+            0,
+        ))
+        .is_equal_to(7 * DAMAGE_SCALE);
+
+        // shields ignore the damage type
+        assert_that(&mitigate_damage(
+            20 * DAMAGE_SCALE,
+            DamageType::Explosive,
+            UnitSize::Small,
+            1,
+            80 * DAMAGE_SCALE,
+            1,
+        ))
+        .is_equal_to(19 * DAMAGE_SCALE);
+    }
+}
@@ -1,18 +1,25 @@
-use amethyst::{
-    assets::Format,
-    assets::{Asset, Handle},
-    ecs::DenseVecStorage,
-};
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use boolinator::Boolinator;
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
     error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
-    Finish, IResult, Parser,
+    IResult, Parser,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+use std::io::{self, Write};
 
-#[derive(Debug)]
+use super::column::{dat_column, DatColumn};
+
+use crate::error::finish_parse;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Flingy {
     sprite: u16,
     top_speed: u32,
@@ -22,6 +29,65 @@ pub struct Flingy {
     move_control: u8,
 }
 
+impl Flingy {
+    pub fn new(
+        sprite: u16,
+        top_speed: u32,
+        acceleration: u16,
+        halt_distance: u32,
+        turn_radius: u8,
+        move_control: u8,
+    ) -> Flingy {
+        Flingy {
+            sprite,
+            top_speed,
+            acceleration,
+            halt_distance,
+            turn_radius,
+            move_control,
+        }
+    }
+
+    /// Pointer to sprites.dat
+    pub fn sprite(&self) -> u16 {
+        self.sprite
+    }
+
+    pub fn top_speed(&self) -> u32 {
+        self.top_speed
+    }
+
+    pub fn acceleration(&self) -> u16 {
+        self.acceleration
+    }
+
+    pub fn halt_distance(&self) -> u32 {
+        self.halt_distance
+    }
+
+    pub fn turn_radius(&self) -> u8 {
+        self.turn_radius
+    }
+
+    pub fn move_control(&self) -> u8 {
+        self.move_control
+    }
+
+    /// Value of a field, widened to `u32`, or `None` for
+    /// [`FlingyField::Unknown`], which is kept by [`FlingyDat::raw_unknown`].
+    pub fn field(&self, field: FlingyField) -> Option<u32> {
+        match field {
+            FlingyField::Sprite => Some(self.sprite.into()),
+            FlingyField::TopSpeed => Some(self.top_speed),
+            FlingyField::Acceleration => Some(self.acceleration.into()),
+            FlingyField::HaltDistance => Some(self.halt_distance),
+            FlingyField::TurnRadius => Some(self.turn_radius.into()),
+            FlingyField::Unknown => None,
+            FlingyField::MoveControl => Some(self.move_control.into()),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FlingyPointer(u32);
 
@@ -29,45 +95,300 @@ impl FlingyPointer {
     pub fn new(p: u32) -> FlingyPointer {
         FlingyPointer(p)
     }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FlingyDat(Vec<Flingy>, Vec<Vec<u8>>);
+
+dat_iter!(FlingyDat, Flingy);
+
+dat_unknown_columns!(FlingyDat);
+
+impl FlingyDat {
+    /// Number of entries in flingy.dat
+    pub const LEN: usize = BLOCK_SIZE;
+
+    /// Encodes the dat in the same columnar layout it is parsed from.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(BLOCK_SIZE * 15);
+
+        for flingy in &self.0 {
+            b.extend_from_slice(&flingy.sprite.to_le_bytes());
+        }
+        for flingy in &self.0 {
+            b.extend_from_slice(&flingy.top_speed.to_le_bytes());
+        }
+        for flingy in &self.0 {
+            b.extend_from_slice(&flingy.acceleration.to_le_bytes());
+        }
+        for flingy in &self.0 {
+            b.extend_from_slice(&flingy.halt_distance.to_le_bytes());
+        }
+        b.extend(self.0.iter().map(|f| f.turn_radius));
+        for column in &self.1 {
+            b.extend_from_slice(column);
+        }
+        b.extend(self.0.iter().map(|f| f.move_control));
+
+        b
+    }
+
+    /// Value of a field of the entry at `index`, widened to `u32`, including
+    /// the column of unknown purpose. `None` if there is no entry at `index`
+    /// or the dat has no column of unknown purpose.
+    pub fn field(&self, index: usize, field: FlingyField) -> Option<u32> {
+        self.0.get(index)?.field(field).or_else(|| {
+            self.raw_unknown(0)
+                .and_then(|column| column.get(index))
+                .map(|&value| value.into())
+        })
+    }
+
+    /// Pretty RON of every entry, see [`crate::snapshot`].
+    #[cfg(feature = "snapshot")]
+    pub fn to_ron(&self) -> String {
+        crate::snapshot::snapshot(self)
+    }
+
+    /// Writes every entry as a JSON object on its own line, so large modded
+    /// dats can be piped and processed one entry at a time.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn write_jsonl<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        for flingy in &self.0 {
+            serde_json::to_writer(&mut *w, flingy)?;
+            w.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Fields of a flingy, in the order their columns are stored in flingy.dat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlingyField {
+    Sprite,
+    TopSpeed,
+    Acceleration,
+    HaltDistance,
+    TurnRadius,
+    Unknown,
+    MoveControl,
+}
+
+impl FlingyField {
+    pub const ALL: [FlingyField; 7] = [
+        FlingyField::Sprite,
+        FlingyField::TopSpeed,
+        FlingyField::Acceleration,
+        FlingyField::HaltDistance,
+        FlingyField::TurnRadius,
+        FlingyField::Unknown,
+        FlingyField::MoveControl,
+    ];
+
+    /// Bytes per entry.
+    pub fn entry_size(self) -> usize {
+        match self {
+            FlingyField::Sprite | FlingyField::Acceleration => 2,
+            FlingyField::TopSpeed | FlingyField::HaltDistance => 4,
+            FlingyField::TurnRadius | FlingyField::Unknown | FlingyField::MoveControl => 1,
+        }
+    }
+
+    pub fn column(self) -> DatColumn {
+        let offset = FlingyField::ALL
+            .iter()
+            .take_while(|&&column| column != self)
+            .map(|column| column.entry_size() * BLOCK_SIZE)
+            .sum();
+
+        DatColumn::at(offset)
+    }
+
+    /// Bytes of the column in a flingy.dat file, or `None` if the file is too
+    /// short.
+    pub fn slice(self, bytes: &[u8]) -> Option<&[u8]> {
+        dat_column(bytes, self.column(), self.entry_size(), BLOCK_SIZE)
+    }
+}
+
+/// Reads a single field of the flingy at `index` straight from the bytes of a
+/// flingy.dat, without parsing the rest of the file.
+///
+/// # Panics
+///
+/// Panics if `index` is not less than [`FlingyDat::LEN`] or the file is too
+/// short to hold the field.
+pub fn read_flingy_field(bytes: &[u8], index: usize, field: FlingyField) -> u32 {
+    let mut value = [0; 4];
+    value[..field.entry_size()].copy_from_slice(&bytes[field_range(index, field)]);
+
+    u32::from_le_bytes(value)
+}
+
+/// Overwrites a single field of the flingy at `index` in the bytes of a
+/// flingy.dat, e.g. to tweak one value of a modded dat without encoding the
+/// whole file again.
+///
+/// # Panics
+///
+/// Panics if `index` is not less than [`FlingyDat::LEN`], the file is too
+/// short to hold the field or `value` does not fit in the field.
+pub fn write_flingy_field(bytes: &mut [u8], index: usize, field: FlingyField, value: u32) {
+    let size = field.entry_size();
+    assert!(
+        size == 4 || value >> (size * 8) == 0,
+        "{} does not fit in the {} bytes of {:?}",
+        value,
+        size,
+        field
+    );
+
+    bytes[field_range(index, field)].copy_from_slice(&value.to_le_bytes()[..size]);
+}
+
+/// Parses the entries of a flingy.dat one at a time, reading each field from
+/// its column as the entry is reached, e.g. to stop at the first entry that
+/// matches with [`Iterator::find`] without parsing the rest of the file.
+///
+/// Yields [`FlingyDat::LEN`] entries. An entry whose fields lie past the end
+/// of a file that is too short is an error. Unlike [`FlingyDat::from_bytes`],
+/// bytes past the end of the last column are not checked.
+pub fn iter_flingy(bytes: &[u8]) -> impl Iterator<Item = Result<Flingy, crate::ParseError>> + '_ {
+    (0..BLOCK_SIZE).map(move |index| {
+        let read = |field| {
+            let range = field_range(index, field);
+            if range.end > bytes.len() {
+                return Err(crate::ParseError::OutOfBounds {
+                    offset: range.start,
+                    len: range.len(),
+                    available: bytes.len(),
+                });
+            }
+
+            Ok(read_flingy_field(bytes, index, field))
+        };
+
+        Ok(Flingy {
+            sprite: read(FlingyField::Sprite)? as u16,
+            top_speed: read(FlingyField::TopSpeed)?,
+            acceleration: read(FlingyField::Acceleration)? as u16,
+            halt_distance: read(FlingyField::HaltDistance)?,
+            turn_radius: read(FlingyField::TurnRadius)? as u8,
+            move_control: read(FlingyField::MoveControl)? as u8,
+        })
+    })
+}
+
+/// A field that differs between two flingy.dat files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlingyFieldDiff {
+    pub field: FlingyField,
+    pub old: u32,
+    pub new: u32,
+}
+
+/// Every field that differs between the entries of two flingy.dat files, e.g.
+/// to list the changes of a balance mod against the stock dat.
+///
+/// Changes are ordered by index, then by the column order of the file. Fields
+/// that are missing from either dat are skipped.
+pub fn diff_flingy_dat(a: &FlingyDat, b: &FlingyDat) -> Vec<(usize, FlingyFieldDiff)> {
+    (0..a.0.len().min(b.0.len()))
+        .flat_map(|index| {
+            FlingyField::ALL.iter().filter_map(move |&field| {
+                let diff = FlingyFieldDiff {
+                    field,
+                    old: a.field(index, field)?,
+                    new: b.field(index, field)?,
+                };
+
+                (diff.old != diff.new).as_some((index, diff))
+            })
+        })
+        .collect()
 }
 
-pub struct FlingyDat(Vec<Flingy>);
+fn field_range(index: usize, field: FlingyField) -> std::ops::Range<usize> {
+    assert!(
+        index < BLOCK_SIZE,
+        "flingy {} is out of range of {} entries",
+        index,
+        BLOCK_SIZE
+    );
+    let start = field.column().offset() + index * field.entry_size();
 
-pub struct FlingyDatAsset(Option<FlingyDat>);
+    start..start + field.entry_size()
+}
 
-impl FlingyDatAsset {
-    pub fn take(&mut self) -> Option<FlingyDat> {
-        self.0.take()
+/// Assembles a flingy.dat in memory, e.g. to generate a modded dat file.
+///
+/// Every entry that is not set is [`Flingy::default`], and the column of
+/// unknown purpose is all zeros.
+#[derive(Debug)]
+pub struct FlingyDatBuilder {
+    flingies: Vec<Flingy>,
+}
+
+impl FlingyDatBuilder {
+    pub fn new() -> FlingyDatBuilder {
+        FlingyDatBuilder {
+            flingies: vec![Flingy::default(); BLOCK_SIZE],
+        }
+    }
+
+    /// Sets the entry at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`FlingyDat::LEN`].
+    pub fn set(&mut self, index: usize, flingy: Flingy) -> &mut Self {
+        assert!(
+            index < BLOCK_SIZE,
+            "flingy {} is out of range of {} entries",
+            index,
+            BLOCK_SIZE
+        );
+        self.flingies[index] = flingy;
+        self
+    }
+
+    pub fn build(&self) -> FlingyDat {
+        FlingyDat(self.flingies.clone(), vec![vec![0; BLOCK_SIZE]])
     }
 }
 
-pub type FlingyDatHandle = Handle<FlingyDatAsset>;
+impl Default for FlingyDatBuilder {
+    fn default() -> Self {
+        FlingyDatBuilder::new()
+    }
+}
+
+dat_asset!(FlingyDatAsset, FlingyDat, FlingyDatHandle);
 
-impl Asset for FlingyDatAsset {
-    const NAME: &'static str = "bw_assets::dat::FlingyDatAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<FlingyDatHandle>;
+impl FlingyDat {
+    pub fn from_bytes(b: &[u8]) -> Result<FlingyDat, crate::ParseError> {
+        finish_parse("flingy.dat", b, parse_flingy_dat(b))
+    }
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct FlingyDatFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<FlingyDatAsset> for FlingyDatFormat {
     fn name(&self) -> &'static str {
         "FlingyDatFormat"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<FlingyDatAsset> {
-        let (_, flingy_dat) = parse_flingy_dat(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load flingy.dat asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-
-        Ok(FlingyDatAsset(Some(flingy_dat)))
+        Ok(FlingyDatAsset(Some(FlingyDat::from_bytes(&b)?)))
     }
 }
 
@@ -89,8 +410,7 @@ fn parse_flingy_dat(b: &[u8]) -> IResult<&[u8], FlingyDat> {
     let (remaining, halt_distance_col) = count_total(le_u32)(remaining)?;
     let (remaining, turn_radius_col) = count_total(le_u8)(remaining)?;
 
-    // unknown block
-    let (remaining, _) = count_total(le_u8)(remaining)?;
+    let (remaining, unknown_col) = count_total(le_u8)(remaining)?;
 
     let (remaining, move_control_col) = count_total(le_u8)(remaining)?;
 
@@ -107,5 +427,223 @@ fn parse_flingy_dat(b: &[u8]) -> IResult<&[u8], FlingyDat> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, FlingyDat(flingies)))
+    Ok((remaining, FlingyDat(flingies, vec![unknown_col])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::column::read_u32_column;
+    use super::*;
+    use spectral::prelude::*;
+
+    const FLINGY_DAT_LEN: usize = 3135;
+
+    /// A flingy.dat whose bytes count up and wrap at a prime, so that no two
+    /// columns hold the same values.
+    fn flingy_dat_bytes() -> Vec<u8> {
+        (0..FLINGY_DAT_LEN).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn it_round_trips_through_bytes() {
+        let b = flingy_dat_bytes();
+
+        let (_, flingy_dat) = parse_flingy_dat(&b).unwrap();
+        let encoded = flingy_dat.to_bytes();
+        let (_, reparsed) = parse_flingy_dat(&encoded).unwrap();
+
+        assert_that(&encoded).is_equal_to(b);
+        assert_that(&reparsed).is_equal_to(flingy_dat);
+    }
+
+    #[test]
+    fn it_parses_entries_lazily() {
+        let b = flingy_dat_bytes();
+        let (_, flingy_dat) = parse_flingy_dat(&b).unwrap();
+
+        let flingies = iter_flingy(&b).collect::<Result<Vec<_>, _>>().unwrap();
+        let found = iter_flingy(&b).find(|flingy| flingy.as_ref().unwrap().turn_radius() == 60);
+        let expected = flingy_dat.iter().find(|flingy| flingy.turn_radius() == 60);
+
+        assert_that(&flingies).has_length(209);
+        assert_that(&flingies.iter().collect::<Vec<_>>())
+            .is_equal_to(flingy_dat.iter().collect::<Vec<_>>());
+        assert_that(&found.map(|flingy| flingy.unwrap())).is_equal_to(expected.cloned());
+        assert_that(&iter_flingy(&b[..FLINGY_DAT_LEN - 1]).last())
+            .is_some()
+            .is_err_containing(crate::ParseError::OutOfBounds {
+                offset: FLINGY_DAT_LEN - 1,
+                len: 1,
+                available: FLINGY_DAT_LEN - 1,
+            });
+    }
+
+    #[test]
+    fn it_keeps_the_column_of_unknown_purpose() {
+        let b = flingy_dat_bytes();
+
+        let (_, flingy_dat) = parse_flingy_dat(&b).unwrap();
+
+        assert_that(&flingy_dat.unknown_column_count()).is_equal_to(1);
+        assert_that(&flingy_dat.raw_unknown(0)).is_equal_to(FlingyField::Unknown.slice(&b));
+        assert_that(&flingy_dat.raw_unknown(1)).is_none();
+        let unknown = read_flingy_field(&b, 7, FlingyField::Unknown);
+        assert_that(&flingy_dat.field(7, FlingyField::Unknown)).is_equal_to(Some(unknown));
+        assert_that(&flingy_dat.field(FlingyDat::LEN, FlingyField::Unknown)).is_none();
+        assert_that(&FlingyDat(vec![Flingy::default()], vec![]).field(0, FlingyField::Unknown))
+            .is_none();
+    }
+
+    #[test]
+    fn it_diffs_the_column_of_unknown_purpose() {
+        let stock = FlingyDatBuilder::new().build();
+        let mut b = stock.to_bytes();
+        write_flingy_field(&mut b, 8, FlingyField::Unknown, 1);
+        let (_, modded) = parse_flingy_dat(&b).unwrap();
+
+        assert_that(&diff_flingy_dat(&stock, &modded)).is_equal_to(vec![(
+            8,
+            FlingyFieldDiff {
+                field: FlingyField::Unknown,
+                old: 0,
+                new: 1,
+            },
+        )]);
+    }
+
+    #[test]
+    fn it_diffs_two_dats_field_by_field() {
+        let stock = FlingyDatBuilder::new()
+            .set(3, Flingy::new(1, 1707, 107, 13616, 40, 0))
+            .build();
+        let modded = FlingyDatBuilder::new()
+            .set(3, Flingy::new(1, 2000, 107, 13616, 40, 0))
+            .build();
+
+        assert_that(&diff_flingy_dat(&stock, &stock.clone())).is_empty();
+        assert_that(&diff_flingy_dat(&stock, &modded)).is_equal_to(vec![(
+            3,
+            FlingyFieldDiff {
+                field: FlingyField::TopSpeed,
+                old: 1707,
+                new: 2000,
+            },
+        )]);
+    }
+
+    #[test]
+    fn it_clones_a_parsed_dat() {
+        let b = flingy_dat_bytes();
+        let (_, flingy_dat) = parse_flingy_dat(&b).unwrap();
+
+        let cloned = flingy_dat.clone();
+
+        assert_that(&cloned.iter().nth(10).map(Flingy::top_speed))
+            .is_equal_to(flingy_dat.iter().nth(10).map(Flingy::top_speed));
+        assert_that(&cloned).is_equal_to(flingy_dat);
+    }
+
+    #[test]
+    fn it_reads_a_single_column() {
+        let b = flingy_dat_bytes();
+        let (_, flingy_dat) = parse_flingy_dat(&b).unwrap();
+
+        let top_speed = FlingyField::TopSpeed.slice(&b).unwrap();
+        let parsed = flingy_dat.iter().map(Flingy::top_speed).collect::<Vec<_>>();
+
+        assert_that(&FlingyField::TopSpeed.column().offset()).is_equal_to(418);
+        assert_that(&read_u32_column(top_speed)).is_equal_to(parsed);
+    }
+
+    #[test]
+    fn it_edits_a_single_field_in_place() {
+        let mut b = FlingyDatBuilder::new()
+            .set(5, Flingy::new(1, 1707, 107, 13616, 40, 0))
+            .build()
+            .to_bytes();
+
+        write_flingy_field(&mut b, 5, FlingyField::TopSpeed, 2000);
+        write_flingy_field(&mut b, 5, FlingyField::TurnRadius, 20);
+        let (_, flingy_dat) = parse_flingy_dat(&b).unwrap();
+
+        assert_that(&read_flingy_field(&b, 5, FlingyField::TopSpeed)).is_equal_to(2000);
+        assert_that(&read_flingy_field(&b, 5, FlingyField::Acceleration)).is_equal_to(107);
+        assert_that(&flingy_dat.iter().nth(5))
+            .is_equal_to(Some(&Flingy::new(1, 2000, 107, 13616, 20, 0)));
+    }
+
+    #[test]
+    fn it_parses_a_dat_in_the_middle_of_a_buffer() {
+        let flingy_dat = FlingyDatBuilder::new()
+            .set(0, Flingy::new(1, 1707, 107, 13616, 40, 0))
+            .build();
+        let padded = [vec![0xff; 100], flingy_dat.to_bytes(), vec![0xff; 50]].concat();
+
+        let parsed = crate::parse_at(&padded, 100, FLINGY_DAT_LEN, FlingyDat::from_bytes);
+        let past_the_end = crate::parse_at(&padded, 200, FLINGY_DAT_LEN, FlingyDat::from_bytes);
+
+        assert_that(&parsed).is_ok_containing(flingy_dat);
+        assert_that(&past_the_end).is_err_containing(crate::ParseError::OutOfBounds {
+            offset: 200,
+            len: FLINGY_DAT_LEN,
+            available: padded.len(),
+        });
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[test]
+    fn it_matches_the_snapshot() {
+        let flingy_dat = FlingyDat(
+            vec![
+                Flingy::new(1, 1707, 107, 13616, 40, 0),
+                Flingy::new(221, 640, 27, 7585, 27, 2),
+            ],
+            vec![vec![0, 3]],
+        );
+
+        assert_that(&flingy_dat.to_ron().as_str())
+            .is_equal_to(include_str!("snapshots/flingy.ron").trim_end());
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    #[test]
+    fn it_writes_one_json_line_per_entry() {
+        let flingy_dat = FlingyDatBuilder::new()
+            .set(0, Flingy::new(1, 1707, 107, 13616, 40, 0))
+            .build();
+        let mut jsonl = Vec::new();
+
+        flingy_dat.write_jsonl(&mut jsonl).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        let first: Flingy = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+
+        assert_that(&jsonl.lines().count()).is_equal_to(FlingyDat::LEN);
+        assert_that(&first).is_equal_to(Flingy::new(1, 1707, 107, 13616, 40, 0));
+    }
+
+    #[test]
+    fn it_builds_a_dat_with_default_entries() {
+        let flingy = Flingy::new(1, 1707, 107, 13616, 40, 0);
+
+        let flingy_dat = FlingyDatBuilder::new().set(3, flingy.clone()).build();
+        let (_, reparsed) = parse_flingy_dat(&flingy_dat.to_bytes()).unwrap();
+
+        assert_that(&flingy_dat.iter().len()).is_equal_to(FlingyDat::LEN);
+        assert_that(&flingy_dat.iter().nth(3)).is_equal_to(Some(&flingy));
+        assert_that(&flingy_dat.iter().nth(4)).is_equal_to(Some(&Flingy::default()));
+        assert_that(&reparsed).is_equal_to(flingy_dat);
+    }
+
+    #[cfg(feature = "amethyst")]
+    #[test]
+    fn it_fails_to_take_an_asset_twice() {
+        use super::super::{AlreadyTakenError, DatAsset};
+
+        let mut asset = FlingyDatAsset(Some(FlingyDatBuilder::new().build()));
+
+        assert_that(&asset.try_take()).is_ok();
+        assert_that(&asset.try_take()).is_err_containing(AlreadyTakenError { dat: "FlingyDat" });
+        assert_that(&AlreadyTakenError { dat: "FlingyDat" }.to_string())
+            .is_equal_to("FlingyDat was already taken out of its asset".to_string());
+    }
 }
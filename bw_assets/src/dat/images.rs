@@ -0,0 +1,349 @@
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use nom::{
+    bytes::complete::take,
+    combinator::{all_consuming, map},
+    number::complete::{le_u32, le_u8},
+    IResult,
+};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+
+use crate::error::{finish_parse, ParseError};
+
+/// How the frames of an image are drawn.
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum DrawFunction {
+    Normal = 0,
+    NormalNoHallucination = 1,
+    NonVisionCloaking = 2,
+    NonVisionCloaked = 3,
+    NonVisionDecloaking = 4,
+    VisionCloaking = 5,
+    VisionCloaked = 6,
+    VisionDecloaking = 7,
+    EmpShockwave = 8,
+    /// Colors are shifted with the image's [`RemapTable`].
+    Remapping = 9,
+    Shadow = 10,
+    HpBar = 11,
+    WarpTexture = 12,
+    SelectionCircle = 13,
+    /// Uses the player color of the owner, e.g. flags and beacons.
+    PlayerColorOverride = 14,
+    /// Only updates the screen area of the frame without drawing it.
+    HideFrame = 15,
+    Hallucination = 16,
+    WarpFlash = 17,
+}
+
+impl TryFrom<u8> for DrawFunction {
+    type Error = u8;
+
+    /// Fails with the original value if it is not a draw function.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FromPrimitive::from_u8(value).ok_or(value)
+    }
+}
+
+/// Color shift applied to images drawn with [`DrawFunction::Remapping`].
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum RemapTable {
+    None = 0,
+    /// `ofire.pcx`
+    OrangeFire = 1,
+    /// `gfire.pcx`
+    GreenFire = 2,
+    /// `bfire.pcx`
+    BlueFire = 3,
+    /// `bexpl.pcx`
+    BlueExplosion = 4,
+    /// Cloaking of units owned by the local player.
+    OwnCloak = 5,
+}
+
+impl TryFrom<u8> for RemapTable {
+    type Error = u8;
+
+    /// Fails with the original value if it is not a remap table.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FromPrimitive::from_u8(value).ok_or(value)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Image {
+    grp_file: u32,
+    gfx_turns: bool,
+    clickable: bool,
+    use_full_iscript: bool,
+    draw_if_cloaked: bool,
+    /// Stored raw so that modded images.dat files with draw functions the
+    /// game does not know still parse, see [`Image::draw_function`].
+    draw_function: u8,
+    remapping: u8,
+    iscript: u32,
+    shield_overlay: u32,
+    attack_overlay: u32,
+    damage_overlay: u32,
+    special_overlay: u32,
+    landing_dust_overlay: u32,
+    lift_off_overlay: u32,
+}
+
+impl Image {
+    /// Index of the GRP's path in images.tbl. The index is 1-based.
+    pub fn grp_file(&self) -> u32 {
+        self.grp_file
+    }
+
+    /// Whether the GRP has frames for 17 directions that are mirrored for
+    /// the other 15.
+    pub fn gfx_turns(&self) -> bool {
+        self.gfx_turns
+    }
+
+    pub fn clickable(&self) -> bool {
+        self.clickable
+    }
+
+    pub fn use_full_iscript(&self) -> bool {
+        self.use_full_iscript
+    }
+
+    pub fn draw_if_cloaked(&self) -> bool {
+        self.draw_if_cloaked
+    }
+
+    /// Fails with the raw value if it is not a draw function.
+    pub fn draw_function(&self) -> Result<DrawFunction, u8> {
+        DrawFunction::try_from(self.draw_function)
+    }
+
+    /// Fails with the raw value if it is not a remap table.
+    pub fn remap(&self) -> Result<RemapTable, u8> {
+        RemapTable::try_from(self.remapping)
+    }
+
+    /// Entry of the image's animation script in iscript.bin
+    pub fn iscript(&self) -> u32 {
+        self.iscript
+    }
+
+    /// Indices of the overlay offsets in images.tbl, where 0 means there is no
+    /// overlay.
+    pub fn shield_overlay(&self) -> u32 {
+        self.shield_overlay
+    }
+
+    pub fn attack_overlay(&self) -> u32 {
+        self.attack_overlay
+    }
+
+    pub fn damage_overlay(&self) -> u32 {
+        self.damage_overlay
+    }
+
+    pub fn special_overlay(&self) -> u32 {
+        self.special_overlay
+    }
+
+    pub fn landing_dust_overlay(&self) -> u32 {
+        self.landing_dust_overlay
+    }
+
+    pub fn lift_off_overlay(&self) -> u32 {
+        self.lift_off_overlay
+    }
+}
+
+/// Number of directions a GRP with [`Image::gfx_turns`] has frames for, from
+/// facing up to facing down on the right side.
+pub const TURN_FRAME_COUNT: u16 = 17;
+
+/// Frame of a GRP to draw for a facing direction and whether to mirror it.
+///
+/// `direction` is the facing in 256ths of a turn, clockwise from facing up.
+/// It is rounded to the nearest of the 32 directions units can be drawn in.
+/// GRPs with [`Image::gfx_turns`] only store the 17 directions facing right,
+/// so the directions facing left mirror the frame of the direction facing
+/// right at the same angle. Images without turns always draw `base_frame`.
+pub fn frame_for_direction(base_frame: u16, direction: u8, gfx_turns: bool) -> (u16, bool) {
+    if !gfx_turns {
+        return (base_frame, false);
+    }
+
+    let direction = ((direction as u16 + 4) / 8) % 32;
+    if direction < TURN_FRAME_COUNT {
+        (base_frame + direction, false)
+    } else {
+        (base_frame + 32 - direction, true)
+    }
+}
+
+#[derive(Clone)]
+pub struct ImagesDat(Vec<Image>);
+
+dat_iter!(ImagesDat, Image);
+
+dat_asset!(ImagesDatAsset, ImagesDat, ImagesDatHandle);
+
+impl ImagesDat {
+    pub fn from_bytes(b: &[u8]) -> Result<ImagesDat, ParseError> {
+        finish_parse("images.dat", b, parse_images_dat(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImagesDatFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<ImagesDatAsset> for ImagesDatFormat {
+    fn name(&self) -> &'static str {
+        "ImagesDatFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<ImagesDatAsset> {
+        Ok(ImagesDatAsset(Some(ImagesDat::from_bytes(&b)?)))
+    }
+}
+
+const BLOCK_SIZE: usize = 999;
+
+fn parse_u8_boolean(b: &[u8]) -> IResult<&[u8], bool> {
+    map(le_u8, |x| x != 0)(b)
+}
+
+fn parse_images_dat(b: &[u8]) -> IResult<&[u8], ImagesDat> {
+    parse_images_dat_with(b, cfg!(feature = "parallel"))
+}
+
+fn parse_images_dat_with(b: &[u8], parallel: bool) -> IResult<&[u8], ImagesDat> {
+    dat_columns! {
+        b, parallel => remaining;
+        grp_file_col: [le_u32; BLOCK_SIZE] => 4,
+        gfx_turns_col: [parse_u8_boolean; BLOCK_SIZE] => 1,
+        clickable_col: [parse_u8_boolean; BLOCK_SIZE] => 1,
+        use_full_iscript_col: [parse_u8_boolean; BLOCK_SIZE] => 1,
+        draw_if_cloaked_col: [parse_u8_boolean; BLOCK_SIZE] => 1,
+        draw_function_col: [le_u8; BLOCK_SIZE] => 1,
+        remapping_col: [le_u8; BLOCK_SIZE] => 1,
+        iscript_col: [le_u32; BLOCK_SIZE] => 4,
+        shield_overlay_col: [le_u32; BLOCK_SIZE] => 4,
+        attack_overlay_col: [le_u32; BLOCK_SIZE] => 4,
+        damage_overlay_col: [le_u32; BLOCK_SIZE] => 4,
+        special_overlay_col: [le_u32; BLOCK_SIZE] => 4,
+        landing_dust_overlay_col: [le_u32; BLOCK_SIZE] => 4,
+        lift_off_overlay_col: [le_u32; BLOCK_SIZE] => 4,
+    }
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let images = (0..BLOCK_SIZE)
+        .map(|i| Image {
+            grp_file: grp_file_col[i],
+            gfx_turns: gfx_turns_col[i],
+            clickable: clickable_col[i],
+            use_full_iscript: use_full_iscript_col[i],
+            draw_if_cloaked: draw_if_cloaked_col[i],
+            draw_function: draw_function_col[i],
+            remapping: remapping_col[i],
+            iscript: iscript_col[i],
+            shield_overlay: shield_overlay_col[i],
+            attack_overlay: attack_overlay_col[i],
+            damage_overlay: damage_overlay_col[i],
+            special_overlay: special_overlay_col[i],
+            landing_dust_overlay: landing_dust_overlay_col[i],
+            lift_off_overlay: lift_off_overlay_col[i],
+        })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, ImagesDat(images)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::Finish;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_mirrors_the_frames_facing_left() {
+        let frames = (0..8)
+            .map(|i| frame_for_direction(34, i * 32, true))
+            .collect::<Vec<_>>();
+
+        assert_that(&frames).is_equal_to(vec![
+            (34, false),
+            (38, false),
+            (42, false),
+            (46, false),
+            (50, false),
+            (46, true),
+            (42, true),
+            (38, true),
+        ]);
+        assert_that(&frame_for_direction(34, 252, true)).is_equal_to((34, false));
+        assert_that(&frame_for_direction(34, 96, false)).is_equal_to((34, false));
+    }
+
+    const IMAGES_DAT_LEN: usize = 37962;
+
+    /// Offset of the draw function column, after the grp file column and four
+    /// flag columns.
+    const DRAW_FUNCTION_OFFSET: usize = BLOCK_SIZE * (4 + 4);
+
+    #[test]
+    fn it_reports_the_same_error_in_parallel_and_sequentially() {
+        let position = |b: &[u8], parallel| {
+            let err = parse_images_dat_with(b, parallel).finish().err().unwrap();
+            b.len() - err.input.len()
+        };
+        let truncated = vec![0; IMAGES_DAT_LEN - 1];
+        let too_long = vec![0; IMAGES_DAT_LEN + 1];
+
+        // the last column is cut off in the middle of its last entry
+        assert_that(&position(&truncated, true)).is_equal_to(IMAGES_DAT_LEN - 4);
+        assert_that(&position(&truncated, false)).is_equal_to(IMAGES_DAT_LEN - 4);
+        assert_that(&position(&too_long, true)).is_equal_to(IMAGES_DAT_LEN);
+        assert_that(&position(&too_long, false)).is_equal_to(IMAGES_DAT_LEN);
+    }
+
+    #[test]
+    fn it_keeps_values_the_game_does_not_know() {
+        let mut b = vec![0; IMAGES_DAT_LEN];
+        b[DRAW_FUNCTION_OFFSET + 10] = 255;
+        b[DRAW_FUNCTION_OFFSET + BLOCK_SIZE + 10] = 6;
+
+        let (_, images_dat) = parse_images_dat(&b).unwrap();
+        let image = images_dat.iter().nth(10).unwrap();
+
+        assert_that(&image.draw_function()).is_err_containing(255);
+        assert_that(&image.remap()).is_err_containing(6);
+    }
+
+    #[test]
+    fn it_decodes_a_cloaked_image() {
+        let mut b = vec![0; IMAGES_DAT_LEN];
+        b[DRAW_FUNCTION_OFFSET + 10] = 6;
+        b[DRAW_FUNCTION_OFFSET + BLOCK_SIZE + 10] = 5;
+
+        let (_, images_dat) = parse_images_dat(&b).unwrap();
+        let image = images_dat.iter().nth(10).unwrap();
+
+        assert_that(&image.draw_function()).is_ok_containing(DrawFunction::VisionCloaked);
+        assert_that(&image.remap()).is_ok_containing(RemapTable::OwnCloak);
+        assert_that(&images_dat.iter().len()).is_equal_to(BLOCK_SIZE);
+    }
+
+    #[test]
+    fn it_decodes_every_draw_function() {
+        assert_that(&DrawFunction::try_from(0)).is_ok_containing(DrawFunction::Normal);
+        assert_that(&DrawFunction::try_from(9)).is_ok_containing(DrawFunction::Remapping);
+        assert_that(&DrawFunction::try_from(17)).is_ok_containing(DrawFunction::WarpFlash);
+        assert_that(&DrawFunction::try_from(18)).is_err_containing(18);
+        assert_that(&RemapTable::try_from(6)).is_err_containing(6);
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use nom::{
+    bytes::complete::take, combinator::all_consuming, error::ParseError, multi::count,
+    number::complete::le_u32, IResult, Parser,
+};
+
+use crate::error::finish_parse;
+
+/// A map of the campaign.
+#[derive(Clone, Debug)]
+pub struct CampaignMap {
+    map_file: u32,
+}
+
+impl CampaignMap {
+    /// Index of the map's directory in mapdata.tbl, e.g.
+    /// `campaign\terran\terran01`.
+    ///
+    /// The index is 1-based; 0 means there is no map.
+    pub fn map_file(&self) -> u32 {
+        self.map_file
+    }
+}
+
+#[derive(Clone)]
+pub struct MapDataDat(Vec<CampaignMap>);
+
+dat_iter!(MapDataDat, CampaignMap);
+
+dat_asset!(MapDataDatAsset, MapDataDat, MapDataDatHandle);
+
+impl MapDataDat {
+    pub fn from_bytes(b: &[u8]) -> Result<MapDataDat, crate::ParseError> {
+        finish_parse("mapdata.dat", b, parse_mapdata_dat(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MapDataDatFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<MapDataDatAsset> for MapDataDatFormat {
+    fn name(&self) -> &'static str {
+        "MapDataDatFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<MapDataDatAsset> {
+        Ok(MapDataDatAsset(Some(MapDataDat::from_bytes(&b)?)))
+    }
+}
+
+const BLOCK_SIZE: usize = 65;
+
+pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+where
+    I: Clone + PartialEq,
+    F: Parser<I, O, E>,
+    E: ParseError<I>,
+{
+    count(f, BLOCK_SIZE)
+}
+
+fn parse_mapdata_dat(b: &[u8]) -> IResult<&[u8], MapDataDat> {
+    let (remaining, map_file_col) = count_total(le_u32)(b)?;
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let maps = map_file_col
+        .into_iter()
+        .map(|map_file| CampaignMap { map_file })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, MapDataDat(maps)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const MAPDATA_DAT_LEN: usize = BLOCK_SIZE * 4;
+
+    fn mapdata_dat_bytes() -> Vec<u8> {
+        (0..BLOCK_SIZE as u32)
+            .flat_map(|i| i.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn it_reads_the_map_of_every_entry() {
+        let mapdata_dat = MapDataDat::from_bytes(&mapdata_dat_bytes()).unwrap();
+        let map_files = mapdata_dat
+            .iter()
+            .map(CampaignMap::map_file)
+            .collect::<Vec<_>>();
+
+        assert_that(&map_files).is_equal_to((0..BLOCK_SIZE as u32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn it_rejects_a_truncated_file() {
+        let b = mapdata_dat_bytes();
+
+        assert_that(&MapDataDat::from_bytes(&b[..MAPDATA_DAT_LEN - 1]).err()).is_equal_to(Some(
+            crate::ParseError::Malformed {
+                asset: "mapdata.dat",
+                description: "End of file".to_string(),
+                position: MAPDATA_DAT_LEN - 4,
+            },
+        ));
+    }
+}
@@ -1,15 +1,244 @@
+/// An asset wrapping a parsed dat file.
+///
+/// Dat assets hand their data over to the ECS world once loaded, so the data
+/// can only be taken out of the asset a single time. Every call after the
+/// first returns `None`; use [`DatAsset::try_take`] to treat that as an
+/// error instead.
+#[cfg(feature = "amethyst")]
+pub trait DatAsset {
+    type Data;
+
+    /// Name of the dat type, used in error messages.
+    const DAT_NAME: &'static str;
+
+    fn take(&mut self) -> Option<Self::Data>;
+
+    /// Takes the data out of the asset, failing if it was already taken.
+    ///
+    /// An asset that is still loading has no entry in the asset storage, so
+    /// every asset that can be reached holds its data until it is taken.
+    fn try_take(&mut self) -> Result<Self::Data, AlreadyTakenError> {
+        self.take().ok_or(AlreadyTakenError { dat: Self::DAT_NAME })
+    }
+}
+
+/// Error returned by [`DatAsset::try_take`] when the data of an asset has
+/// already been handed out.
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlreadyTakenError {
+    pub dat: &'static str,
+}
+
+#[cfg(feature = "amethyst")]
+impl std::fmt::Display for AlreadyTakenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} was already taken out of its asset", self.dat)
+    }
+}
+
+#[cfg(feature = "amethyst")]
+impl std::error::Error for AlreadyTakenError {}
+
+/// Declares the asset wrapper and handle alias for a dat file and implements
+/// [`DatAsset`] and [`amethyst::assets::Asset`] for it. Expands to nothing
+/// without the `amethyst` feature.
+macro_rules! dat_asset {
+    ($asset:ident, $dat:ident, $handle:ident) => {
+        #[cfg(feature = "amethyst")]
+        pub struct $asset(Option<$dat>);
+
+        #[cfg(feature = "amethyst")]
+        impl $crate::dat::DatAsset for $asset {
+            type Data = $dat;
+
+            const DAT_NAME: &'static str = stringify!($dat);
+
+            fn take(&mut self) -> Option<$dat> {
+                self.0.take()
+            }
+        }
+
+        #[cfg(feature = "amethyst")]
+        pub type $handle = amethyst::assets::Handle<$asset>;
+
+        #[cfg(feature = "amethyst")]
+        impl amethyst::assets::Asset for $asset {
+            const NAME: &'static str = concat!("bw_assets::dat::", stringify!($asset));
+            type Data = Self;
+            type HandleStorage = amethyst::ecs::DenseVecStorage<$handle>;
+        }
+    };
+}
+
+/// Implements iteration over the entries of a dat file.
+macro_rules! dat_iter {
+    ($dat:ident, $entry:ident) => {
+        impl $dat {
+            pub fn iter(&self) -> std::slice::Iter<$entry> {
+                self.0.iter()
+            }
+        }
+
+        impl<'a> IntoIterator for &'a $dat {
+            type Item = &'a $entry;
+            type IntoIter = std::slice::Iter<'a, $entry>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.0.iter()
+            }
+        }
+    };
+}
+
+/// Implements access to the columns of a dat file whose purpose is unknown,
+/// which the dat keeps in its second field as they are stored.
+macro_rules! dat_unknown_columns {
+    ($dat:ident) => {
+        impl $dat {
+            /// Bytes of a column whose purpose is unknown, as they are stored,
+            /// or `None` if `column` is not less than
+            /// [`unknown_column_count`](Self::unknown_column_count). The
+            /// columns are numbered from 0 in the order they are stored.
+            pub fn raw_unknown(&self, column: usize) -> Option<&[u8]> {
+                self.1.get(column).map(Vec::as_slice)
+            }
+
+            /// Number of columns whose purpose is unknown.
+            pub fn unknown_column_count(&self) -> usize {
+                self.1.len()
+            }
+        }
+    };
+}
+
+/// Parses the columns of a dat file into a `Vec` for each column, binding
+/// every column to its name and the input after the last column to
+/// `$remaining`.
+///
+/// Each column is written as `name: [parser; entries] => width`, where width
+/// is the number of bytes the parser consumes for every entry. When
+/// `$parallel` is true and the `parallel` feature is enabled, the input is
+/// split at the column boundaries and the columns are parsed with rayon. If
+/// any column fails to parse, the columns are parsed again one after another
+/// so that errors point at the same position either way.
+macro_rules! dat_columns {
+    (
+        $b:expr, $parallel:expr => $remaining:ident;
+        $($col:ident: [$parser:expr; $entries:expr] => $width:expr,)*
+    ) => {
+        let b: &[u8] = $b;
+        let widths = [$($entries * $width),*];
+        let columns_len = widths.iter().sum::<usize>();
+
+        #[cfg(feature = "parallel")]
+        let parsed = if $parallel && b.len() >= columns_len {
+            $(let mut $col = None;)*
+            {
+                $(let $col = &mut $col;)*
+                let mut columns = $crate::dat::split_columns(b, &widths).into_iter();
+                rayon::scope(move |s| {
+                    $(
+                        let column = columns.next().expect("a slice for every column");
+                        s.spawn(move |_| {
+                            let parsed: nom::IResult<&[u8], _> = nom::combinator::all_consuming(
+                                nom::multi::count($parser, $entries),
+                            )(column);
+                            *$col = parsed.ok().map(|(_, parsed)| parsed);
+                        });
+                    )*
+                });
+            }
+
+            match ($($col,)*) {
+                ($(Some($col),)*) => Some(($($col,)*)),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        #[cfg(not(feature = "parallel"))]
+        let parsed = {
+            let _ = $parallel;
+            None
+        };
+
+        let ($($col,)* $remaining) = match parsed {
+            Some(($($col,)*)) => ($($col,)* &b[columns_len..]),
+            None => {
+                let remaining = b;
+                $(let (remaining, $col) = nom::multi::count($parser, $entries)(remaining)?;)*
+                ($($col,)* remaining)
+            }
+        };
+    };
+}
+
+mod column;
+mod damage;
 mod flingy;
+mod images;
+mod mapdata;
+mod orders;
+mod portdata;
+mod registry;
+mod sfxdata;
 mod sprites;
 mod tech_data;
 mod unit;
 mod upgrades;
 mod weapons;
 
-pub use flingy::{Flingy, FlingyDat, FlingyDatAsset, FlingyDatFormat, FlingyDatHandle};
-pub use sprites::{Sprite, SpritesDat, SpritesDatAsset, SpritesDatFormat, SpritesDatHandle};
-pub use tech_data::{
-    TechData, TechDataDat, TechDataDatAsset, TechDataDatFormat, TechDataDatHandle,
+pub use column::{dat_column, read_u16_column, read_u32_column, read_u8_column, DatColumn};
+pub use damage::{
+    damage_against, final_damage, mitigate_damage, scale_damage, DamageType, DAMAGE_SCALE,
+    MIN_DAMAGE,
+};
+pub use flingy::{
+    diff_flingy_dat, iter_flingy, read_flingy_field, write_flingy_field, Flingy, FlingyDat,
+    FlingyDatBuilder, FlingyField, FlingyFieldDiff,
 };
-pub use unit::{Unit, UnitPointer, UnitsDat, UnitsDatAsset, UnitsDatFormat, UnitsDatHandle};
-pub use upgrades::{Upgrade, UpgradesDat, UpgradesDatAsset, UpgradesDatFormat, UpgradesDatHandle};
-pub use weapons::{Weapon, WeaponsDat, WeaponsDatAsset, WeaponsDatFormat, WeaponsDatHandle};
+pub use images::{
+    frame_for_direction, DrawFunction, Image, ImagesDat, RemapTable, TURN_FRAME_COUNT,
+};
+pub use mapdata::{CampaignMap, MapDataDat};
+pub use orders::{Order, OrderPointer, OrderTargeting, OrdersDat};
+pub use portdata::{PortDataDat, Portrait, PortraitVideo};
+pub use registry::{DatRefError, DatRegistry};
+pub use sfxdata::{SfxDataDat, Sound};
+pub use sprites::{Sprite, SpritesDat};
+pub use tech_data::{TechData, TechDataDat};
+pub use unit::{Unit, UnitDimensions, UnitPointer, UnitSize, UnitsDat};
+pub use upgrades::{Upgrade, UpgradesDat};
+pub use weapons::{BehaviorKind, TargetFlags, Weapon, WeaponPointer, WeaponsDat};
+
+#[cfg(feature = "amethyst")]
+pub use self::{
+    flingy::{FlingyDatAsset, FlingyDatFormat, FlingyDatHandle},
+    images::{ImagesDatAsset, ImagesDatFormat, ImagesDatHandle},
+    mapdata::{MapDataDatAsset, MapDataDatFormat, MapDataDatHandle},
+    orders::{OrdersDatAsset, OrdersDatFormat, OrdersDatHandle},
+    portdata::{PortDataDatAsset, PortDataDatFormat, PortDataDatHandle},
+    sfxdata::{SfxDataDatAsset, SfxDataDatFormat, SfxDataDatHandle},
+    sprites::{SpritesDatAsset, SpritesDatFormat, SpritesDatHandle},
+    tech_data::{TechDataDatAsset, TechDataDatFormat, TechDataDatHandle},
+    unit::{UnitsDatAsset, UnitsDatFormat, UnitsDatHandle},
+    upgrades::{UpgradesDatAsset, UpgradesDatFormat, UpgradesDatHandle},
+    weapons::{WeaponsDatAsset, WeaponsDatFormat, WeaponsDatHandle},
+};
+
+/// Splits the start of a dat file into consecutive columns of `widths` bytes.
+/// `b` must be at least as long as all of the columns together.
+#[cfg(feature = "parallel")]
+pub(crate) fn split_columns<'a>(b: &'a [u8], widths: &[usize]) -> Vec<&'a [u8]> {
+    let mut rest = b;
+
+    widths
+        .iter()
+        .map(|&width| {
+            let (column, next) = rest.split_at(width);
+            rest = next;
+            column
+        })
+        .collect()
+}
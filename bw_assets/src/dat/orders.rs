@@ -0,0 +1,396 @@
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use boolinator::Boolinator;
+use nom::{
+    bytes::complete::take,
+    combinator::all_consuming,
+    error::ParseError,
+    multi::count,
+    number::complete::{le_u8, le_u16},
+    IResult, Parser,
+};
+
+use super::weapons::WeaponPointer;
+
+use crate::error::finish_parse;
+
+#[derive(Clone, Debug)]
+pub struct OrderPointer(u8);
+
+impl OrderPointer {
+    pub fn new(p: u8) -> OrderPointer {
+        OrderPointer(p)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// How an order picks its targets.
+#[derive(Clone, Debug)]
+pub enum OrderTargeting {
+    /// Targets are restricted by the target flags of the weapon.
+    Weapon(WeaponPointer),
+
+    /// Targets are not restricted by a weapon.
+    Any,
+}
+
+#[derive(Clone, Debug)]
+pub struct Order {
+    /// Index of the order's name in stat_txt.tbl.
+    label: u16,
+
+    /// Targets are chosen with the target flags of `weapon`.
+    use_weapon_targeting: bool,
+
+    /// Order runs alongside the unit's main order. Unused by Brood War.
+    is_secondary: bool,
+
+    /// Order can be given to units that are not subunits.
+    non_subunit: bool,
+
+    /// Subunits take over the order from their parent unit.
+    subunit_inheritance: bool,
+
+    /// Order can be given to subunits.
+    subunit_can_use: bool,
+
+    /// Order can be interrupted by another order.
+    is_interruptible: bool,
+
+    /// Unit stops moving once the order is given.
+    stop_moving: bool,
+
+    /// Order can be queued with shift.
+    is_queueable: bool,
+
+    /// Unit keeps the order while disabled.
+    keep_while_disabled: bool,
+
+    /// Order can be blocked by an obstruction.
+    is_obstructable: bool,
+
+    /// Unit flees from its target. Unknown behavior in Brood War.
+    flee: bool,
+
+    /// Order can only be given to units that can move.
+    requires_movable: bool,
+
+    weapon: WeaponPointer,
+
+    /// Pointer to techdata.dat that the energy cost is taken from.
+    tech: u8,
+
+    /// Script animation played when the order starts.
+    animation: u8,
+
+    /// Icon highlighted in the command card while the order is active.
+    highlight: u16,
+
+    /// Pointer to the order requirements in the requirements table.
+    requirements: u16,
+
+    /// Order shown to other players while the unit is hidden by the fog of
+    /// war.
+    obscured: OrderPointer,
+}
+
+impl Order {
+    /// Value of `weapon` when the order does not use a weapon.
+    const NO_WEAPON: u8 = 130;
+
+    /// Value of `tech` when the order does not use energy.
+    const NO_TECH: u8 = 44;
+
+    /// Value of `obscured` when the order is not obscured.
+    const NO_ORDER: u8 = BLOCK_SIZE as u8;
+
+    /// Value of `highlight` when the order has no icon.
+    const NO_HIGHLIGHT: u16 = 0xffff;
+
+    pub fn label(&self) -> u16 {
+        self.label
+    }
+
+    pub fn weapon(&self) -> Option<&WeaponPointer> {
+        (self.weapon.0 != Order::NO_WEAPON).as_some(&self.weapon)
+    }
+
+    pub fn targeting(&self) -> OrderTargeting {
+        match self.weapon() {
+            Some(weapon) if self.use_weapon_targeting => OrderTargeting::Weapon(weapon.clone()),
+            _ => OrderTargeting::Any,
+        }
+    }
+
+    pub fn tech(&self) -> Option<u8> {
+        (self.tech != Order::NO_TECH).as_some(self.tech)
+    }
+
+    pub fn animation(&self) -> u8 {
+        self.animation
+    }
+
+    pub fn highlight(&self) -> Option<u16> {
+        (self.highlight != Order::NO_HIGHLIGHT).as_some(self.highlight)
+    }
+
+    pub fn requirements(&self) -> u16 {
+        self.requirements
+    }
+
+    pub fn obscured(&self) -> Option<&OrderPointer> {
+        (self.obscured.0 != Order::NO_ORDER).as_some(&self.obscured)
+    }
+
+    pub fn is_secondary(&self) -> bool {
+        self.is_secondary
+    }
+
+    pub fn non_subunit(&self) -> bool {
+        self.non_subunit
+    }
+
+    pub fn subunit_inheritance(&self) -> bool {
+        self.subunit_inheritance
+    }
+
+    pub fn subunit_can_use(&self) -> bool {
+        self.subunit_can_use
+    }
+
+    pub fn is_interruptible(&self) -> bool {
+        self.is_interruptible
+    }
+
+    pub fn stop_moving(&self) -> bool {
+        self.stop_moving
+    }
+
+    pub fn is_queueable(&self) -> bool {
+        self.is_queueable
+    }
+
+    pub fn keep_while_disabled(&self) -> bool {
+        self.keep_while_disabled
+    }
+
+    pub fn is_obstructable(&self) -> bool {
+        self.is_obstructable
+    }
+
+    pub fn flee(&self) -> bool {
+        self.flee
+    }
+
+    pub fn requires_movable(&self) -> bool {
+        self.requires_movable
+    }
+}
+
+#[derive(Clone)]
+pub struct OrdersDat(Vec<Order>);
+
+dat_iter!(OrdersDat, Order);
+
+dat_asset!(OrdersDatAsset, OrdersDat, OrdersDatHandle);
+
+impl OrdersDat {
+    pub fn from_bytes(b: &[u8]) -> Result<OrdersDat, crate::ParseError> {
+        finish_parse("orders.dat", b, parse_orders_dat(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrdersDatFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<OrdersDatAsset> for OrdersDatFormat {
+    fn name(&self) -> &'static str {
+        "OrdersDatFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<OrdersDatAsset> {
+        Ok(OrdersDatAsset(Some(OrdersDat::from_bytes(&b)?)))
+    }
+}
+
+const BLOCK_SIZE: usize = 189;
+
+pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+where
+    I: Clone + PartialEq,
+    F: Parser<I, O, E>,
+    E: ParseError<I>,
+{
+    count(f, BLOCK_SIZE)
+}
+
+fn parse_flag(b: &[u8]) -> IResult<&[u8], bool> {
+    let (remaining, flag) = le_u8(b)?;
+    Ok((remaining, flag != 0))
+}
+
+fn parse_orders_dat(b: &[u8]) -> IResult<&[u8], OrdersDat> {
+    let (remaining, label_col) = count_total(le_u16)(b)?;
+    let (remaining, use_weapon_targeting_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, is_secondary_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, non_subunit_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, subunit_inheritance_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, subunit_can_use_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, is_interruptible_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, stop_moving_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, is_queueable_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, keep_while_disabled_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, is_obstructable_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, flee_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, requires_movable_col) = count_total(parse_flag)(remaining)?;
+    let (remaining, weapon_col) = count_total(le_u8)(remaining)?;
+    let (remaining, tech_col) = count_total(le_u8)(remaining)?;
+    let (remaining, animation_col) = count_total(le_u8)(remaining)?;
+    let (remaining, highlight_col) = count_total(le_u16)(remaining)?;
+    let (remaining, requirements_col) = count_total(le_u16)(remaining)?;
+    let (remaining, obscured_col) = count_total(le_u8)(remaining)?;
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let orders = (0..BLOCK_SIZE)
+        .map(|i| Order {
+            label: label_col[i],
+            use_weapon_targeting: use_weapon_targeting_col[i],
+            is_secondary: is_secondary_col[i],
+            non_subunit: non_subunit_col[i],
+            subunit_inheritance: subunit_inheritance_col[i],
+            subunit_can_use: subunit_can_use_col[i],
+            is_interruptible: is_interruptible_col[i],
+            stop_moving: stop_moving_col[i],
+            is_queueable: is_queueable_col[i],
+            keep_while_disabled: keep_while_disabled_col[i],
+            is_obstructable: is_obstructable_col[i],
+            flee: flee_col[i],
+            requires_movable: requires_movable_col[i],
+            weapon: WeaponPointer::new(weapon_col[i]),
+            tech: tech_col[i],
+            animation: animation_col[i],
+            highlight: highlight_col[i],
+            requirements: requirements_col[i],
+            obscured: OrderPointer::new(obscured_col[i]),
+        })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, OrdersDat(orders)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const ORDERS_DAT_LEN: usize = BLOCK_SIZE * 22;
+
+    fn u8_column(entry: usize, value: u8) -> Vec<u8> {
+        let mut column = vec![0; BLOCK_SIZE];
+        column[entry] = value;
+        column
+    }
+
+    fn u16_column(entry: usize, value: u16) -> Vec<u8> {
+        let mut column = vec![0; BLOCK_SIZE * 2];
+        column[entry * 2..entry * 2 + 2].copy_from_slice(&value.to_le_bytes());
+        column
+    }
+
+    /// An orders.dat where order `j` has only the `j`th of the 12 flags set
+    /// and order 20 has a distinct value in every other column.
+    fn orders_dat_bytes() -> Vec<u8> {
+        let mut columns = vec![u16_column(20, 1337)];
+        columns.extend((0..12).map(|j| u8_column(j, 1)));
+        columns.extend(vec![
+            u8_column(20, 5),
+            u8_column(20, 7),
+            u8_column(20, 9),
+            u16_column(20, 23),
+            u16_column(20, 0x1234),
+            u8_column(20, 3),
+        ]);
+
+        columns.concat()
+    }
+
+    #[test]
+    fn it_parses_every_column_of_an_order() {
+        let b = orders_dat_bytes();
+        let orders_dat = OrdersDat::from_bytes(&b).unwrap();
+        let order = orders_dat.iter().nth(20).unwrap();
+
+        assert_that(&b.len()).is_equal_to(ORDERS_DAT_LEN);
+        assert_that(&orders_dat.iter().count()).is_equal_to(BLOCK_SIZE);
+        assert_that(&order.label()).is_equal_to(1337);
+        assert_that(&order.weapon().map(WeaponPointer::index)).is_equal_to(Some(5));
+        assert_that(&order.tech()).is_equal_to(Some(7));
+        assert_that(&order.animation()).is_equal_to(9);
+        assert_that(&order.highlight()).is_equal_to(Some(23));
+        assert_that(&order.requirements()).is_equal_to(0x1234);
+        assert_that(&order.obscured().map(OrderPointer::index)).is_equal_to(Some(3));
+    }
+
+    #[test]
+    fn it_parses_the_flags_in_order() {
+        let orders_dat = OrdersDat::from_bytes(&orders_dat_bytes()).unwrap();
+        let orders = orders_dat.iter().collect::<Vec<_>>();
+        let flags = |order: &Order| {
+            vec![
+                order.is_secondary(),
+                order.non_subunit(),
+                order.subunit_inheritance(),
+                order.subunit_can_use(),
+                order.is_interruptible(),
+                order.stop_moving(),
+                order.is_queueable(),
+                order.keep_while_disabled(),
+                order.is_obstructable(),
+                order.flee(),
+                order.requires_movable(),
+            ]
+        };
+
+        let weapon_targeting =
+            |order: &Order| matches!(order.targeting(), OrderTargeting::Weapon(_));
+        assert_that(&weapon_targeting(orders[0])).is_true();
+        assert_that(&weapon_targeting(orders[1])).is_false();
+        assert_that(&flags(orders[0])).is_equal_to(vec![false; 11]);
+        for (j, order) in orders_dat.iter().enumerate().take(12).skip(1) {
+            let mut expected = vec![false; 11];
+            expected[j - 1] = true;
+
+            assert_that(&flags(order))
+                .named(&format!("flags of order {}", j))
+                .is_equal_to(expected);
+        }
+    }
+
+    #[test]
+    fn it_rejects_files_of_the_wrong_length() {
+        let b = orders_dat_bytes();
+        let mut long = b.clone();
+        long.push(0);
+
+        assert_that(&OrdersDat::from_bytes(&b[..ORDERS_DAT_LEN - 1]).err()).is_equal_to(Some(
+            crate::ParseError::Malformed {
+                asset: "orders.dat",
+                description: "End of file".to_string(),
+                position: ORDERS_DAT_LEN - 1,
+            },
+        ));
+        assert_that(&OrdersDat::from_bytes(&long).err()).is_equal_to(Some(
+            crate::ParseError::Malformed {
+                asset: "orders.dat",
+                description: "End of file".to_string(),
+                position: ORDERS_DAT_LEN,
+            },
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use nom::{
+    bytes::complete::take,
+    combinator::all_consuming,
+    error::ParseError,
+    multi::count,
+    number::complete::{le_u8, le_u32},
+    IResult, Parser,
+};
+
+use crate::error::finish_parse;
+
+/// One of the two videos of a portrait.
+#[derive(Clone, Debug)]
+pub struct PortraitVideo {
+    file: u32,
+    smk_change: u8,
+    unknown: u8,
+}
+
+impl PortraitVideo {
+    /// Index of the video's directory in portdata.tbl.
+    ///
+    /// The index is 1-based; 0 means the portrait has no video.
+    pub fn file(&self) -> u32 {
+        self.file
+    }
+
+    /// How often the portrait switches between its SMK videos.
+    pub fn smk_change(&self) -> u8 {
+        self.smk_change
+    }
+
+    pub fn unknown(&self) -> u8 {
+        self.unknown
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Portrait {
+    /// Video played while the unit is selected.
+    idle: PortraitVideo,
+
+    /// Video played while the unit is speaking.
+    talking: PortraitVideo,
+}
+
+impl Portrait {
+    pub fn idle(&self) -> &PortraitVideo {
+        &self.idle
+    }
+
+    pub fn talking(&self) -> &PortraitVideo {
+        &self.talking
+    }
+
+    pub fn idle_file(&self) -> u32 {
+        self.idle.file
+    }
+
+    pub fn talking_file(&self) -> u32 {
+        self.talking.file
+    }
+}
+
+#[derive(Clone)]
+pub struct PortDataDat(Vec<Portrait>);
+
+dat_iter!(PortDataDat, Portrait);
+
+dat_asset!(PortDataDatAsset, PortDataDat, PortDataDatHandle);
+
+impl PortDataDat {
+    pub fn from_bytes(b: &[u8]) -> Result<PortDataDat, crate::ParseError> {
+        finish_parse("portdata.dat", b, parse_portdata_dat(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PortDataDatFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<PortDataDatAsset> for PortDataDatFormat {
+    fn name(&self) -> &'static str {
+        "PortDataDatFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<PortDataDatAsset> {
+        Ok(PortDataDatAsset(Some(PortDataDat::from_bytes(&b)?)))
+    }
+}
+
+const PORTRAIT_COUNT: usize = 110;
+
+/// Every column has the idle videos of all portraits followed by the talking
+/// videos of all portraits.
+const BLOCK_SIZE: usize = PORTRAIT_COUNT * 2;
+
+pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+where
+    I: Clone + PartialEq,
+    F: Parser<I, O, E>,
+    E: ParseError<I>,
+{
+    count(f, BLOCK_SIZE)
+}
+
+fn parse_portdata_dat(b: &[u8]) -> IResult<&[u8], PortDataDat> {
+    let (remaining, file_col) = count_total(le_u32)(b)?;
+    let (remaining, smk_change_col) = count_total(le_u8)(remaining)?;
+    let (remaining, unknown_col) = count_total(le_u8)(remaining)?;
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let video = |i: usize| PortraitVideo {
+        file: file_col[i],
+        smk_change: smk_change_col[i],
+        unknown: unknown_col[i],
+    };
+
+    let portraits = (0..PORTRAIT_COUNT)
+        .map(|i| Portrait {
+            idle: video(i),
+            talking: video(PORTRAIT_COUNT + i),
+        })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, PortDataDat(portraits)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const PORTDATA_DAT_LEN: usize = BLOCK_SIZE * 6;
+
+    /// A portdata.dat where portrait 3 has idle video 7 and talking video 8.
+    fn portdata_dat_bytes() -> Vec<u8> {
+        let mut file = vec![0; BLOCK_SIZE * 4];
+        let mut smk_change = vec![0; BLOCK_SIZE];
+        let mut unknown = vec![0; BLOCK_SIZE];
+
+        file[3 * 4..3 * 4 + 4].copy_from_slice(&7u32.to_le_bytes());
+        file[(PORTRAIT_COUNT + 3) * 4..(PORTRAIT_COUNT + 3) * 4 + 4]
+            .copy_from_slice(&8u32.to_le_bytes());
+        smk_change[3] = 2;
+        smk_change[PORTRAIT_COUNT + 3] = 5;
+        unknown[PORTRAIT_COUNT + 3] = 1;
+
+        [file, smk_change, unknown].concat()
+    }
+
+    #[test]
+    fn it_pairs_the_idle_and_talking_videos_of_a_portrait() {
+        let portdata_dat = PortDataDat::from_bytes(&portdata_dat_bytes()).unwrap();
+        let portrait = portdata_dat.iter().nth(3).unwrap();
+
+        assert_that(&portdata_dat.iter().count()).is_equal_to(PORTRAIT_COUNT);
+        assert_that(&portrait.idle_file()).is_equal_to(7);
+        assert_that(&portrait.idle().smk_change()).is_equal_to(2);
+        assert_that(&portrait.idle().unknown()).is_equal_to(0);
+        assert_that(&portrait.talking_file()).is_equal_to(8);
+        assert_that(&portrait.talking().smk_change()).is_equal_to(5);
+        assert_that(&portrait.talking().unknown()).is_equal_to(1);
+    }
+
+    #[test]
+    fn it_rejects_a_truncated_file() {
+        let b = portdata_dat_bytes();
+
+        assert_that(&PortDataDat::from_bytes(&b[..PORTDATA_DAT_LEN - 1]).err()).is_equal_to(Some(
+            crate::ParseError::Malformed {
+                asset: "portdata.dat",
+                description: "End of file".to_string(),
+                position: PORTDATA_DAT_LEN - 1,
+            },
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use snafu::Snafu;
+
+use super::{
+    FlingyDat, ImagesDat, OrdersDat, SpritesDat, TechDataDat, UnitsDat, UpgradesDat, WeaponsDat,
+};
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum DatRefError {
+    #[snafu(display(
+        "{}[{}].{} points to entry {} of {}, which only has {} entries",
+        dat,
+        index,
+        field,
+        value,
+        target,
+        len
+    ))]
+    OutOfRange {
+        dat: &'static str,
+        index: usize,
+        field: &'static str,
+        target: &'static str,
+        value: usize,
+        len: usize,
+    },
+}
+
+/// A complete set of dat files.
+pub struct DatRegistry {
+    pub units: UnitsDat,
+    pub flingy: FlingyDat,
+    pub sprites: SpritesDat,
+    pub images: ImagesDat,
+    pub weapons: WeaponsDat,
+    pub upgrades: UpgradesDat,
+    pub tech_data: TechDataDat,
+    pub orders: OrdersDat,
+}
+
+impl DatRegistry {
+    /// Checks that every pointer from one dat into another is within the
+    /// bounds of the dat it points to.
+    ///
+    /// Every broken pointer is reported, not just the first one.
+    pub fn validate(&self) -> Result<(), Vec<DatRefError>> {
+        let mut errors = vec![];
+        let mut check = |dat, index, field, target, value, len| {
+            if value >= len {
+                errors.push(DatRefError::OutOfRange {
+                    dat,
+                    index,
+                    field,
+                    target,
+                    value,
+                    len,
+                });
+            }
+        };
+
+        let units_len = self.units.iter().len();
+        let flingy_len = self.flingy.iter().len();
+        let sprites_len = self.sprites.iter().len();
+        let images_len = self.images.iter().len();
+        let weapons_len = self.weapons.iter().len();
+        let upgrades_len = self.upgrades.iter().len();
+        let tech_data_len = self.tech_data.iter().len();
+        let orders_len = self.orders.iter().len();
+
+        for (i, unit) in self.units.iter().enumerate() {
+            let graphics = unit.graphics() as usize;
+            check("units", i, "graphics", "flingy", graphics, flingy_len);
+            if let Some(subunit) = unit.subunit() {
+                check("units", i, "subunit", "units", subunit.index(), units_len);
+            }
+            if let Some(infestation) = unit.infestation() {
+                let infestation = infestation.index();
+                check("units", i, "infestation", "units", infestation, units_len);
+            }
+            if let Some(weapon) = unit.ground_weapon() {
+                let weapon = weapon.index();
+                check("units", i, "ground_weapon", "weapons", weapon, weapons_len);
+            }
+            if let Some(weapon) = unit.air_weapon() {
+                let weapon = weapon.index();
+                check("units", i, "air_weapon", "weapons", weapon, weapons_len);
+            }
+            let upgrade = unit.armour_upgrade() as usize;
+            check(
+                "units",
+                i,
+                "armour_upgrade",
+                "upgrades",
+                upgrade,
+                upgrades_len,
+            );
+        }
+
+        for (i, weapon) in self.weapons.iter().enumerate() {
+            let graphics = weapon.graphics().index();
+            check("weapons", i, "graphics", "flingy", graphics, flingy_len);
+            let upgrade = weapon.damage_upgrade() as usize;
+            check(
+                "weapons",
+                i,
+                "damage_upgrade",
+                "upgrades",
+                upgrade,
+                upgrades_len,
+            );
+        }
+
+        for (i, flingy) in self.flingy.iter().enumerate() {
+            let sprite = flingy.sprite() as usize;
+            check("flingy", i, "sprite", "sprites", sprite, sprites_len);
+        }
+
+        for (i, sprite) in self.sprites.iter().enumerate() {
+            let image = sprite.image_file() as usize;
+            check("sprites", i, "image_file", "images", image, images_len);
+        }
+
+        for (i, order) in self.orders.iter().enumerate() {
+            if let Some(weapon) = order.weapon() {
+                check(
+                    "orders",
+                    i,
+                    "weapon",
+                    "weapons",
+                    weapon.index(),
+                    weapons_len,
+                );
+            }
+            if let Some(tech) = order.tech() {
+                check(
+                    "orders",
+                    i,
+                    "tech",
+                    "techdata",
+                    tech as usize,
+                    tech_data_len,
+                );
+            }
+            if let Some(obscured) = order.obscured() {
+                check(
+                    "orders",
+                    i,
+                    "obscured",
+                    "orders",
+                    obscured.index(),
+                    orders_len,
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn registry(flingy: Vec<u8>) -> DatRegistry {
+        DatRegistry {
+            units: UnitsDat::from_bytes(&vec![0; 19876]).unwrap(),
+            flingy: FlingyDat::from_bytes(&flingy).unwrap(),
+            sprites: SpritesDat::from_bytes(&vec![0; 3229]).unwrap(),
+            images: ImagesDat::from_bytes(&vec![0; 37962]).unwrap(),
+            weapons: WeaponsDat::from_bytes(&vec![0; 5460]).unwrap(),
+            upgrades: UpgradesDat::from_bytes(&vec![0; 1281]).unwrap(),
+            tech_data: TechDataDat::from_bytes(&vec![0; 836]).unwrap(),
+            orders: OrdersDat::from_bytes(&vec![0; 4158]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn it_validates_consistent_dats() {
+        assert_that(&registry(vec![0; 3135]).validate()).is_ok();
+    }
+
+    #[test]
+    fn it_collects_every_broken_reference() {
+        let mut flingy = vec![0; 3135];
+        // sprite column of the first two flingies
+        flingy[0..4].copy_from_slice(&[0xff, 0xff, 0x05, 0x02]);
+
+        let errors = registry(flingy).validate().unwrap_err();
+
+        assert_that(&errors).has_length(2);
+        assert_that(&errors[0]).is_equal_to(DatRefError::OutOfRange {
+            dat: "flingy",
+            index: 0,
+            field: "sprite",
+            target: "sprites",
+            value: 0xffff,
+            len: 517,
+        });
+        assert_that(&errors[1].to_string()).is_equal_to(
+            "flingy[1].sprite points to entry 517 of sprites, which only has 517 entries"
+                .to_string(),
+        );
+    }
+}
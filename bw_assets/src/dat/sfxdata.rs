@@ -0,0 +1,210 @@
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use nom::{
+    bytes::complete::take,
+    combinator::all_consuming,
+    error::ParseError,
+    multi::count,
+    number::complete::{le_u8, le_u16, le_u32},
+    IResult, Parser,
+};
+
+use crate::error::finish_parse;
+
+#[derive(Clone, Debug)]
+pub struct Sound {
+    sound_file: u32,
+    priority: u8,
+    flags: u8,
+    race: u16,
+    min_volume: u8,
+}
+
+impl Sound {
+    const PRELOAD: u8 = 0x01;
+    const UNIT_SPEECH: u8 = 0x02;
+    const ONE_AT_A_TIME: u8 = 0x10;
+    const NEVER_PREEMPT: u8 = 0x20;
+
+    /// Index of the sound's file path in sfxdata.tbl.
+    ///
+    /// The index is 1-based; 0 means the sound has no file.
+    pub fn sound_file(&self) -> u32 {
+        self.sound_file
+    }
+
+    /// Priority of the sound when the sound queue is full. Sounds with a
+    /// lower priority are dropped first.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn race(&self) -> u16 {
+        self.race
+    }
+
+    /// Minimum volume (0-100) of the sound, regardless of the distance to the
+    /// screen. Brood War does not store a maximum volume.
+    pub fn min_volume(&self) -> u8 {
+        self.min_volume
+    }
+
+    /// The sound is loaded into memory when the game starts.
+    pub fn is_preloaded(&self) -> bool {
+        self.flags & Sound::PRELOAD != 0
+    }
+
+    /// The sound is spoken by a unit, e.g. an acknowledgement.
+    pub fn is_unit_speech(&self) -> bool {
+        self.flags & Sound::UNIT_SPEECH != 0
+    }
+
+    /// Only one instance of the sound may play at a time.
+    pub fn is_unstackable(&self) -> bool {
+        self.flags & Sound::ONE_AT_A_TIME != 0
+    }
+
+    /// The sound is never interrupted by a sound with a higher priority.
+    pub fn is_never_preempted(&self) -> bool {
+        self.flags & Sound::NEVER_PREEMPT != 0
+    }
+}
+
+#[derive(Clone)]
+pub struct SfxDataDat(Vec<Sound>);
+
+dat_iter!(SfxDataDat, Sound);
+
+dat_asset!(SfxDataDatAsset, SfxDataDat, SfxDataDatHandle);
+
+impl SfxDataDat {
+    pub fn from_bytes(b: &[u8]) -> Result<SfxDataDat, crate::ParseError> {
+        finish_parse("sfxdata.dat", b, parse_sfxdata_dat(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SfxDataDatFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<SfxDataDatAsset> for SfxDataDatFormat {
+    fn name(&self) -> &'static str {
+        "SfxDataDatFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<SfxDataDatAsset> {
+        Ok(SfxDataDatAsset(Some(SfxDataDat::from_bytes(&b)?)))
+    }
+}
+
+const BLOCK_SIZE: usize = 1144;
+
+pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
+where
+    I: Clone + PartialEq,
+    F: Parser<I, O, E>,
+    E: ParseError<I>,
+{
+    count(f, BLOCK_SIZE)
+}
+
+fn parse_sfxdata_dat(b: &[u8]) -> IResult<&[u8], SfxDataDat> {
+    let (remaining, sound_file_col) = count_total(le_u32)(b)?;
+    let (remaining, priority_col) = count_total(le_u8)(remaining)?;
+    let (remaining, flags_col) = count_total(le_u8)(remaining)?;
+    let (remaining, race_col) = count_total(le_u16)(remaining)?;
+    let (remaining, min_volume_col) = count_total(le_u8)(remaining)?;
+
+    all_consuming(take(0u8))(remaining)?;
+
+    let sounds = (0..BLOCK_SIZE)
+        .map(|i| Sound {
+            sound_file: sound_file_col[i],
+            priority: priority_col[i],
+            flags: flags_col[i],
+            race: race_col[i],
+            min_volume: min_volume_col[i],
+        })
+        .collect::<Vec<_>>();
+
+    Ok((remaining, SfxDataDat(sounds)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const SFXDATA_DAT_LEN: usize = BLOCK_SIZE * 9;
+
+    /// A sfxdata.dat whose sound 5 is a preloaded, unstackable zerg sound.
+    fn sfxdata_dat_bytes() -> Vec<u8> {
+        let mut sound_file = vec![0; BLOCK_SIZE * 4];
+        let mut priority = vec![0; BLOCK_SIZE];
+        let mut flags = vec![0; BLOCK_SIZE];
+        let mut race = vec![0; BLOCK_SIZE * 2];
+        let mut min_volume = vec![0; BLOCK_SIZE];
+
+        sound_file[5 * 4..5 * 4 + 4].copy_from_slice(&42u32.to_le_bytes());
+        priority[5] = 3;
+        flags[5] = Sound::PRELOAD | Sound::ONE_AT_A_TIME;
+        race[5 * 2..5 * 2 + 2].copy_from_slice(&2u16.to_le_bytes());
+        min_volume[5] = 50;
+
+        [sound_file, priority, flags, race, min_volume].concat()
+    }
+
+    #[test]
+    fn it_parses_every_column_of_a_sound() {
+        let sfxdata_dat = SfxDataDat::from_bytes(&sfxdata_dat_bytes()).unwrap();
+        let sound = sfxdata_dat.iter().nth(5).unwrap();
+
+        assert_that(&sfxdata_dat.iter().count()).is_equal_to(BLOCK_SIZE);
+        assert_that(&sound.sound_file()).is_equal_to(42);
+        assert_that(&sound.priority()).is_equal_to(3);
+        assert_that(&sound.race()).is_equal_to(2);
+        assert_that(&sound.min_volume()).is_equal_to(50);
+    }
+
+    #[test]
+    fn it_decodes_the_flags() {
+        let sfxdata_dat = SfxDataDat::from_bytes(&sfxdata_dat_bytes()).unwrap();
+        let sound = sfxdata_dat.iter().nth(5).unwrap();
+        let silent = sfxdata_dat.iter().next().unwrap();
+
+        assert_that(&sound.flags()).is_equal_to(0x11);
+        assert_that(&sound.is_preloaded()).is_true();
+        assert_that(&sound.is_unit_speech()).is_false();
+        assert_that(&sound.is_unstackable()).is_true();
+        assert_that(&sound.is_never_preempted()).is_false();
+        assert_that(&silent.is_preloaded()).is_false();
+        assert_that(&silent.is_unstackable()).is_false();
+    }
+
+    #[test]
+    fn it_rejects_files_of_the_wrong_length() {
+        let b = sfxdata_dat_bytes();
+        let mut long = b.clone();
+        long.push(0);
+
+        assert_that(&SfxDataDat::from_bytes(&b[..SFXDATA_DAT_LEN - 1]).err()).is_equal_to(Some(
+            crate::ParseError::Malformed {
+                asset: "sfxdata.dat",
+                description: "End of file".to_string(),
+                position: SFXDATA_DAT_LEN - 1,
+            },
+        ));
+        assert_that(&SfxDataDat::from_bytes(&long).err()).is_equal_to(Some(
+            crate::ParseError::Malformed {
+                asset: "sfxdata.dat",
+                description: "End of file".to_string(),
+                position: SFXDATA_DAT_LEN,
+            },
+        ));
+    }
+}
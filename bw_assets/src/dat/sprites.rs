@@ -1,8 +1,5 @@
-use amethyst::{
-    assets::Format,
-    assets::{Asset, Handle},
-    ecs::DenseVecStorage,
-};
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
 use boolinator::Boolinator;
 use nom::{
     bytes::complete::take,
@@ -10,10 +7,12 @@ use nom::{
     error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16},
-    Finish, IResult, Parser,
+    IResult, Parser,
 };
 
-#[derive(Debug)]
+use crate::error::finish_parse;
+
+#[derive(Clone, Debug)]
 pub struct Sprite {
     image_file: u16,
     health_bar: Option<u8>,
@@ -22,42 +21,64 @@ pub struct Sprite {
     selection_circle_offset: Option<u8>,
 }
 
-pub struct SpritesDat(Vec<Sprite>);
+impl Sprite {
+    /// Pointer to images.dat
+    pub fn image_file(&self) -> u16 {
+        self.image_file
+    }
+
+    /// Length of the health bar drawn under the selected sprite, in pixels.
+    /// `None` for sprites that cannot be selected.
+    pub fn health_bar(&self) -> Option<u8> {
+        self.health_bar
+    }
+
+    /// Whether the sprite can be selected, i.e. has an entry in the health
+    /// bar and selection circle columns.
+    pub fn is_selectable(&self) -> bool {
+        self.selection_circle_image.is_some()
+    }
 
-pub struct SpritesDatAsset(Option<SpritesDat>);
+    /// Selection circle drawn around the selected sprite, as an offset from
+    /// the first selection circle image of images.dat. 0 for sprites that
+    /// cannot be selected, see [`Sprite::is_selectable`].
+    pub fn selection_circle(&self) -> u8 {
+        self.selection_circle_image.unwrap_or_default()
+    }
 
-impl SpritesDatAsset {
-    pub fn take(&mut self) -> Option<SpritesDat> {
-        self.0.take()
+    /// Vertical offset of the health bar, and of the selection circle above
+    /// it, from the center of the sprite. 0 for sprites that cannot be
+    /// selected, see [`Sprite::is_selectable`].
+    pub fn health_bar_offset(&self) -> u8 {
+        self.selection_circle_offset.unwrap_or_default()
     }
 }
 
-pub type SpritesDatHandle = Handle<SpritesDatAsset>;
+#[derive(Clone)]
+pub struct SpritesDat(Vec<Sprite>);
+
+dat_iter!(SpritesDat, Sprite);
+
+dat_asset!(SpritesDatAsset, SpritesDat, SpritesDatHandle);
 
-impl Asset for SpritesDatAsset {
-    const NAME: &'static str = "bw_assets::dat::SpritesDatAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<SpritesDatHandle>;
+impl SpritesDat {
+    pub fn from_bytes(b: &[u8]) -> Result<SpritesDat, crate::ParseError> {
+        finish_parse("sprites.dat", b, parse_sprites_dat(b))
+    }
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct SpritesDatFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<SpritesDatAsset> for SpritesDatFormat {
     fn name(&self) -> &'static str {
         "SpritesDatAsset"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<SpritesDatAsset> {
-        let (_, sprites_dat) = parse_sprites_dat(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load sprites.dat asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(SpritesDatAsset(Some(sprites_dat)))
+        Ok(SpritesDatAsset(Some(SpritesDat::from_bytes(&b)?)))
     }
 }
 
@@ -124,3 +145,32 @@ fn parse_sprites_dat(b: &[u8]) -> IResult<&[u8], SpritesDat> {
 
     Ok((remaining, SpritesDat(sprites)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_decodes_the_selection_circle_of_selectable_sprites() {
+        let mut b = vec![0; BLOCK_SIZE * 4 + SELECTABLE_COUNT * 3];
+        let health_bar = BLOCK_SIZE * 2;
+        let selection_circle_image = BLOCK_SIZE * 4 + SELECTABLE_COUNT;
+        let selection_circle_offset = selection_circle_image + SELECTABLE_COUNT;
+        // the first selectable sprite, e.g. a marine
+        b[health_bar] = 22;
+        b[selection_circle_image] = 1;
+        b[selection_circle_offset] = 9;
+
+        let sprites_dat = SpritesDat::from_bytes(&b).unwrap();
+        let marine = sprites_dat.iter().nth(NON_SELECTABLE_COUNT).unwrap();
+        let non_selectable = sprites_dat.iter().next().unwrap();
+
+        assert_that(&marine.health_bar()).is_equal_to(Some(22));
+        assert_that(&marine.selection_circle()).is_equal_to(1);
+        assert_that(&marine.health_bar_offset()).is_equal_to(9);
+        assert_that(&marine.is_selectable()).is_true();
+        assert_that(&non_selectable.is_selectable()).is_false();
+        assert_that(&non_selectable.selection_circle()).is_equal_to(0);
+    }
+}
@@ -1,18 +1,17 @@
-use amethyst::{
-    assets::Format,
-    assets::{Asset, Handle},
-    ecs::DenseVecStorage,
-};
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
     error::ParseError,
     multi::count,
-    number::complete::{le_u8, le_u16, le_u32},
-    Finish, IResult, Parser,
+    number::complete::{le_u8, le_u16},
+    IResult, Parser,
 };
 
-#[derive(Debug)]
+use crate::error::finish_parse;
+
+#[derive(Clone, Debug)]
 pub struct TechData {
     mineral_cost: u16,
     vespene_cost: u16,
@@ -24,42 +23,33 @@ pub struct TechData {
     broodwar: u8,
 }
 
-pub struct TechDataDat(Vec<TechData>);
+#[derive(Clone)]
+pub struct TechDataDat(Vec<TechData>, Vec<Vec<u8>>);
 
-pub struct TechDataDatAsset(Option<TechDataDat>);
+dat_iter!(TechDataDat, TechData);
 
-impl TechDataDatAsset {
-    pub fn take(&mut self) -> Option<TechDataDat> {
-        self.0.take()
-    }
-}
+dat_unknown_columns!(TechDataDat);
 
-pub type TechDataDatHandle = Handle<TechDataDatAsset>;
+dat_asset!(TechDataDatAsset, TechDataDat, TechDataDatHandle);
 
-impl Asset for TechDataDatAsset {
-    const NAME: &'static str = "bw_assets::dat::TechDataDatAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<TechDataDatHandle>;
+impl TechDataDat {
+    pub fn from_bytes(b: &[u8]) -> Result<TechDataDat, crate::ParseError> {
+        finish_parse("techdata.dat", b, parse_tech_dat_dat(b))
+    }
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct TechDataDatFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<TechDataDatAsset> for TechDataDatFormat {
     fn name(&self) -> &'static str {
         "TechDataDatAsset"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<TechDataDatAsset> {
-        let (_, techdata_dat) = parse_tech_dat_dat(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load techdata.dat asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(TechDataDatAsset(Some(techdata_dat)))
+        Ok(TechDataDatAsset(Some(TechDataDat::from_bytes(&b)?)))
     }
 }
 
@@ -80,17 +70,14 @@ fn parse_tech_dat_dat(b: &[u8]) -> IResult<&[u8], TechDataDat> {
     let (remaining, research_time_col) = count_total(le_u16)(remaining)?;
     let (remaining, energy_cost_col) = count_total(le_u16)(remaining)?;
 
-    // unknown block
-    let (remaining, _) = count_total(le_u32)(remaining)?;
+    let (remaining, unknown_u32_col) = take(BLOCK_SIZE * 4)(remaining)?;
 
     let (remaining, icon_cost_col) = count_total(le_u16)(remaining)?;
     let (remaining, label_cost_col) = count_total(le_u16)(remaining)?;
     let (remaining, race_cost_col) = count_total(le_u8)(remaining)?;
 
-    // unknown block
-    let (remaining, _) = count_total(le_u8)(remaining)?;
+    let (remaining, unknown_u8_col) = take(BLOCK_SIZE)(remaining)?;
 
-    // unknown block
     let (remaining, broodwar_col) = count_total(le_u8)(remaining)?;
 
     all_consuming(take(0u8))(remaining)?;
@@ -108,5 +95,7 @@ fn parse_tech_dat_dat(b: &[u8]) -> IResult<&[u8], TechDataDat> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, TechDataDat(tech_data)))
+    let unknown = vec![unknown_u32_col.to_vec(), unknown_u8_col.to_vec()];
+
+    Ok((remaining, TechDataDat(tech_data, unknown)))
 }
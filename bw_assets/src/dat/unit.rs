@@ -1,9 +1,7 @@
-use amethyst::{
-    assets::Format,
-    assets::{Asset, Handle},
-    ecs::DenseVecStorage,
-};
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
 use boolinator::Boolinator;
+use bw_core::UnitId;
 use nom::{
     bytes::complete::take,
     combinator::{all_consuming, map},
@@ -11,28 +9,98 @@ use nom::{
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
     sequence::tuple,
-    Finish, IResult, InputIter, InputTake, Parser,
+    IResult, Parser,
 };
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
 use struple::Struple;
 
+use super::weapons::WeaponPointer;
+
+use crate::error::finish_parse;
+use crate::tbl::StringTable;
+
 #[derive(Clone, Debug, Struple)]
 pub struct StarEditPlacementBox {
     width: u16,
     height: u16,
 }
 
-#[derive(Clone, Debug, Struple)]
-pub struct Dimensions {
+/// Extents of a unit from its center, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Struple)]
+pub struct UnitDimensions {
     left: u16,
     up: u16,
     right: u16,
     down: u16,
 }
 
+impl UnitDimensions {
+    pub fn new(left: u16, up: u16, right: u16, down: u16) -> UnitDimensions {
+        UnitDimensions {
+            left,
+            up,
+            right,
+            down,
+        }
+    }
+
+    pub fn left(&self) -> u16 {
+        self.left
+    }
+
+    pub fn up(&self) -> u16 {
+        self.up
+    }
+
+    pub fn right(&self) -> u16 {
+        self.right
+    }
+
+    pub fn down(&self) -> u16 {
+        self.down
+    }
+
+    /// Width and height of the unit in pixels. The extents do not include
+    /// the center pixel, so it is added to both dimensions.
+    pub fn bounding_box(&self) -> (u32, u32) {
+        (
+            self.left as u32 + self.right as u32 + 1,
+            self.up as u32 + self.down as u32 + 1,
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UnitPointer(u16);
 
-#[derive(Debug)]
+impl UnitPointer {
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Size of a unit, used to scale the damage of explosive and concussive
+/// weapons.
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum UnitSize {
+    Independent = 0,
+    Small = 1,
+    Medium = 2,
+    Large = 3,
+}
+
+impl TryFrom<u8> for UnitSize {
+    type Error = u8;
+
+    /// Fails with the original value if it is not a unit size.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FromPrimitive::from_u8(value).ok_or(value)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Unit {
     /// Unit's main graphics object.
     ///
@@ -161,6 +229,8 @@ pub struct Unit {
     /// (no matter by what unit or weapon and regardless of its Armor.
     //// Spell effects may vary, e.g. Plague works normally, but Irradiate
     /// doesn't).
+    ///
+    /// Stored as it is in the file, as mods use sizes the game does not know.
     unit_size: u8,
 
     /// Unit's basic Armor level. Armor is subtracted from damage caused by
@@ -234,7 +304,7 @@ pub struct Unit {
     addon_vertical: Option<u16>,
 
     /// Dimensions of the unit. Measured in pixels.
-    dimensions: Dimensions,
+    dimensions: UnitDimensions,
 
     /// Unit's Idle and Talking portraits.
     ///
@@ -291,42 +361,113 @@ pub struct Unit {
     star_edit_availability_flags: u16,
 }
 
-pub struct UnitsDat(Vec<Unit>);
+impl Unit {
+    /// Value of a unit pointer that does not point to any unit.
+    const NO_UNIT: u16 = BLOCK_SIZE as u16;
+
+    /// Value of a weapon pointer that does not point to any weapon.
+    const NO_WEAPON: u8 = 130;
+
+    /// Pointer to flingy.dat
+    pub fn graphics(&self) -> u8 {
+        self.graphics
+    }
+
+    pub fn subunit(&self) -> Option<&UnitPointer> {
+        (self.sub_unit_1.0 != Unit::NO_UNIT).as_some(&self.sub_unit_1)
+    }
+
+    pub fn infestation(&self) -> Option<&UnitPointer> {
+        self.infestation
+            .as_ref()
+            .filter(|unit| unit.0 != Unit::NO_UNIT)
+    }
+
+    pub fn ground_weapon(&self) -> Option<WeaponPointer> {
+        (self.ground_weapon != Unit::NO_WEAPON).as_some(WeaponPointer::new(self.ground_weapon))
+    }
+
+    pub fn air_weapon(&self) -> Option<WeaponPointer> {
+        (self.air_weapon != Unit::NO_WEAPON).as_some(WeaponPointer::new(self.air_weapon))
+    }
+
+    /// Pointer to upgrades.dat
+    pub fn armour_upgrade(&self) -> u8 {
+        self.armour_upgrade
+    }
 
-pub struct UnitsDatAsset(Option<UnitsDat>);
+    /// Maximum shields of the unit, if it has shields.
+    pub fn shield_amount(&self) -> Option<u16> {
+        self.are_shields_enabled.as_some(self.shield_amount)
+    }
+
+    /// Size of the unit, or the stored value if it is not one of the four
+    /// unit sizes.
+    pub fn size(&self) -> Result<UnitSize, u8> {
+        UnitSize::try_from(self.unit_size)
+    }
+
+    /// Extents of the unit used for collision and placement.
+    pub fn dimensions(&self) -> UnitDimensions {
+        self.dimensions
+    }
+
+    /// Width and height of the green placement rectangle in StarEdit, in
+    /// pixels.
+    pub fn placement_box(&self) -> (u16, u16) {
+        (
+            self.star_edit_placement_box.width,
+            self.star_edit_placement_box.height,
+        )
+    }
+
+    pub fn build_score(&self) -> u16 {
+        self.build_score
+    }
 
-impl UnitsDatAsset {
-    pub fn take(&mut self) -> Option<UnitsDat> {
-        self.0.take()
+    pub fn destroy_score(&self) -> u16 {
+        self.destroy_score
     }
 }
 
-pub type UnitsDatHandle = Handle<UnitsDatAsset>;
+#[derive(Clone)]
+pub struct UnitsDat(Vec<Unit>, Vec<Vec<u8>>);
 
-impl Asset for UnitsDatAsset {
-    const NAME: &'static str = "bw_assets::dat::UnitsDatAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<UnitsDatHandle>;
+dat_iter!(UnitsDat, Unit);
+
+dat_unknown_columns!(UnitsDat);
+
+dat_asset!(UnitsDatAsset, UnitsDat, UnitsDatHandle);
+
+impl UnitsDat {
+    pub fn from_bytes(b: &[u8]) -> Result<UnitsDat, crate::ParseError> {
+        finish_parse("units.dat", b, parse_unit_dat(b))
+    }
+
+    /// Unit whose name in stat_txt.tbl is `name`, ignoring case and the
+    /// control bytes the name starts with.
+    ///
+    /// units.dat has no label column: the name of every unit is the string
+    /// after its id, i.e. string `id + 1`.
+    pub fn find_by_name(&self, tbl: &StringTable, name: &str) -> Option<UnitId> {
+        (0..self.0.len())
+            .find(|&id| tbl.is_named(id as u16 + 1, name))
+            .and_then(FromPrimitive::from_usize)
+    }
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct UnitsDatFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<UnitsDatAsset> for UnitsDatFormat {
     fn name(&self) -> &'static str {
         "UnitsDatFormat"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<UnitsDatAsset> {
-        let (_, unit_dat) = parse_unit_dat(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load units.dat asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(UnitsDatAsset(Some(unit_dat)))
+        Ok(UnitsDatAsset(Some(UnitsDat::from_bytes(&b)?)))
     }
 }
 
@@ -338,18 +479,6 @@ fn parse_u8_boolean(b: &[u8]) -> IResult<&[u8], bool> {
     map(le_u8, |x| x != 0)(b)
 }
 
-fn take_block(b: &[u8], size: u8) -> IResult<&[u8], ()> {
-    map(count_total(take(size)), |_| ())(b)
-}
-
-fn take_u8_block(b: &[u8]) -> IResult<&[u8], ()> {
-    take_block(b, 1u8)
-}
-
-fn take_u16_block(b: &[u8]) -> IResult<&[u8], ()> {
-    take_block(b, 2u8)
-}
-
 fn u32_to_f32(x: u32, decimal_places: u32) -> f32 {
     let whole = (x >> decimal_places) as f32;
     let decimals = (x & ((1 << decimal_places) - 1)) as f32 / 10f32.powf(decimal_places as f32);
@@ -357,24 +486,6 @@ fn u32_to_f32(x: u32, decimal_places: u32) -> f32 {
     whole + decimals
 }
 
-fn count_building_block<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq + InputIter + InputTake,
-    F: Parser<I, O, E> + Clone,
-    E: ParseError<I>,
-{
-    count(f, BUILDING_COUNT)
-}
-
-fn count_unit_block<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
-where
-    I: Clone + PartialEq + InputIter + InputTake,
-    F: Parser<I, O, E>,
-    E: ParseError<I>,
-{
-    count(f, UNIT_COUNT)
-}
-
 pub fn count_total<I, O, E, F>(f: F) -> impl FnMut(I) -> IResult<I, Vec<O>, E>
 where
     I: Clone + PartialEq,
@@ -389,83 +500,76 @@ fn parse_unit_pointer(b: &[u8]) -> IResult<&[u8], UnitPointer> {
 }
 
 fn parse_unit_dat(b: &[u8]) -> IResult<&[u8], UnitsDat> {
-    let (remaining, graphic_col) = count_total(le_u8)(b)?;
-
-    let (remaining, sub_unit_1_col) = count_total(parse_unit_pointer)(remaining)?;
-
-    // The next u16 is sub_unit_2 which is unused by the game.
-    let (remaining, _) = take_u16_block(remaining)?;
-
-    let (remaining, infestation_col) = count_building_block(parse_unit_pointer)(remaining)?;
-    let (remaining, construction_animation_col) = count_total(le_u32)(remaining)?;
-    let (remaining, unit_direction_col) = count_total(le_u8)(remaining)?;
-    let (remaining, are_shields_enabled_col) = count_total(parse_u8_boolean)(remaining)?;
-    let (remaining, shield_amount_col) = count_total(le_u16)(remaining)?;
-    let (remaining, hit_points_col) = count_total(map(le_u32, |x| u32_to_f32(x, 8)))(remaining)?;
-    let (remaining, elevation_level_col) = count_total(le_u8)(remaining)?;
-
-    // unknown block
-    let (remaining, _) = take_u8_block(remaining)?;
-
-    let (remaining, sub_label_col) = count_total(le_u8)(remaining)?;
-    let (remaining, comp_ai_idle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, human_ai_idle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, return_to_idle_col) = count_total(le_u8)(remaining)?;
-    let (remaining, attack_unit_col) = count_total(le_u8)(remaining)?;
-    let (remaining, attack_move_col) = count_total(le_u8)(remaining)?;
-    let (remaining, ground_weapon_col) = count_total(le_u8)(remaining)?;
-    let (remaining, max_ground_hits_col) = count_total(le_u8)(remaining)?;
-    let (remaining, air_weapon_col) = count_total(le_u8)(remaining)?;
-    let (remaining, max_air_hits_col) = count_total(le_u8)(remaining)?;
-    let (remaining, ai_internal_col) = count_total(le_u8)(remaining)?;
-    let (remaining, special_ability_flags_col) = count_total(le_u32)(remaining)?;
-    let (remaining, target_acquisition_range_col) = count_total(le_u8)(remaining)?;
-    let (remaining, sight_range_col) = count_total(le_u8)(remaining)?;
-    let (remaining, armour_upgrade_col) = count_total(le_u8)(remaining)?;
-    let (remaining, unit_size_col) = count_total(le_u8)(remaining)?;
-    let (remaining, armour_col) = count_total(le_u8)(remaining)?;
-    let (remaining, right_click_action_col) = count_total(le_u8)(remaining)?;
-    let (remaining, ready_sound_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, what_sound_start_col) = count_total(le_u16)(remaining)?;
-    let (remaining, what_sound_end_col) = count_total(le_u16)(remaining)?;
-    let (remaining, annoyed_sound_start_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, annoyed_sound_end_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, yes_sound_start_col) = count_unit_block(le_u16)(remaining)?;
-    let (remaining, yes_sound_end_col) = count_unit_block(le_u16)(remaining)?;
-
-
-    let (remaining, star_edit_placement_col) = count_total(map(
-        tuple((le_u16, le_u16)),
-        StarEditPlacementBox::from_tuple,
-    ))(remaining)?;
-
-    let (remaining, addon_horizontal_col) = count_building_block(le_u16)(remaining)?;
-    let (remaining, addon_vertical_col) = count_building_block(le_u16)(remaining)?;
-
-    let (remaining, dimensions_col) = count_total(map(
-        tuple((le_u16, le_u16, le_u16, le_u16)),
-        Dimensions::from_tuple,
-    ))(remaining)?;
-
-    let (remaining, portrait_col) = count_total(le_u16)(remaining)?;
-    let (remaining, mineral_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, vespense_cost_col) = count_total(le_u16)(remaining)?;
-    let (remaining, build_time_col) = count_total(le_u16)(remaining)?;
-
-    // The u16 after build time is unknown and useless
-    let (remaining, _) = take_u16_block(remaining)?;
-
-    let (remaining, star_edit_group_flags_col) = count_total(le_u8)(remaining)?;
-    let (remaining, supply_provided_col) = count_total(le_u8)(remaining)?;
-    let (remaining, supply_required_col) = count_total(le_u8)(remaining)?;
-    let (remaining, space_required_col) = count_total(le_u8)(remaining)?;
-    let (remaining, space_provided_col) = count_total(le_u8)(remaining)?;
-    let (remaining, build_score_col) = count_total(le_u16)(remaining)?;
-    let (remaining, destroy_score_col) = count_total(le_u16)(remaining)?;
-    let (remaining, unit_map_string_col) = count_total(le_u16)(remaining)?;
-    let (remaining, is_broodwar_only_col) = count_total(parse_u8_boolean)(remaining)?;
-
-    let (remaining, star_edit_availability_flags_col) = count_total(le_u16)(remaining)?;
+    parse_unit_dat_with(b, cfg!(feature = "parallel"))
+}
+
+fn parse_unit_dat_with(b: &[u8], parallel: bool) -> IResult<&[u8], UnitsDat> {
+    dat_columns! {
+        b, parallel => remaining;
+        graphic_col: [le_u8; BLOCK_SIZE] => 1,
+        sub_unit_1_col: [parse_unit_pointer; BLOCK_SIZE] => 2,
+        // sub_unit_2 is unused by the game
+        sub_unit_2_col: [take(2u8); BLOCK_SIZE] => 2,
+        infestation_col: [parse_unit_pointer; BUILDING_COUNT] => 2,
+        construction_animation_col: [le_u32; BLOCK_SIZE] => 4,
+        unit_direction_col: [le_u8; BLOCK_SIZE] => 1,
+        are_shields_enabled_col: [parse_u8_boolean; BLOCK_SIZE] => 1,
+        shield_amount_col: [le_u16; BLOCK_SIZE] => 2,
+        hit_points_col: [map(le_u32, |x| u32_to_f32(x, 8)); BLOCK_SIZE] => 4,
+        elevation_level_col: [le_u8; BLOCK_SIZE] => 1,
+        unknown_col: [take(1u8); BLOCK_SIZE] => 1,
+        sub_label_col: [le_u8; BLOCK_SIZE] => 1,
+        comp_ai_idle_col: [le_u8; BLOCK_SIZE] => 1,
+        human_ai_idle_col: [le_u8; BLOCK_SIZE] => 1,
+        return_to_idle_col: [le_u8; BLOCK_SIZE] => 1,
+        attack_unit_col: [le_u8; BLOCK_SIZE] => 1,
+        attack_move_col: [le_u8; BLOCK_SIZE] => 1,
+        ground_weapon_col: [le_u8; BLOCK_SIZE] => 1,
+        max_ground_hits_col: [le_u8; BLOCK_SIZE] => 1,
+        air_weapon_col: [le_u8; BLOCK_SIZE] => 1,
+        max_air_hits_col: [le_u8; BLOCK_SIZE] => 1,
+        ai_internal_col: [le_u8; BLOCK_SIZE] => 1,
+        special_ability_flags_col: [le_u32; BLOCK_SIZE] => 4,
+        target_acquisition_range_col: [le_u8; BLOCK_SIZE] => 1,
+        sight_range_col: [le_u8; BLOCK_SIZE] => 1,
+        armour_upgrade_col: [le_u8; BLOCK_SIZE] => 1,
+        unit_size_col: [le_u8; BLOCK_SIZE] => 1,
+        armour_col: [le_u8; BLOCK_SIZE] => 1,
+        right_click_action_col: [le_u8; BLOCK_SIZE] => 1,
+        ready_sound_col: [le_u16; UNIT_COUNT] => 2,
+        what_sound_start_col: [le_u16; BLOCK_SIZE] => 2,
+        what_sound_end_col: [le_u16; BLOCK_SIZE] => 2,
+        annoyed_sound_start_col: [le_u16; UNIT_COUNT] => 2,
+        annoyed_sound_end_col: [le_u16; UNIT_COUNT] => 2,
+        yes_sound_start_col: [le_u16; UNIT_COUNT] => 2,
+        yes_sound_end_col: [le_u16; UNIT_COUNT] => 2,
+        star_edit_placement_col: [
+            map(tuple((le_u16, le_u16)), StarEditPlacementBox::from_tuple);
+            BLOCK_SIZE
+        ] => 4,
+        addon_horizontal_col: [le_u16; BUILDING_COUNT] => 2,
+        addon_vertical_col: [le_u16; BUILDING_COUNT] => 2,
+        dimensions_col: [
+            map(tuple((le_u16, le_u16, le_u16, le_u16)), UnitDimensions::from_tuple);
+            BLOCK_SIZE
+        ] => 8,
+        portrait_col: [le_u16; BLOCK_SIZE] => 2,
+        mineral_cost_col: [le_u16; BLOCK_SIZE] => 2,
+        vespense_cost_col: [le_u16; BLOCK_SIZE] => 2,
+        build_time_col: [le_u16; BLOCK_SIZE] => 2,
+        // The u16 after build time is unknown and useless
+        build_time_unknown_col: [take(2u8); BLOCK_SIZE] => 2,
+        star_edit_group_flags_col: [le_u8; BLOCK_SIZE] => 1,
+        supply_provided_col: [le_u8; BLOCK_SIZE] => 1,
+        supply_required_col: [le_u8; BLOCK_SIZE] => 1,
+        space_required_col: [le_u8; BLOCK_SIZE] => 1,
+        space_provided_col: [le_u8; BLOCK_SIZE] => 1,
+        build_score_col: [le_u16; BLOCK_SIZE] => 2,
+        destroy_score_col: [le_u16; BLOCK_SIZE] => 2,
+        unit_map_string_col: [le_u16; BLOCK_SIZE] => 2,
+        is_broodwar_only_col: [parse_u8_boolean; BLOCK_SIZE] => 1,
+        star_edit_availability_flags_col: [le_u16; BLOCK_SIZE] => 2,
+    }
 
     all_consuming(take(0u8))(remaining)?;
 
@@ -525,7 +629,7 @@ fn parse_unit_dat(b: &[u8]) -> IResult<&[u8], UnitsDat> {
                             .get(i - UNIT_COUNT)
                             .map(ToOwned::to_owned)
                     }),
-                dimensions: dimensions_col[i].clone(),
+                dimensions: dimensions_col[i],
                 portrait: portrait_col[i],
                 mineral_cost: mineral_cost_col[i],
                 vespense_cost: vespense_cost_col[i],
@@ -543,5 +647,103 @@ fn parse_unit_dat(b: &[u8]) -> IResult<&[u8], UnitsDat> {
             })
             .collect::<Vec<_>>();
 
-    Ok((remaining, UnitsDat(units)))
+    let unknown = vec![
+        sub_unit_2_col.concat(),
+        unknown_col.concat(),
+        build_time_unknown_col.concat(),
+    ];
+
+    Ok((remaining, UnitsDat(units, unknown)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const UNITS_DAT_LEN: usize = 19876;
+
+    fn units_dat_bytes() -> Vec<u8> {
+        (0..UNITS_DAT_LEN).map(|i| (i % 7 % 4) as u8).collect()
+    }
+
+    #[test]
+    fn it_finds_a_unit_by_its_name() {
+        let (_, units_dat) = parse_unit_dat(&units_dat_bytes()).unwrap();
+        let mut names = vec![String::new(); BLOCK_SIZE];
+        names[0] = "Terran Marine".to_string();
+        names[5] = "\u{4}Siege Tank".to_string();
+        let tbl = StringTable::new(names);
+
+        assert_that(&units_dat.find_by_name(&tbl, "siege tank"))
+            .is_equal_to(Some(UnitId::TerranSiegeTankTankMode));
+        assert_that(&units_dat.find_by_name(&tbl, "Terran Marine"))
+            .is_equal_to(Some(UnitId::TerranMarine));
+        assert_that(&units_dat.find_by_name(&tbl, "Zergling")).is_none();
+    }
+
+    #[test]
+    fn it_keeps_the_columns_of_unknown_purpose() {
+        let b = units_dat_bytes();
+        let sub_unit_2_offset = BLOCK_SIZE * (1 + 2);
+        let sub_unit_2 = &b[sub_unit_2_offset..sub_unit_2_offset + BLOCK_SIZE * 2];
+
+        let (_, units_dat) = parse_unit_dat(&b).unwrap();
+
+        assert_that(&units_dat.unknown_column_count()).is_equal_to(3);
+        assert_that(&units_dat.raw_unknown(0)).is_equal_to(Some(sub_unit_2));
+        assert_that(&units_dat.raw_unknown(1).map(<[u8]>::len)).is_equal_to(Some(BLOCK_SIZE));
+        assert_that(&units_dat.raw_unknown(2).map(<[u8]>::len)).is_equal_to(Some(BLOCK_SIZE * 2));
+        assert_that(&units_dat.raw_unknown(3)).is_none();
+    }
+
+    #[test]
+    fn it_parses_columns_in_parallel_the_same_as_sequentially() {
+        let b = units_dat_bytes();
+
+        let (_, parallel) = parse_unit_dat_with(&b, true).unwrap();
+        let (_, sequential) = parse_unit_dat_with(&b, false).unwrap();
+
+        assert_that(&format!("{:?}", parallel.0)).is_equal_to(format!("{:?}", sequential.0));
+    }
+
+    #[test]
+    fn it_decodes_unit_sizes() {
+        assert_that(&UnitSize::try_from(0)).is_ok_containing(UnitSize::Independent);
+        assert_that(&UnitSize::try_from(1)).is_ok_containing(UnitSize::Small);
+        assert_that(&UnitSize::try_from(2)).is_ok_containing(UnitSize::Medium);
+        assert_that(&UnitSize::try_from(3)).is_ok_containing(UnitSize::Large);
+        assert_that(&UnitSize::try_from(4)).is_err_containing(4);
+    }
+
+    #[test]
+    fn it_keeps_unit_sizes_the_game_does_not_know() {
+        const UNIT_SIZE_OFFSET: usize = 8628;
+        let mut b = vec![0; UNITS_DAT_LEN];
+        b[UNIT_SIZE_OFFSET + 5] = 9;
+        b[UNIT_SIZE_OFFSET + 6] = 3;
+
+        let units_dat = UnitsDat::from_bytes(&b).unwrap();
+        let size = |id| units_dat.iter().nth(id).unwrap().size();
+
+        assert_that(&size(5)).is_err_containing(9);
+        assert_that(&size(6)).is_ok_containing(UnitSize::Large);
+    }
+
+    #[test]
+    fn it_reads_the_dimensions_of_the_command_center() {
+        const COMMAND_CENTER: usize = 106;
+        let mut b = vec![0; UNITS_DAT_LEN];
+        let placement = 11284 + COMMAND_CENTER * 4;
+        b[placement..placement + 4].copy_from_slice(&[128, 0, 96, 0]);
+        let dimensions = 12580 + COMMAND_CENTER * 8;
+        b[dimensions..dimensions + 8].copy_from_slice(&[58, 0, 41, 0, 58, 0, 41, 0]);
+
+        let units_dat = UnitsDat::from_bytes(&b).unwrap();
+        let command_center = units_dat.iter().nth(COMMAND_CENTER).unwrap();
+
+        assert_that(&command_center.dimensions()).is_equal_to(UnitDimensions::new(58, 41, 58, 41));
+        assert_that(&command_center.dimensions().bounding_box()).is_equal_to((117, 83));
+        assert_that(&command_center.placement_box()).is_equal_to((128, 96));
+    }
 }
@@ -1,18 +1,17 @@
-use amethyst::{
-    assets::Format,
-    assets::{Asset, Handle},
-    ecs::DenseVecStorage,
-};
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
     error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16},
-    Finish, IResult, Parser,
+    IResult, Parser,
 };
 
-#[derive(Debug)]
+use crate::error::finish_parse;
+
+#[derive(Clone, Debug)]
 pub struct Upgrade {
     mineral_cost: u16,
     mineral_factor: u16,
@@ -27,42 +26,33 @@ pub struct Upgrade {
     brood_war_specific: u8,
 }
 
-pub struct UpgradesDat(Vec<Upgrade>);
+#[derive(Clone)]
+pub struct UpgradesDat(Vec<Upgrade>, Vec<Vec<u8>>);
 
-pub struct UpgradesDatAsset(Option<UpgradesDat>);
+dat_iter!(UpgradesDat, Upgrade);
 
-impl UpgradesDatAsset {
-    pub fn take(&mut self) -> Option<UpgradesDat> {
-        self.0.take()
-    }
-}
+dat_unknown_columns!(UpgradesDat);
 
-pub type UpgradesDatHandle = Handle<UpgradesDatAsset>;
+dat_asset!(UpgradesDatAsset, UpgradesDat, UpgradesDatHandle);
 
-impl Asset for UpgradesDatAsset {
-    const NAME: &'static str = "bw_assets::dat::UpgradesDatAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<UpgradesDatHandle>;
+impl UpgradesDat {
+    pub fn from_bytes(b: &[u8]) -> Result<UpgradesDat, crate::ParseError> {
+        finish_parse("upgrades.dat", b, parse_upgrades_dat(b))
+    }
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct UpgradesDatFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<UpgradesDatAsset> for UpgradesDatFormat {
     fn name(&self) -> &'static str {
         "UpgradesDatAsset"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<UpgradesDatAsset> {
-        let (_, upgrades_dat) = parse_upgrades_dat(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load upgrades.dat asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(UpgradesDatAsset(Some(upgrades_dat)))
+        Ok(UpgradesDatAsset(Some(UpgradesDat::from_bytes(&b)?)))
     }
 }
 
@@ -85,8 +75,7 @@ fn parse_upgrades_dat(b: &[u8]) -> IResult<&[u8], UpgradesDat> {
     let (remaining, time_cost_col) = count_total(le_u16)(remaining)?;
     let (remaining, time_factor_col) = count_total(le_u16)(remaining)?;
 
-    // unknown block
-    let (remaining, _) = count_total(le_u16)(remaining)?;
+    let (remaining, unknown_col) = take(BLOCK_SIZE * 2)(remaining)?;
 
     let (remaining, icon_col) = count_total(le_u16)(remaining)?;
     let (remaining, label_col) = count_total(le_u16)(remaining)?;
@@ -112,5 +101,5 @@ fn parse_upgrades_dat(b: &[u8]) -> IResult<&[u8], UpgradesDat> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, UpgradesDat(upgrades)))
+    Ok((remaining, UpgradesDat(upgrades, vec![unknown_col.to_vec()])))
 }
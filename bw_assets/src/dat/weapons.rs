@@ -1,8 +1,6 @@
-use amethyst::{
-    assets::Format,
-    assets::{Asset, Handle},
-    ecs::DenseVecStorage,
-};
+#[cfg(feature = "amethyst")]
+use amethyst::assets::Format;
+use bitflags::bitflags;
 use nom::{
     bytes::complete::take,
     combinator::all_consuming,
@@ -10,20 +8,83 @@ use nom::{
     error::ParseError,
     multi::count,
     number::complete::{le_u8, le_u16, le_u32},
-    Finish, IResult, Parser,
+    IResult, Parser,
 };
 
-use super::flingy::FlingyPointer;
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+
+use super::{damage::DamageType, flingy::FlingyPointer};
+
+use crate::error::finish_parse;
+use crate::tbl::StringTable;
+
+#[derive(Clone, Debug)]
+pub struct WeaponPointer(pub(super) u8);
+
+impl WeaponPointer {
+    pub fn new(p: u8) -> WeaponPointer {
+        WeaponPointer(p)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+bitflags! {
+    /// Targets a weapon is allowed to attack.
+    pub struct TargetFlags: u16 {
+        const AIR = 0x001;
+        const GROUND = 0x002;
+        const MECHANICAL = 0x004;
+        const ORGANIC = 0x008;
+        const NON_BUILDING = 0x010;
+        const NON_ROBOTIC = 0x020;
+        const TERRAIN = 0x040;
+        const ORGANIC_OR_MECHANICAL = 0x080;
+        /// Only units owned by the attacker, e.g. Consume.
+        const OWN = 0x100;
+    }
+}
+
+/// How a weapon moves from the attacker to its target.
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum BehaviorKind {
+    FlyAndDontFollowTarget = 0,
+    FlyAndFollowTarget = 1,
+    AppearOnTargetUnit = 2,
+    PersistOnTargetSite = 3,
+    AppearOnTargetSite = 4,
+    AppearOnAttacker = 5,
+    AttackAndSelfDestruct = 6,
+    Bounce = 7,
+    AttackNearbyArea = 8,
+    GoToMaxRange = 9,
+}
+
+impl TryFrom<u8> for BehaviorKind {
+    type Error = u8;
 
-#[derive(Debug)]
+    /// Fails with the original value if it is not a weapon behavior.
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        FromPrimitive::from_u8(value).ok_or(value)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Weapon {
     label: u16,
     graphics: FlingyPointer,
-    target_flags: u16,
+    target_flags: TargetFlags,
     minimum_range: u32,
     maximum_range: u32,
     damage_upgrade: u8,
-    weapon_type: u8,
+    /// Stored as it is in the file, as mods use damage types the game does
+    /// not know.
+    damage_type: u8,
+    /// Stored as it is in the file, see `damage_type`.
     weapon_behavior: u8,
     remove_after: u8,
     weapon_effect: u8,
@@ -42,42 +103,89 @@ pub struct Weapon {
     icon: u16,
 }
 
-pub struct WeaponsDat(Vec<Weapon>);
+impl Weapon {
+    /// 1-based id of the weapon's name in stat_txt.tbl.
+    pub fn label(&self) -> u16 {
+        self.label
+    }
+
+    pub fn graphics(&self) -> &FlingyPointer {
+        &self.graphics
+    }
+
+    /// Pointer to upgrades.dat
+    pub fn damage_upgrade(&self) -> u8 {
+        self.damage_upgrade
+    }
+
+    /// Damage type of the weapon, or the stored value if it is not one of
+    /// the five damage types.
+    pub fn damage_type(&self) -> Result<DamageType, u8> {
+        DamageType::try_from(self.damage_type)
+    }
+
+    pub fn targeting(&self) -> TargetFlags {
+        self.target_flags
+    }
+
+    /// How the weapon moves, or the stored value if it is not one of the
+    /// weapon behaviors.
+    pub fn behavior(&self) -> Result<BehaviorKind, u8> {
+        BehaviorKind::try_from(self.weapon_behavior)
+    }
+
+    /// Base damage of each hit.
+    pub fn damage_amount(&self) -> u16 {
+        self.damage_amount
+    }
 
-pub struct WeaponsDatAsset(Option<WeaponsDat>);
+    /// Damage added to each hit for every upgrade level.
+    pub fn damage_bonus(&self) -> u16 {
+        self.damage_bonus
+    }
 
-impl WeaponsDatAsset {
-    pub fn take(&mut self) -> Option<WeaponsDat> {
-        self.0.take()
+    /// Number of hits in each attack.
+    pub fn damage_factor(&self) -> u8 {
+        self.damage_factor
     }
 }
 
-pub type WeaponsDatHandle = Handle<WeaponsDatAsset>;
+#[derive(Clone)]
+pub struct WeaponsDat(Vec<Weapon>, Vec<Vec<u8>>);
+
+dat_iter!(WeaponsDat, Weapon);
+
+dat_unknown_columns!(WeaponsDat);
 
-impl Asset for WeaponsDatAsset {
-    const NAME: &'static str = "bw_assets::dat::WeaponsDatAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<WeaponsDatHandle>;
+dat_asset!(WeaponsDatAsset, WeaponsDat, WeaponsDatHandle);
+
+impl WeaponsDat {
+    pub fn from_bytes(b: &[u8]) -> Result<WeaponsDat, crate::ParseError> {
+        finish_parse("weapons.dat", b, parse_weapons_dat(b))
+    }
+
+    /// Weapon whose label in stat_txt.tbl is `name`, ignoring case and the
+    /// control bytes the label starts with.
+    pub fn find_by_name(&self, tbl: &StringTable, name: &str) -> Option<WeaponPointer> {
+        self.0
+            .iter()
+            .position(|weapon| tbl.is_named(weapon.label, name))
+            .map(|i| WeaponPointer::new(i as u8))
+    }
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct WeaponsDatFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<WeaponsDatAsset> for WeaponsDatFormat {
     fn name(&self) -> &'static str {
         "WeaponsDatFormat"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<WeaponsDatAsset> {
-        let (_, weapons_dat) = parse_weapons_dat(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load weapons.dat asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(WeaponsDatAsset(Some(weapons_dat)))
+        Ok(WeaponsDatAsset(Some(WeaponsDat::from_bytes(&b)?)))
     }
 }
 
@@ -96,14 +204,14 @@ fn parse_weapons_dat(b: &[u8]) -> IResult<&[u8], WeaponsDat> {
     let (remaining, label_col) = count_total(le_u16)(b)?;
     let (remaining, flingy_pointer_col) = count_total(map(le_u32, FlingyPointer::new))(remaining)?;
 
-    // unused block
-    let (remaining, _) = count_total(le_u8)(remaining)?;
+    let (remaining, unused_col) = take(BLOCK_SIZE)(remaining)?;
 
-    let (remaining, target_flags_col) = count_total(le_u16)(remaining)?;
+    let (remaining, target_flags_col) =
+        count_total(map(le_u16, TargetFlags::from_bits_truncate))(remaining)?;
     let (remaining, minimum_range_col) = count_total(le_u32)(remaining)?;
     let (remaining, maximum_range_col) = count_total(le_u32)(remaining)?;
     let (remaining, damage_upgrade_col) = count_total(le_u8)(remaining)?;
-    let (remaining, weapon_type_col) = count_total(le_u8)(remaining)?;
+    let (remaining, damage_type_col) = count_total(le_u8)(remaining)?;
     let (remaining, weapon_behavior_col) = count_total(le_u8)(remaining)?;
     let (remaining, remove_after_col) = count_total(le_u8)(remaining)?;
     let (remaining, weapon_effect_col) = count_total(le_u8)(remaining)?;
@@ -134,7 +242,7 @@ fn parse_weapons_dat(b: &[u8]) -> IResult<&[u8], WeaponsDat> {
             minimum_range: minimum_range_col[i],
             maximum_range: maximum_range_col[i],
             damage_upgrade: damage_upgrade_col[i],
-            weapon_type: weapon_type_col[i],
+            damage_type: damage_type_col[i],
             weapon_behavior: weapon_behavior_col[i],
             remove_after: remove_after_col[i],
             weapon_effect: weapon_effect_col[i],
@@ -154,5 +262,82 @@ fn parse_weapons_dat(b: &[u8]) -> IResult<&[u8], WeaponsDat> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, WeaponsDat(weapons)))
+    Ok((remaining, WeaponsDat(weapons, vec![unused_col.to_vec()])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    const GLAVE_WURM: usize = 46;
+    const LONGBOLT_MISSILE: usize = 27;
+
+    fn weapons_dat(weapons: &[(usize, u16, u8)]) -> WeaponsDat {
+        let target_flags_offset = BLOCK_SIZE * (2 + 4 + 1);
+        let weapon_behavior_offset = target_flags_offset + BLOCK_SIZE * (2 + 4 + 4 + 1 + 1);
+
+        let mut b = vec![0; BLOCK_SIZE * 42];
+        for &(i, target_flags, weapon_behavior) in weapons {
+            let offset = target_flags_offset + i * 2;
+            b[offset..offset + 2].copy_from_slice(&target_flags.to_le_bytes());
+            b[weapon_behavior_offset + i] = weapon_behavior;
+        }
+
+        parse_weapons_dat(&b).unwrap().1
+    }
+
+    #[test]
+    fn it_keeps_the_unused_column() {
+        let unused_offset = BLOCK_SIZE * (2 + 4);
+        let mut b = vec![0; BLOCK_SIZE * 42];
+        for (i, byte) in b[unused_offset..unused_offset + BLOCK_SIZE]
+            .iter_mut()
+            .enumerate()
+        {
+            *byte = i as u8;
+        }
+
+        let (_, weapons_dat) = parse_weapons_dat(&b).unwrap();
+
+        assert_that(&weapons_dat.unknown_column_count()).is_equal_to(1);
+        assert_that(&weapons_dat.raw_unknown(0))
+            .is_equal_to(Some(&b[unused_offset..unused_offset + BLOCK_SIZE]));
+        assert_that(&weapons_dat.raw_unknown(1)).is_none();
+    }
+
+    #[test]
+    fn it_decodes_a_bouncing_weapon() {
+        let weapons_dat = weapons_dat(&[(GLAVE_WURM, 0x003, 7)]);
+        let glave_wurm = weapons_dat.iter().nth(GLAVE_WURM).unwrap();
+
+        assert_that(&glave_wurm.behavior()).is_ok_containing(BehaviorKind::Bounce);
+        assert_that(&glave_wurm.targeting()).is_equal_to(TargetFlags::AIR | TargetFlags::GROUND);
+    }
+
+    #[test]
+    fn it_decodes_a_normal_projectile() {
+        let weapons_dat = weapons_dat(&[(LONGBOLT_MISSILE, 0x001, 1)]);
+        let longbolt_missile = weapons_dat.iter().nth(LONGBOLT_MISSILE).unwrap();
+
+        assert_that(&longbolt_missile.behavior())
+            .is_ok_containing(BehaviorKind::FlyAndFollowTarget);
+        assert_that(&longbolt_missile.targeting().contains(TargetFlags::AIR)).is_true();
+        assert_that(&longbolt_missile.targeting().contains(TargetFlags::GROUND)).is_false();
+    }
+
+    #[test]
+    fn it_keeps_values_the_game_does_not_know() {
+        let damage_type_offset = BLOCK_SIZE * (2 + 4 + 1 + 2 + 4 + 4 + 1);
+        let mut b = vec![0; BLOCK_SIZE * 42];
+        b[damage_type_offset] = 5;
+        b[damage_type_offset + BLOCK_SIZE] = 10;
+
+        let (_, weapons_dat) = parse_weapons_dat(&b).unwrap();
+        let weapon = weapons_dat.iter().next().unwrap();
+
+        assert_that(&weapon.damage_type()).is_err_containing(5);
+        assert_that(&weapon.behavior()).is_err_containing(10);
+        assert_that(&BehaviorKind::try_from(10)).is_err_containing(10);
+    }
 }
@@ -0,0 +1,27 @@
+//! Byte order of the Brood War file formats.
+//!
+//! Every format parsed by this crate was written by the x86 build of the game,
+//! so all multi-byte integers are little endian: the dat files, the tileset
+//! files, the CHK chunks of a map and the MPQ archive itself. Parsers must use
+//! the `le_` combinators from `nom::number::complete` or `from_le_bytes`, never
+//! the `be_` or native endian variants.
+
+#[cfg(test)]
+mod tests {
+    use crate::dat::read_u32_column;
+    use crate::tileset::VX4s;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_reads_multi_byte_values_as_little_endian() {
+        let mut vx4 = vec![0; 32];
+        vx4[0..2].copy_from_slice(&[0x03, 0x01]);
+        let vx4s = VX4s::from_bytes(&vx4).unwrap();
+        let minitile = &vx4s.iter().next().unwrap()[0];
+
+        // 0x0103: flipped, pointing at VR4 0x81
+        assert_that(&minitile.is_horizontally_flipped()).is_true();
+        assert_that(&minitile.index()).is_equal_to(0x81);
+        assert_that(&read_u32_column(&[0x78, 0x56, 0x34, 0x12])).is_equal_to(vec![0x1234_5678]);
+    }
+}
@@ -0,0 +1,126 @@
+use nom::{Finish, IResult};
+use snafu::Snafu;
+
+/// Error returned when the bytes of an asset cannot be parsed.
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum ParseError {
+    #[snafu(display(
+        "failed to load {} asset: {} at position {}",
+        asset,
+        description,
+        position
+    ))]
+    Malformed {
+        asset: &'static str,
+        description: String,
+        position: usize,
+    },
+
+    #[snafu(display(
+        "failed to load {} asset: expected {} bytes but found {}",
+        asset,
+        expected,
+        len
+    ))]
+    UnexpectedLength {
+        asset: &'static str,
+        expected: &'static str,
+        len: usize,
+    },
+
+    #[snafu(display(
+        "bytes {}..{} are out of bounds of {} bytes",
+        offset,
+        offset.saturating_add(*len),
+        available
+    ))]
+    OutOfBounds {
+        offset: usize,
+        len: usize,
+        available: usize,
+    },
+}
+
+/// Parses the `len` bytes of `bytes` that start at `offset`, e.g. a dat file
+/// embedded in a larger container, without copying them out first.
+///
+/// The range is checked against `bytes` before it is sliced, so a bad offset
+/// is an error instead of a panic. Positions in errors of `from_bytes` are
+/// relative to `offset`.
+///
+/// ```ignore
+/// let flingy_dat = bw_assets::parse_at(&container, 1024, 3135, FlingyDat::from_bytes)?;
+/// ```
+pub fn parse_at<T>(
+    bytes: &[u8],
+    offset: usize,
+    len: usize,
+    from_bytes: fn(&[u8]) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let range = offset
+        .checked_add(len)
+        .filter(|&end| end <= bytes.len())
+        .map(|end| &bytes[offset..end]);
+
+    match range {
+        Some(b) => from_bytes(b),
+        None => Err(ParseError::OutOfBounds {
+            offset,
+            len,
+            available: bytes.len(),
+        }),
+    }
+}
+
+/// Converts the result of parsing all of `b` into a [`ParseError`] that points
+/// at the position in `b` where parsing failed.
+pub(crate) fn finish_parse<T>(
+    asset: &'static str,
+    b: &[u8],
+    result: IResult<&[u8], T>,
+) -> Result<T, ParseError> {
+    result
+        .finish()
+        .map(|(_, parsed)| parsed)
+        .map_err(|err| ParseError::Malformed {
+            asset,
+            description: err.code.description().to_string(),
+            position: b.len() - err.input.len(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::{number::complete::le_u16, sequence::pair};
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_points_at_the_position_parsing_failed() {
+        let b: &[u8] = &[1, 0, 2];
+        let result = finish_parse("test", b, pair(le_u16, le_u16)(b));
+
+        assert_that(&result).is_err_containing(ParseError::Malformed {
+            asset: "test",
+            description: "End of file".to_string(),
+            position: 2,
+        });
+        assert_that(&result.unwrap_err().to_string())
+            .is_equal_to("failed to load test asset: End of file at position 2".to_string());
+    }
+
+    #[test]
+    fn it_rejects_ranges_out_of_bounds() {
+        let parse = |b: &[u8]| -> Result<Vec<u8>, ParseError> { Ok(b.to_vec()) };
+
+        assert_that(&parse_at(&[1, 2, 3, 4], 1, 2, parse)).is_ok_containing(vec![2, 3]);
+        assert_that(&parse_at(&[1, 2, 3, 4], 3, 2, parse)).is_err_containing(
+            ParseError::OutOfBounds {
+                offset: 3,
+                len: 2,
+                available: 4,
+            },
+        );
+        assert_that(&parse_at(&[1, 2], usize::MAX, 2, parse)).is_err();
+    }
+}
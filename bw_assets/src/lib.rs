@@ -3,7 +3,17 @@
 //! This crate provides the types and parsers necessary to process Brood War
 //! assets. First class support is provided to load these assets using the
 //! Amethyst game engine.
+//!
+//! The parsers only depend on amethyst through the `amethyst` feature, which
+//! is enabled by default. Build with `--no-default-features` to use the
+//! parsers on their own, e.g. `UnitsDat::from_bytes`. The `amethyst` feature
+//! also enables `serde`, which lets tilesets be declared in amethyst prefabs.
+//! The opt-in `snapshot` feature serializes parsed dats to RON snapshots and,
+//! together with `serde`, the opt-in `serde_json` feature streams them as JSON
+//! lines.
 
+#[cfg(feature = "amethyst")]
+extern crate amethyst_engine as amethyst;
 #[macro_use]
 extern crate derive_builder;
 #[macro_use]
@@ -11,6 +21,13 @@ extern crate derive_builder;
 extern crate maplit;
 
 pub mod dat;
+mod endian;
+mod error;
 pub mod map;
 pub mod mpq;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod tbl;
 pub mod tileset;
+
+pub use error::{parse_at, ParseError};
@@ -0,0 +1,153 @@
+//! Queries over the megatiles of a map.
+use std::collections::HashMap;
+
+use super::MegaTile;
+use crate::tileset::{CV5s, CV5};
+
+/// Coordinates of every tile whose tile group satisfies `predicate`, in row
+/// major order. Tiles that reference a tile group outside of the tileset are
+/// skipped.
+///
+/// ```ignore
+/// let water = find_tiles(&map.megatiles, width, &cv5s, |cv5| {
+///     cv5.terrain_type() == TerrainType::new(2)
+/// });
+/// ```
+pub fn find_tiles(
+    tiles: &[MegaTile],
+    width: usize,
+    cv5s: &CV5s,
+    predicate: impl Fn(&CV5) -> bool,
+) -> Vec<(usize, usize)> {
+    if width == 0 {
+        return vec![];
+    }
+
+    tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, megatile)| cv5s.get(megatile).map_or(false, |cv5| predicate(cv5)))
+        .map(|(i, _)| (i % width, i / width))
+        .collect()
+}
+
+/// Checks that every tile references a tile group of the tileset, returning
+/// the indices of the tiles that do not, in row major order.
+///
+/// A map authored for a different tileset than the one loaded references
+/// groups the tileset does not have, which would otherwise panic when the map
+/// is rendered.
+pub fn validate_map_tiles(tiles: &[MegaTile], cv5s: &CV5s) -> Result<(), Vec<usize>> {
+    let invalid = tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, megatile)| cv5s.get(megatile).is_none())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+/// Number of times every raw tile id appears in `tiles`.
+pub fn tile_histogram(tiles: &[MegaTile]) -> HashMap<u16, usize> {
+    let mut histogram = HashMap::new();
+    for megatile in tiles {
+        *histogram.entry(megatile.raw()).or_insert(0) += 1;
+    }
+
+    histogram
+}
+
+/// The `n` most common tiles of a [`tile_histogram`] with their counts, most
+/// common first. Tiles that appear equally often are ordered by id.
+pub fn most_common(histogram: &HashMap<u16, usize>, n: usize) -> Vec<(u16, usize)> {
+    let mut tiles = histogram
+        .iter()
+        .map(|(&tile, &count)| (tile, count))
+        .collect::<Vec<_>>();
+    tiles.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    tiles.truncate(n);
+
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::{BuildFlag, TerrainType};
+    use spectral::prelude::*;
+
+    fn cv5s() -> CV5s {
+        // group 0 is dirt, group 1 is unbuildable water
+        let mut cv5 = vec![0; 52 * 2];
+        cv5[0] = 4;
+        cv5[52] = 2;
+        cv5[52 + 2] = 8 << 4;
+
+        CV5s::from_bytes(&cv5).unwrap()
+    }
+
+    #[test]
+    fn it_finds_all_tiles_of_a_terrain_type() {
+        // a 3x2 map of dirt and water
+        let tiles = [0u16, 1, 0, 1, 1, 0]
+            .iter()
+            .map(|group| MegaTile::from_raw(group << 4))
+            .collect::<Vec<_>>();
+
+        let found = find_tiles(&tiles, 3, &cv5s(), |cv5| {
+            cv5.terrain_type() == TerrainType::new(2)
+        });
+
+        assert_that(&found).is_equal_to(vec![(1, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn it_finds_tiles_by_buildability() {
+        let tiles = vec![MegaTile::from_raw(1 << 4), MegaTile::from_raw(0)];
+
+        let found = find_tiles(&tiles, 2, &cv5s(), |cv5| {
+            cv5.build_flag() == BuildFlag::Unbuildable
+        });
+
+        assert_that(&found).is_equal_to(vec![(0, 0)]);
+    }
+
+    #[test]
+    fn it_skips_tile_groups_out_of_range() {
+        let tiles = vec![MegaTile::from_raw(5 << 4)];
+
+        assert_that(&find_tiles(&tiles, 1, &cv5s(), |_| true)).is_empty();
+    }
+
+    #[test]
+    fn it_reports_tiles_of_groups_the_tileset_does_not_have() {
+        let tiles = [0u16, 1, 2, 1, 0x7ff]
+            .iter()
+            .map(|group| MegaTile::from_raw(group << 4))
+            .collect::<Vec<_>>();
+
+        assert_that(&validate_map_tiles(&tiles[..2], &cv5s())).is_ok();
+        assert_that(&validate_map_tiles(&tiles, &cv5s())).is_err_containing(vec![2, 4]);
+    }
+
+    #[test]
+    fn it_counts_how_often_every_tile_is_used() {
+        let tiles = [5u16, 3, 5, 0x12, 3, 5, 7]
+            .iter()
+            .map(|&raw| MegaTile::from_raw(raw))
+            .collect::<Vec<_>>();
+
+        let histogram = tile_histogram(&tiles);
+
+        assert_that(&histogram.len()).is_equal_to(4);
+        assert_that(&histogram[&5]).is_equal_to(3);
+        assert_that(&histogram[&0x12]).is_equal_to(1);
+        assert_that(&most_common(&histogram, 3)).is_equal_to(vec![(5, 3), (3, 2), (7, 1)]);
+        assert_that(&most_common(&histogram, 10)).has_length(4);
+    }
+}
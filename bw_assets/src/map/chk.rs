@@ -10,6 +10,8 @@ use nom::{
 };
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use std::cmp::max;
 use std::convert::From;
@@ -29,6 +31,7 @@ pub enum Chunk {
     Dimensions(Dimensions),
     Sides(Vec<Side>),
     MegaTiles(Vec<MegaTile>),
+    Mask(Vec<u8>),
     Units(Vec<Unit>),
     StringData(StringData),
     Unknown,
@@ -61,6 +64,9 @@ pub fn parse_chunk(b: &[u8]) -> nom::IResult<&[u8], Chunk> {
             let size = header.size as usize / mem::size_of::<MegaTile>();
             map(count(parse_megatile, size), Chunk::MegaTiles)(remaining)
         }
+        ChunkName::Mask => {
+            map(take(header.size), |mask: &[u8]| Chunk::Mask(mask.to_vec()))(remaining)
+        }
         ChunkName::Unit => {
             const UNIT_BYTE_SIZE: usize = 36;
             let size = header.size as usize / UNIT_BYTE_SIZE;
@@ -80,6 +86,7 @@ pub enum ChunkName {
     Dimensions,
     Side,
     MegaTiles,
+    Mask,
     StringData,
     Unit,
     Unknown,
@@ -101,6 +108,7 @@ impl ChunkName {
             ChunkName::Dimensions => "DIM ".as_bytes(),
             ChunkName::Side => "SIDE".as_bytes(),
             ChunkName::MegaTiles => "MTXM".as_bytes(),
+            ChunkName::Mask => "MASK".as_bytes(),
             ChunkName::StringData => "STR ".as_bytes(),
             ChunkName::Unit => "UNIT".as_bytes(),
             ChunkName::Unknown => "????".as_bytes(),
@@ -138,6 +146,7 @@ pub fn parse_header(b: &[u8]) -> nom::IResult<&[u8], Header> {
         map(tag(ChunkName::MegaTiles.as_bytes()), |_| {
             ChunkName::MegaTiles
         }),
+        map(tag(ChunkName::Mask.as_bytes()), |_| ChunkName::Mask),
         map(tag(ChunkName::StringData.as_bytes()), |_| {
             ChunkName::StringData
         }),
@@ -180,6 +189,7 @@ pub fn parse_file_format_version(b: &[u8]) -> nom::IResult<&[u8], FileFormatVers
 }
 
 #[derive(Debug, Clone, Hash, FromPrimitive, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Tileset {
     Badlands = 00,
     SpacePlatform = 01,
@@ -250,10 +260,27 @@ impl MegaTile {
     pub const PIXEL_WIDTH: u32 = 32;
     pub const PIXEL_HEIGHT: u32 = 32;
 
+    pub fn from_raw(value: u16) -> MegaTile {
+        MegaTile(value)
+    }
+
+    /// Tile id as it is stored in the MTXM chunk.
+    pub fn raw(&self) -> u16 {
+        self.0
+    }
+
+    /// Tile group of the megatile in a classic CV5, from the 11 bits above
+    /// the subtile index. The top bit is ignored, as it is by the game.
     pub fn group_index(&self) -> usize {
         return ((self.0 >> 4) & 0x7ff) as usize;
     }
 
+    /// Tile group of the megatile in an extended CV5 with more than 2048
+    /// groups, from all 12 bits above the subtile index.
+    pub fn extended_group_index(&self) -> usize {
+        return (self.0 >> 4) as usize;
+    }
+
     pub fn subtile_index(&self) -> usize {
         return (self.0 & 0xf) as usize;
     }
@@ -269,6 +296,28 @@ impl From<u16> for MegaTile {
     }
 }
 
+impl PartialEq<u16> for MegaTile {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<MegaTile> for u16 {
+    fn eq(&self, other: &MegaTile) -> bool {
+        *self == other.0
+    }
+}
+
+/// Parses the fog of war of a MASK chunk, one byte for every megatile of a
+/// `width` by `height` map in row major order.
+///
+/// Bit `n` of a byte is set when the megatile starts covered by fog for
+/// player `n`, so a 0 byte is revealed to every player. The chunk must hold
+/// exactly one byte for every megatile.
+pub fn parse_mask(width: usize, height: usize, b: &[u8]) -> nom::IResult<&[u8], Vec<u8>> {
+    map(all_consuming(take(width * height)), <[u8]>::to_vec)(b)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StringData(Vec<Vec<u8>>);
 
@@ -497,6 +546,29 @@ mod tests {
             .is_equal_to(MegaTile(value));
     }
 
+    #[test]
+    fn it_parses_the_fog_mask() {
+        // a 3x2 map whose top left corner is revealed to player 0 and the
+        // bottom right tile to every player
+        let b = [0xfe, 0xfe, 0xff, 0xff, 0xff, 0x00];
+
+        let (_, mask) = parse_mask(3, 2, &b).unwrap();
+
+        assert_that(&mask).is_equal_to(b.to_vec());
+        assert_that(&(mask[0] & 1)).is_equal_to(0);
+        assert_that(&(mask[2] & 1)).is_equal_to(1);
+        assert_that(&parse_mask(3, 2, &b[..5])).is_err();
+        assert_that(&parse_mask(2, 2, &b)).is_err();
+
+        let mut chunk = b"MASK".to_vec();
+        chunk.write_u32::<LittleEndian>(b.len() as u32).unwrap();
+        chunk.extend_from_slice(&b);
+        assert_that(&parse_chunk(&chunk))
+            .is_ok()
+            .map(|(_, chunk)| chunk)
+            .is_equal_to(Chunk::Mask(b.to_vec()));
+    }
+
     #[test]
     fn it_parses_string_data() {
         let s1 = b"starcraft\0";
@@ -530,4 +602,20 @@ mod tests {
             .is_ok()
             .is_equal_to(expected);
     }
+
+    #[test]
+    fn it_compares_megatiles_to_raw_tile_ids() {
+        let raw = (0x123 << 4) | 0x5;
+        let megatile = MegaTile::from_raw(raw);
+
+        assert_that(&megatile.raw()).is_equal_to(raw);
+        assert_that(&(megatile == raw)).is_true();
+        assert_that(&(raw == megatile)).is_true();
+        assert_that(&(megatile == raw + 1)).is_false();
+        assert_that(&MegaTile::from_raw(megatile.raw())).is_equal_to(megatile.clone());
+        assert_that(&megatile.group_index()).is_equal_to(0x123);
+        assert_that(&MegaTile::from_raw(0x8000 | raw).group_index()).is_equal_to(0x123);
+        assert_that(&MegaTile::from_raw(0x8000 | raw).extended_group_index()).is_equal_to(0x923);
+        assert_that(&megatile.subtile_index()).is_equal_to(0x5);
+    }
 }
@@ -0,0 +1,150 @@
+//! Auto-tiling of Zerg creep.
+use super::MegaTile;
+
+/// Which part of a creep patch a tile is, chosen from the creep of its eight
+/// neighbours.
+///
+/// Outer corners and edges border tiles without creep. Inner corners are
+/// surrounded by creep on every side but are missing creep on one diagonal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CreepEdge {
+    Fill,
+    North,
+    East,
+    South,
+    West,
+    NorthEast,
+    SouthEast,
+    SouthWest,
+    NorthWest,
+    InnerNorthEast,
+    InnerSouthEast,
+    InnerSouthWest,
+    InnerNorthWest,
+}
+
+impl CreepEdge {
+    /// Picks the edge of a creep tile from the creep of its neighbours, given
+    /// as `[north, north east, east, south east, south, south west, west,
+    /// north west]`.
+    pub fn from_neighbours(neighbours: [bool; 8]) -> CreepEdge {
+        let [n, ne, e, se, s, sw, w, nw] = neighbours;
+
+        match (n, e, s, w) {
+            (false, _, _, false) => CreepEdge::NorthWest,
+            (false, false, _, _) => CreepEdge::NorthEast,
+            (_, _, false, false) => CreepEdge::SouthWest,
+            (_, false, false, _) => CreepEdge::SouthEast,
+            (false, _, _, _) => CreepEdge::North,
+            (_, false, _, _) => CreepEdge::East,
+            (_, _, false, _) => CreepEdge::South,
+            (_, _, _, false) => CreepEdge::West,
+            _ if !ne => CreepEdge::InnerNorthEast,
+            _ if !se => CreepEdge::InnerSouthEast,
+            _ if !sw => CreepEdge::InnerSouthWest,
+            _ if !nw => CreepEdge::InnerNorthWest,
+            _ => CreepEdge::Fill,
+        }
+    }
+}
+
+/// Selects the creep megatile to draw over every tile of a map from a grid of
+/// where there is creep, in row major order.
+///
+/// The tileset files do not say which megatile draws which edge, so the
+/// megatile of every edge is looked up with `megatile`. Tiles without creep,
+/// and tiles whose edge has no megatile, are `None`. Tiles outside of the map
+/// count as having creep, so creep that reaches the edge of the map has no
+/// border there.
+pub fn creep_overlay<F>(
+    creep_grid: &[bool],
+    width: usize,
+    height: usize,
+    megatile: F,
+) -> Vec<Option<MegaTile>>
+where
+    F: Fn(CreepEdge) -> Option<MegaTile>,
+{
+    let has_creep = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            return true;
+        }
+
+        creep_grid
+            .get(y as usize * width + x as usize)
+            .copied()
+            .unwrap_or(false)
+    };
+
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = ((i % width) as isize, (i / width) as isize);
+            if !has_creep(x, y) {
+                return None;
+            }
+
+            let neighbours = [
+                has_creep(x, y - 1),
+                has_creep(x + 1, y - 1),
+                has_creep(x + 1, y),
+                has_creep(x + 1, y + 1),
+                has_creep(x, y + 1),
+                has_creep(x - 1, y + 1),
+                has_creep(x - 1, y),
+                has_creep(x - 1, y - 1),
+            ];
+
+            megatile(CreepEdge::from_neighbours(neighbours))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    /// Megatiles of a tileset whose subtile `n` of group 1 is the `n`th edge.
+    fn megatile(edge: CreepEdge) -> Option<MegaTile> {
+        Some(MegaTile::from_raw((1 << 4) | edge as u16))
+    }
+
+    #[test]
+    fn it_borders_a_creep_patch_with_edges() {
+        // a 3x3 patch of creep in the middle of a 5x5 map
+        let creep_grid = (0..25)
+            .map(|i| (1..4).contains(&(i % 5)) && (1..4).contains(&(i / 5)))
+            .collect::<Vec<_>>();
+
+        let overlay = creep_overlay(&creep_grid, 5, 5, megatile);
+        let edge = |x: usize, y: usize| overlay[y * 5 + x].clone();
+
+        assert_that(&edge(0, 0)).is_none();
+        assert_that(&edge(1, 1)).is_equal_to(megatile(CreepEdge::NorthWest));
+        assert_that(&edge(2, 1)).is_equal_to(megatile(CreepEdge::North));
+        assert_that(&edge(3, 1)).is_equal_to(megatile(CreepEdge::NorthEast));
+        assert_that(&edge(1, 2)).is_equal_to(megatile(CreepEdge::West));
+        assert_that(&edge(2, 2)).is_equal_to(megatile(CreepEdge::Fill));
+        assert_that(&edge(3, 2)).is_equal_to(megatile(CreepEdge::East));
+        assert_that(&edge(1, 3)).is_equal_to(megatile(CreepEdge::SouthWest));
+        assert_that(&edge(2, 3)).is_equal_to(megatile(CreepEdge::South));
+        assert_that(&edge(3, 3)).is_equal_to(megatile(CreepEdge::SouthEast));
+    }
+
+    #[test]
+    fn it_uses_inner_corners_for_missing_diagonals() {
+        let mut neighbours = [true; 8];
+        neighbours[1] = false;
+
+        let edge = CreepEdge::from_neighbours(neighbours);
+
+        assert_that(&edge).is_equal_to(CreepEdge::InnerNorthEast);
+    }
+
+    #[test]
+    fn it_has_no_overlay_for_edges_without_a_megatile() {
+        let overlay = creep_overlay(&[true; 4], 2, 2, |_| None);
+
+        assert_that(&overlay).is_equal_to(vec![None; 4]);
+    }
+}
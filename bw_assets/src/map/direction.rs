@@ -0,0 +1,85 @@
+/// Compass direction on the tile grid, with north at the top of the map.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Every direction, clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Offset to the neighbouring tile in this direction, as `(x, y)` with y
+    /// growing towards the south.
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        let (dx, dy) = self.offset();
+        dx != 0 && dy != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_offsets_towards_the_neighbouring_tile() {
+        assert_that(&Direction::North.offset()).is_equal_to((0, -1));
+        assert_that(&Direction::SouthEast.offset()).is_equal_to((1, 1));
+        assert_that(&Direction::West.offset()).is_equal_to((-1, 0));
+        assert_that(&Direction::NorthEast.is_diagonal()).is_true();
+        assert_that(&Direction::South.is_diagonal()).is_false();
+    }
+
+    #[test]
+    fn it_cancels_out_the_opposite_offset() {
+        for &direction in Direction::ALL.iter() {
+            let (dx, dy) = direction.offset();
+            let (opposite_dx, opposite_dy) = direction.opposite().offset();
+
+            assert_that(&(dx + opposite_dx, dy + opposite_dy)).is_equal_to((0, 0));
+            assert_that(&direction.opposite().opposite()).is_equal_to(direction);
+        }
+    }
+}
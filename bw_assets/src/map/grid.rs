@@ -0,0 +1,80 @@
+//! Two dimensional views of grids that are stored flat in row major order,
+//! such as the walk grid of a map.
+
+/// Borrows a flat grid in row major order to index it by `(x, y)`.
+///
+/// The grid is `width` cells wide and as many whole rows tall as it holds;
+/// cells of an incomplete last row are out of range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid2D<'a, T> {
+    cells: &'a [T],
+    width: usize,
+}
+
+impl<'a, T> Grid2D<'a, T> {
+    pub fn new(cells: &'a [T], width: usize) -> Grid2D<'a, T> {
+        Grid2D { cells, width }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    /// Cell at `(x, y)`, or `None` if it is out of range.
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a T> {
+        if x >= self.width || y >= self.height() {
+            return None;
+        }
+
+        self.cells.get(y * self.width + x)
+    }
+
+    /// Cells of row `y`, or `None` if it is out of range.
+    pub fn row(&self, y: usize) -> Option<&'a [T]> {
+        if y >= self.height() {
+            return None;
+        }
+
+        Some(&self.cells[y * self.width..(y + 1) * self.width])
+    }
+
+    /// Every whole row of the grid, from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> {
+        let cells = self.cells;
+        let width = self.width;
+
+        (0..self.height()).map(move |y| &cells[y * width..(y + 1) * width])
+    }
+}
+
+/// Copies a flat grid in row major order into one `Vec` per row, see
+/// [`Grid2D`] to index the grid without copying it.
+pub fn grid_to_2d<T: Clone>(grid: &[T], width: usize) -> Vec<Vec<T>> {
+    Grid2D::new(grid, width).rows().map(<[T]>::to_vec).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_indexes_a_flat_grid_by_position() {
+        let walk_grid = [true, false, false, true, true, false, false];
+        let grid = Grid2D::new(&walk_grid, 3);
+
+        assert_that(&grid.height()).is_equal_to(2);
+        assert_that(&grid.get(1, 0)).is_equal_to(Some(&false));
+        assert_that(&grid.get(0, 1)).is_equal_to(Some(&true));
+        assert_that(&grid.get(3, 0)).is_none();
+        assert_that(&grid.get(0, 2)).is_none();
+        assert_that(&grid.row(1)).is_equal_to(Some(&walk_grid[3..6]));
+        assert_that(&grid_to_2d(&walk_grid, 3))
+            .is_equal_to(vec![vec![true, false, false], vec![true, true, false]]);
+        assert_that(&grid_to_2d(&walk_grid, 0)).is_empty();
+    }
+}
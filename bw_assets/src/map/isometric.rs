@@ -0,0 +1,80 @@
+//! Isometric grid of the terrain editor, mapped onto the megatile grid.
+//!
+//! The editor places terrain in diamonds that are 4 megatiles wide and 2
+//! megatiles tall. The diamond at isometric cell `(iso_x, iso_y)` is centered
+//! on the corner between megatiles at `(2 * (iso_x - iso_y), iso_x + iso_y)`,
+//! so `iso_x` grows towards the bottom right and `iso_y` towards the bottom
+//! left:
+//!
+//! ```text
+//!          /\
+//!         /  \
+//!        / 0,0\
+//!       /\    /\
+//!      /  \  /  \
+//!     / 0,1\/ 1,0\
+//!     \    /\    /
+//!      \  /  \  /
+//!       \/    \/
+//! ```
+//!
+//! A diamond covers three quarters of the 4 megatiles around its center and a
+//! quarter of the 4 megatiles to their left and right. Every megatile is
+//! therefore mostly covered by exactly one diamond, the one it belongs to.
+
+/// Megatiles that belong to an isometric cell, as `(x, y)` in row major
+/// order. Megatiles left of or above the map are left out, so cells along the
+/// top left edge of the map have fewer than 4 megatiles.
+pub fn iso_to_rect(iso_x: i32, iso_y: i32) -> Vec<(usize, usize)> {
+    let (center_x, center_y) = (2 * (iso_x - iso_y), iso_x + iso_y);
+
+    [(-1, -1), (0, -1), (-1, 0), (0, 0)]
+        .iter()
+        .map(|(dx, dy)| (center_x + dx, center_y + dy))
+        .filter(|&(x, y)| x >= 0 && y >= 0)
+        .map(|(x, y)| (x as usize, y as usize))
+        .collect()
+}
+
+/// Isometric cell the megatile at `(x, y)` belongs to, the inverse of
+/// [`iso_to_rect`].
+pub fn rect_to_iso(x: usize, y: usize) -> (i32, i32) {
+    let (x, y) = (x as i32, y as i32);
+
+    // the center is on one of the 4 corners of the megatile, on an even
+    // column whose half has the same parity as the row
+    let center_x = x + (x & 1);
+    let center_y = if (center_x / 2 + y) % 2 == 0 {
+        y
+    } else {
+        y + 1
+    };
+
+    ((center_y + center_x / 2) / 2, (center_y - center_x / 2) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_maps_an_iso_cell_to_its_megatiles() {
+        assert_that(&iso_to_rect(2, 1)).is_equal_to(vec![(1, 2), (2, 2), (1, 3), (2, 3)]);
+        assert_that(&iso_to_rect(0, 0)).is_equal_to(vec![(0, 0)]);
+        assert_that(&iso_to_rect(0, 3)).is_equal_to(vec![]);
+    }
+
+    #[test]
+    fn it_maps_every_megatile_back_to_its_iso_cell() {
+        for y in 0..16 {
+            for x in 0..16 {
+                let (iso_x, iso_y) = rect_to_iso(x, y);
+
+                assert_that(&iso_to_rect(iso_x, iso_y).contains(&(x, y)))
+                    .named(&format!("{}, {}", x, y))
+                    .is_true();
+            }
+        }
+    }
+}
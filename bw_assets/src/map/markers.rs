@@ -0,0 +1,66 @@
+//! Markers of the start locations and resources of a map, e.g. the colored
+//! dots of a minimap preview.
+use bw_core::Unit;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum MarkerKind {
+    StartLocation,
+    MineralField,
+    VespeneGeyser,
+}
+
+/// Marker of every start location, mineral field and vespene geyser placed on
+/// the map, as `(kind, x, y)` with the pixel position of the center of the
+/// unit. Markers are in the order the units are placed in the UNIT section.
+pub fn map_markers(placed_units: &[Unit]) -> Vec<(MarkerKind, u32, u32)> {
+    placed_units
+        .iter()
+        .filter_map(|unit| {
+            let kind = if unit.is_start_location() {
+                MarkerKind::StartLocation
+            } else if unit.is_mineral_field() {
+                MarkerKind::MineralField
+            } else if unit.is_vespene_geyser() {
+                MarkerKind::VespeneGeyser
+            } else {
+                return None;
+            };
+
+            Some((kind, unit.x() as u32, unit.y() as u32))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::chk::parse_placed_unit;
+    use super::*;
+    use spectral::prelude::*;
+
+    fn placed_unit(unit_id: u16, x: u16, y: u16) -> Unit {
+        let mut b = vec![0; 36];
+        b[4..6].copy_from_slice(&x.to_le_bytes());
+        b[6..8].copy_from_slice(&y.to_le_bytes());
+        b[8..10].copy_from_slice(&unit_id.to_le_bytes());
+
+        parse_placed_unit(&b).unwrap().1
+    }
+
+    #[test]
+    fn it_marks_the_start_locations_and_resources() {
+        let placed_units = [
+            placed_unit(0xd6, 400, 272),
+            placed_unit(0x07, 500, 300),
+            placed_unit(0xb0, 320, 200),
+            placed_unit(0xbc, 560, 240),
+            placed_unit(0xd6, 3700, 3800),
+        ];
+
+        assert_that(&map_markers(&placed_units)).is_equal_to(vec![
+            (MarkerKind::StartLocation, 400, 272),
+            (MarkerKind::MineralField, 320, 200),
+            (MarkerKind::VespeneGeyser, 560, 240),
+            (MarkerKind::StartLocation, 3700, 3800),
+        ]);
+    }
+}
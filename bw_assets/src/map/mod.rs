@@ -1,15 +1,36 @@
 //! Asset format for SCX and SCM Starcraft map formats
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::{Asset, Format, Handle},
     ecs::DenseVecStorage,
 };
 use bw_core::{Controllers, Unit};
-use nom::Finish;
+use snafu::{ResultExt, Snafu};
 
-mod chk;
+use crate::error::{finish_parse, ParseError};
 
+mod analysis;
+mod chk;
+mod creep;
+mod direction;
+mod grid;
+mod isometric;
+mod markers;
+mod region;
+mod walkability;
+
+pub use self::analysis::{find_tiles, most_common, tile_histogram, validate_map_tiles};
 pub use self::chk::{
-    Dimensions, FileFormatVersion, MegaTile, ScenarioType, Side, StringData, Tileset,
+    parse_mask, Dimensions, FileFormatVersion, MegaTile, ScenarioType, Side, StringData, Tileset,
+};
+pub use self::creep::{creep_overlay, CreepEdge};
+pub use self::direction::Direction;
+pub use self::grid::{grid_to_2d, Grid2D};
+pub use self::isometric::{iso_to_rect, rect_to_iso};
+pub use self::markers::{map_markers, MarkerKind};
+pub use self::region::{crop_tiles, playable_bounds, Rect};
+pub use self::walkability::{
+    chokepoints, detect_ramps, has_line_of_sight, walk_regions, Chokepoint, Ramp,
 };
 
 /// Every Starcraft map will have this file.
@@ -35,6 +56,11 @@ pub struct Map {
     pub dimensions: Dimensions,
     pub sides: Vec<Side>,
     pub megatiles: Vec<MegaTile>,
+
+    /// Raw MASK chunk, the fog of war the map starts with, or empty if the
+    /// map has none. See [`parse_mask`].
+    #[builder(default)]
+    pub mask: Vec<u8>,
     pub placed_units: Vec<Unit>,
     pub string_data: StringData,
 }
@@ -57,44 +83,40 @@ impl Map {
     }
 }
 
-pub type MapHandle = Handle<Map>;
+#[derive(Debug, Snafu)]
+pub enum MapError {
+    #[snafu(display("failed to open map archive: {}", source))]
+    OpenArchive { source: ceres_mpq::Error },
 
-impl Asset for Map {
-    const NAME: &'static str = "bw_assets::map::MapAsset";
-    type Data = Self;
-    type HandleStorage = DenseVecStorage<MapHandle>;
-}
+    #[snafu(display("failed to read {}: {}", MAP_FILE_NAME, source))]
+    ReadScenario { source: ceres_mpq::Error },
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct MapFormat;
+    #[snafu(display("failed to load chunks: {}", source))]
+    Chunks { source: ParseError },
 
-impl Format<Map> for MapFormat {
-    fn name(&self) -> &'static str {
-        "MapFormat"
-    }
+    #[snafu(display("Map is missing required components: {}", message))]
+    MissingComponents { message: String },
+}
 
-    fn import_simple(&self, bytes: Vec<u8>) -> amethyst::Result<Map> {
+impl Map {
+    /// Parses the bytes of a SCX or SCM file.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Map, MapError> {
         use std::io::Cursor;
 
         let cursor = Cursor::new(bytes);
 
         // A Starcraft map is just a regular MPQ archive with a single file inside.
-        let archive = ceres_mpq::Archive::open(cursor)?;
+        let archive = ceres_mpq::Archive::open(cursor).context(OpenArchive)?;
 
         // The Starcraft map format is divided into chunks denoted by the "chk"
         // format.
         // see: http://www.starcraftai.com/wiki/CHK_Format
-        let chunk_bytes = archive.read_file(MAP_FILE_NAME)?;
+        let chunk_bytes = archive.read_file(MAP_FILE_NAME).context(ReadScenario)?;
 
         let mut map_builder = MapBuilder::default();
 
-        let (_, chunks) = chk::parse_chunks(&chunk_bytes).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load chunks: {} at position {}",
-                err.code.description(),
-                chunk_bytes.len() - err.input.len()
-            )
-        })?;
+        let chunks =
+            finish_parse("chk", &chunk_bytes, chk::parse_chunks(&chunk_bytes)).context(Chunks)?;
 
         chunks.into_iter().for_each(|chunk| match chunk {
             chk::Chunk::ScenarioType(scenario_type) => {
@@ -118,6 +140,9 @@ impl Format<Map> for MapFormat {
             chk::Chunk::MegaTiles(megatiles) => {
                 map_builder.megatiles(megatiles);
             }
+            chk::Chunk::Mask(mask) => {
+                map_builder.mask(mask);
+            }
             chk::Chunk::Units(units) => {
                 map_builder.placed_units(units);
             }
@@ -127,10 +152,33 @@ impl Format<Map> for MapFormat {
             _ => {}
         });
 
-        let map = map_builder.build().map_err(|s| {
-            amethyst::Error::from_string(format!("Map is missing required components: {}", s))
-        })?;
+        map_builder
+            .build()
+            .map_err(|message| MapError::MissingComponents { message })
+    }
+}
+
+#[cfg(feature = "amethyst")]
+pub type MapHandle = Handle<Map>;
+
+#[cfg(feature = "amethyst")]
+impl Asset for Map {
+    const NAME: &'static str = "bw_assets::map::MapAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<MapHandle>;
+}
 
-        Ok(map)
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MapFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<Map> for MapFormat {
+    fn name(&self) -> &'static str {
+        "MapFormat"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> amethyst::Result<Map> {
+        Ok(Map::from_bytes(bytes)?)
     }
 }
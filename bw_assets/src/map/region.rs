@@ -0,0 +1,167 @@
+//! Rectangular regions of the megatile grid of a map.
+use super::MegaTile;
+use crate::tileset::{BuildFlag, CV5s};
+
+/// Rectangle of megatiles, with `(x, y)` the top left megatile.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The part of the rectangle inside of a `width` by `height` grid.
+    pub fn clamp(&self, width: usize, height: usize) -> Rect {
+        let (x, y) = (self.x.min(width), self.y.min(height));
+
+        Rect {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+}
+
+/// Megatiles of a region of a map, in row major order, along with the width
+/// and height of the region.
+///
+/// `tiles` are the `width * height` megatiles of the map in row major order.
+/// The region is clamped to the map, so the returned dimensions are smaller
+/// than `rect` when it reaches past the bottom or right edge of the map, and
+/// 0 when it starts outside of the map.
+pub fn crop_tiles(
+    tiles: &[MegaTile],
+    width: usize,
+    height: usize,
+    rect: Rect,
+) -> (Vec<MegaTile>, usize, usize) {
+    let height = if width == 0 {
+        0
+    } else {
+        height.min(tiles.len() / width)
+    };
+    let rect = rect.clamp(width, height);
+
+    let cropped = (rect.y..rect.y + rect.height)
+        .flat_map(|y| {
+            let start = y * width + rect.x;
+            tiles[start..start + rect.width].iter().cloned()
+        })
+        .collect();
+
+    (cropped, rect.width, rect.height)
+}
+
+/// The playable region of a map, in megatiles.
+///
+/// Maps are often framed by a border of terrain units cannot use. Starting
+/// from each edge of the map, whole rows and columns are skipped as long as
+/// every megatile in them is a border megatile: a placeholder tile group (see
+/// [`CV5::is_null`](crate::tileset::CV5::is_null)), an unbuildable tile group
+/// or a tile group the tileset does not have. The region is empty when the
+/// whole map is border.
+pub fn playable_bounds(tiles: &[MegaTile], width: usize, height: usize, cv5s: &CV5s) -> Rect {
+    let height = if width == 0 {
+        0
+    } else {
+        height.min(tiles.len() / width)
+    };
+    let is_border = |x: usize, y: usize| {
+        cv5s.get(&tiles[y * width + x]).map_or(true, |cv5| {
+            cv5.is_null() || cv5.build_flag() == BuildFlag::Unbuildable
+        })
+    };
+
+    let (mut top, mut bottom) = (0, height);
+    while top < bottom && (0..width).all(|x| is_border(x, top)) {
+        top += 1;
+    }
+    while bottom > top && (0..width).all(|x| is_border(x, bottom - 1)) {
+        bottom -= 1;
+    }
+    if top == bottom {
+        return Rect::default();
+    }
+
+    let (mut left, mut right) = (0, width);
+    while left < right && (top..bottom).all(|y| is_border(left, y)) {
+        left += 1;
+    }
+    while right > left && (top..bottom).all(|y| is_border(right - 1, y)) {
+        right -= 1;
+    }
+
+    Rect::new(left, top, right - left, bottom - top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn tiles(width: usize, height: usize) -> Vec<MegaTile> {
+        (0..width * height)
+            .map(|i| MegaTile::from_raw(i as u16))
+            .collect()
+    }
+
+    fn raw(tiles: &[MegaTile]) -> Vec<u16> {
+        tiles.iter().map(MegaTile::raw).collect()
+    }
+
+    #[test]
+    fn it_crops_a_region_of_the_map() {
+        let (cropped, width, height) = crop_tiles(&tiles(8, 6), 8, 6, Rect::new(2, 1, 3, 2));
+
+        assert_that(&(width, height)).is_equal_to((3, 2));
+        assert_that(&raw(&cropped)).is_equal_to(vec![10, 11, 12, 18, 19, 20]);
+    }
+
+    #[test]
+    fn it_skips_the_border_around_the_playable_region() {
+        // group 0 is a placeholder, group 1 is unbuildable and group 2 is dirt
+        let mut cv5 = vec![0; 52 * 3];
+        cv5[52 + 2] = 8 << 4;
+        cv5[52 + 20] = 1;
+        cv5[52 * 2 + 20] = 2;
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+
+        // a 7x6 map with a 2 tile border, whose playable region has a single
+        // unbuildable tile on its edge
+        let (width, height) = (7, 6);
+        let mut tiles = vec![MegaTile::from_raw(0); width * height];
+        for y in 2..4 {
+            for x in 2..5 {
+                tiles[y * width + x] = MegaTile::from_raw(2 << 4);
+            }
+        }
+        tiles[2 * width + 2] = MegaTile::from_raw(1 << 4);
+
+        assert_that(&playable_bounds(&tiles, width, height, &cv5s))
+            .is_equal_to(Rect::new(2, 2, 3, 2));
+        assert_that(&playable_bounds(&tiles[..width * 2], width, 2, &cv5s))
+            .is_equal_to(Rect::default());
+    }
+
+    #[test]
+    fn it_clamps_the_region_to_the_map() {
+        let (cropped, width, height) = crop_tiles(&tiles(8, 6), 8, 6, Rect::new(6, 4, 10, 10));
+        let (outside, ..) = crop_tiles(&tiles(8, 6), 8, 6, Rect::new(9, 0, 2, 2));
+
+        assert_that(&(width, height)).is_equal_to((2, 2));
+        assert_that(&raw(&cropped)).is_equal_to(vec![38, 39, 46, 47]);
+        assert_that(&outside).is_empty();
+    }
+}
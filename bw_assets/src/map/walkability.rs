@@ -0,0 +1,511 @@
+//! Analysis of the walkable space of a map at walktile (8x8 pixel)
+//! resolution.
+use boolinator::Boolinator;
+
+use super::{MegaTile, MEGATILE_SIDE_LEN};
+use crate::tileset::{CV5s, Elevation, VF4s, VF4};
+
+/// Labels every connected area of walkable walktiles with a region number.
+///
+/// Returns a label for every walktile in row major order and the number of
+/// regions. Regions are numbered from 1 and unwalkable walktiles are labeled
+/// 0. Walktiles are connected to their four horizontal and vertical
+/// neighbours only, so two areas that only touch diagonally are separate
+/// regions.
+pub fn walk_regions(walk_grid: &[bool], width: usize, height: usize) -> (Vec<u32>, usize) {
+    let len = walk_grid.len().min(width * height);
+    let mut labels = vec![0; walk_grid.len()];
+    let mut region_count = 0;
+    let mut stack = vec![];
+
+    for start in 0..len {
+        if !walk_grid[start] || labels[start] != 0 {
+            continue;
+        }
+
+        region_count += 1;
+        let label = region_count as u32;
+        labels[start] = label;
+        stack.push(start);
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            let neighbours = [
+                (x > 0).as_some_from(|| i - 1),
+                (x + 1 < width).as_some_from(|| i + 1),
+                (y > 0).as_some_from(|| i - width),
+                (y + 1 < height).as_some_from(|| i + width),
+            ];
+
+            for &neighbour in neighbours.iter().flatten() {
+                if neighbour < len && walk_grid[neighbour] && labels[neighbour] == 0 {
+                    labels[neighbour] = label;
+                    stack.push(neighbour);
+                }
+            }
+        }
+    }
+
+    (labels, region_count)
+}
+
+/// A narrow passage inside of a walkable region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chokepoint {
+    /// Walktile in the middle of the passage.
+    pub center: (usize, usize),
+
+    /// Number of walkable walktiles across the passage at its center.
+    pub width: usize,
+
+    /// Label of the region from [`walk_regions`] that the passage is in.
+    pub region: u32,
+}
+
+/// Directions a passage can run in: horizontally and vertically.
+const AXES: [(i64, i64); 2] = [(1, 0), (0, 1)];
+
+/// Finds the narrow passages that join two open areas of walkable space.
+///
+/// The heuristic works on the clearance of every walktile, its chessboard
+/// distance to the closest unwalkable walktile or edge of the map:
+///
+/// 1. A walktile lies on a passage running along an axis when its clearance
+///    is a ridge across the axis (no neighbour across it is clearer) and a
+///    minimum along the axis (no neighbour along it is less clear).
+/// 2. The passage is only narrow if walking along the axis in both
+///    directions reaches a clearer walktile of the same region before the
+///    first unwalkable walktile. Dead ends and uniformly narrow areas are not
+///    chokepoints.
+/// 3. Touching candidates are merged into a single chokepoint, centered on
+///    the candidate closest to their average position.
+///
+/// `regions` are the labels returned by [`walk_regions`] for the same grid.
+pub fn chokepoints(
+    walk_grid: &[bool],
+    regions: &[u32],
+    width: usize,
+    height: usize,
+) -> Vec<Chokepoint> {
+    let clearance = clearance(walk_grid, width, height);
+    let index = |x: i64, y: i64| {
+        (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height)
+            .as_some_from(|| y as usize * width + x as usize)
+    };
+    let clearance_at = |x: i64, y: i64| index(x, y).map_or(0, |i| clearance[i]);
+    let region_at = |x: i64, y: i64| index(x, y).and_then(|i| regions.get(i).copied());
+
+    let opens_up = |x: i64, y: i64, (dx, dy): (i64, i64)| {
+        let (region, c) = (region_at(x, y), clearance_at(x, y));
+        (1..)
+            .map(|k| (x + dx * k, y + dy * k))
+            .take_while(|&(x, y)| clearance_at(x, y) > 0 && region_at(x, y) == region)
+            .any(|(x, y)| clearance_at(x, y) > c)
+    };
+    // number of walkable walktiles across a passage running along the axis
+    let cross_width = |x: i64, y: i64, (dx, dy): (i64, i64)| {
+        let run = |sign: i64| {
+            (1..)
+                .take_while(|k| clearance_at(x + dy * sign * k, y + dx * sign * k) > 0)
+                .count()
+        };
+        run(-1) + 1 + run(1)
+    };
+
+    let mut axes = vec![None; clearance.len()];
+    for (i, axis) in axes.iter_mut().enumerate() {
+        let (x, y) = ((i % width) as i64, (i / width) as i64);
+        let c = clearance[i];
+        if c == 0 || region_at(x, y).map_or(true, |region| region == 0) {
+            continue;
+        }
+
+        *axis = AXES
+            .iter()
+            .copied()
+            .filter(|&(dx, dy)| {
+                clearance_at(x - dy, y - dx) <= c
+                    && clearance_at(x + dy, y + dx) <= c
+                    && clearance_at(x - dx, y - dy) >= c
+                    && clearance_at(x + dx, y + dy) >= c
+                    && opens_up(x, y, (-dx, -dy))
+                    && opens_up(x, y, (dx, dy))
+            })
+            .min_by_key(|&axis| cross_width(x, y, axis));
+    }
+
+    let mut chokepoints = vec![];
+    let mut visited = vec![false; axes.len()];
+    let candidates = (0..axes.len())
+        .filter(|&i| axes[i].is_some())
+        .collect::<Vec<_>>();
+    for start in candidates {
+        if visited[start] {
+            continue;
+        }
+
+        // merge every candidate touching this one, diagonals included
+        let mut group = vec![];
+        let mut stack = vec![start];
+        visited[start] = true;
+        while let Some(i) = stack.pop() {
+            group.push(i);
+            let (x, y) = ((i % width) as i64, (i / width) as i64);
+            for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                if let Some(j) = index(x + dx, y + dy) {
+                    if axes[j].is_some() && !visited[j] {
+                        visited[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        group.sort_unstable();
+
+        let n = group.len() as i64;
+        let (sum_x, sum_y) = group.iter().fold((0, 0), |(sum_x, sum_y), &i| {
+            (sum_x + (i % width) as i64, sum_y + (i / width) as i64)
+        });
+        let center = *group
+            .iter()
+            .min_by_key(|&&i| {
+                let (x, y) = ((i % width) as i64, (i / width) as i64);
+                (x * n - sum_x).pow(2) + (y * n - sum_y).pow(2)
+            })
+            .unwrap();
+        let (x, y) = (center % width, center / width);
+
+        chokepoints.push(Chokepoint {
+            center: (x, y),
+            width: cross_width(x as i64, y as i64, axes[center].unwrap()),
+            region: regions[center],
+        });
+    }
+
+    chokepoints
+}
+
+/// Whether the walktiles between `from` and `to` can be seen through.
+///
+/// Walks a Bresenham line from `from` to `to` and fails at the first walktile
+/// whose minitile blocks view. The walktile at `from` is not checked, so a
+/// unit standing on a view blocking minitile can still see out of it. Walktiles
+/// outside of the map, or whose megatile references data outside of the
+/// tileset, block view. Differences in elevation are not taken into account.
+pub fn has_line_of_sight(
+    from: (i32, i32),
+    to: (i32, i32),
+    tiles: &[MegaTile],
+    width: usize,
+    cv5s: &CV5s,
+    vf4s: &VF4s,
+) -> bool {
+    let blocks_view = |x: i32, y: i32| {
+        x < 0
+            || y < 0
+            || minitile_flags((x as usize, y as usize), tiles, width, cv5s, vf4s)
+                .map_or(true, |vf4| vf4.blocks_view())
+    };
+
+    let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (step_x, step_y) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let (mut x, mut y) = from;
+    let mut error = dx + dy;
+
+    while (x, y) != to {
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+
+        if blocks_view(x, y) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Walktiles flagged as ramp that are connected to each other, the
+/// transition between two elevations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ramp {
+    /// Top left walktile of the bounding box of the ramp.
+    pub top_left: (usize, usize),
+
+    /// Bottom right walktile of the bounding box of the ramp, inclusive.
+    pub bottom_right: (usize, usize),
+
+    /// Lowest elevation of the walkable ground next to the ramp.
+    pub low: Elevation,
+
+    /// Highest elevation of the walkable ground next to the ramp.
+    pub high: Elevation,
+}
+
+/// Groups the ramp walktiles of a map into ramps.
+///
+/// Ramp walktiles are connected to their four horizontal and vertical
+/// neighbours. The elevations a ramp connects are read from the walkable
+/// walktiles that border it; a ramp without walkable neighbours takes the
+/// elevations of its own walktiles. Ramps are ordered by their first walktile
+/// in row major order.
+pub fn detect_ramps(tiles: &[MegaTile], width: usize, cv5s: &CV5s, vf4s: &VF4s) -> Vec<Ramp> {
+    let side = MEGATILE_SIDE_LEN as usize;
+    let walk_width = width * side;
+    let walk_height = tiles.len().checked_div(width).unwrap_or(0) * side;
+    let flags = (0..walk_width * walk_height)
+        .map(|i| minitile_flags((i % walk_width, i / walk_width), tiles, width, cv5s, vf4s))
+        .collect::<Vec<_>>();
+    let is_ramp = |i: usize| flags[i].map_or(false, VF4::is_ramp);
+
+    let mut visited = vec![false; flags.len()];
+    let mut ramps = vec![];
+    let mut stack = vec![];
+    for start in 0..flags.len() {
+        if visited[start] || !is_ramp(start) {
+            continue;
+        }
+
+        visited[start] = true;
+        stack.push(start);
+        let mut top_left = (usize::MAX, usize::MAX);
+        let mut bottom_right = (0, 0);
+        let mut ramp_elevations = vec![];
+        let mut ground_elevations = vec![];
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % walk_width, i / walk_width);
+            top_left = (top_left.0.min(x), top_left.1.min(y));
+            bottom_right = (bottom_right.0.max(x), bottom_right.1.max(y));
+            ramp_elevations.extend(flags[i].map(VF4::elevation));
+
+            let neighbours = [
+                (x > 0).as_some_from(|| i - 1),
+                (x + 1 < walk_width).as_some_from(|| i + 1),
+                (y > 0).as_some_from(|| i - walk_width),
+                (y + 1 < walk_height).as_some_from(|| i + walk_width),
+            ];
+            for &neighbour in neighbours.iter().flatten() {
+                if is_ramp(neighbour) {
+                    if !visited[neighbour] {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                } else if let Some(vf4) = flags[neighbour].filter(|vf4| vf4.is_walkable()) {
+                    ground_elevations.push(vf4.elevation());
+                }
+            }
+        }
+
+        let elevations = if ground_elevations.is_empty() {
+            ramp_elevations
+        } else {
+            ground_elevations
+        };
+        ramps.push(Ramp {
+            top_left,
+            bottom_right,
+            low: elevations.iter().min().copied().unwrap_or(Elevation::Low),
+            high: elevations.iter().max().copied().unwrap_or(Elevation::Low),
+        });
+    }
+
+    ramps
+}
+
+/// Flags of the minitile under a walktile, or `None` if the walktile is
+/// outside of the map or its megatile references data outside of the tileset.
+pub(crate) fn minitile_flags<'a>(
+    (x, y): (usize, usize),
+    tiles: &[MegaTile],
+    width: usize,
+    cv5s: &CV5s,
+    vf4s: &'a VF4s,
+) -> Option<&'a VF4> {
+    let side = MEGATILE_SIDE_LEN as usize;
+    if x / side >= width {
+        return None;
+    }
+
+    let megatile = tiles.get(y / side * width + x / side)?;
+    let minitiles = vf4s.get(&cv5s.get(megatile)?[megatile])?;
+
+    minitiles.get(y % side * side + x % side)
+}
+
+/// Chessboard distance of every walktile to the closest unwalkable walktile,
+/// counting the outside of the map as unwalkable. Unwalkable walktiles are 0.
+fn clearance(walk_grid: &[bool], width: usize, height: usize) -> Vec<u32> {
+    let mut distances = (0..width * height)
+        .map(|i| match walk_grid.get(i) {
+            Some(true) => u32::MAX,
+            _ => 0,
+        })
+        .collect::<Vec<_>>();
+    let distance_at = |distances: &[u32], x: i64, y: i64| {
+        if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+            0
+        } else {
+            distances[y as usize * width + x as usize]
+        }
+    };
+
+    // one pass from the top left and one from the bottom right
+    let forward = [(-1, 0), (-1, -1), (0, -1), (1, -1)];
+    let backward = [(1, 0), (1, 1), (0, 1), (-1, 1)];
+    let passes = [
+        (forward, (0..width * height).collect::<Vec<_>>()),
+        (backward, (0..width * height).rev().collect::<Vec<_>>()),
+    ];
+    for (neighbours, order) in passes.iter() {
+        for &i in order {
+            if distances[i] == 0 {
+                continue;
+            }
+
+            let (x, y) = ((i % width) as i64, (i / width) as i64);
+            let nearest = neighbours
+                .iter()
+                .map(|(dx, dy)| distance_at(&distances, x + dx, y + dy))
+                .min()
+                .unwrap_or(0);
+            distances[i] = distances[i].min(nearest.saturating_add(1));
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn grid(rows: &[&str]) -> Vec<bool> {
+        rows.iter()
+            .flat_map(|row| row.chars().map(|c| c == '.'))
+            .collect()
+    }
+
+    #[test]
+    fn it_labels_separated_pockets_as_different_regions() {
+        let walk_grid = grid(&[
+            "..#...", //
+            "..#...", //
+            "###.#.", //
+            "...#..", //
+        ]);
+
+        let (labels, region_count) = walk_regions(&walk_grid, 6, 4);
+
+        assert_that(&region_count).is_equal_to(3);
+        assert_that(&labels[0..6].to_vec()).is_equal_to(vec![1, 1, 0, 2, 2, 2]);
+        assert_that(&labels[6..12].to_vec()).is_equal_to(vec![1, 1, 0, 2, 2, 2]);
+        assert_that(&labels[12..18].to_vec()).is_equal_to(vec![0, 0, 0, 2, 0, 2]);
+        assert_that(&labels[18..24].to_vec()).is_equal_to(vec![3, 3, 3, 0, 2, 2]);
+    }
+
+    #[test]
+    fn it_finds_the_corridor_between_two_rooms() {
+        // two 8x8 rooms joined by a corridor 2 walktiles wide and 3 long
+        let walk_grid = grid(&[
+            "........###........", //
+            "........###........", //
+            "........###........", //
+            "...................", //
+            "...................", //
+            "........###........", //
+            "........###........", //
+            "........###........", //
+        ]);
+        let (regions, _) = walk_regions(&walk_grid, 19, 8);
+
+        let chokepoints = chokepoints(&walk_grid, &regions, 19, 8);
+
+        assert_that(&chokepoints).is_equal_to(vec![Chokepoint {
+            center: (9, 3),
+            width: 2,
+            region: 1,
+        }]);
+    }
+
+    #[test]
+    fn it_does_not_find_chokepoints_in_an_open_room() {
+        let walk_grid = vec![true; 100];
+        let (regions, _) = walk_regions(&walk_grid, 10, 10);
+
+        assert_that(&chokepoints(&walk_grid, &regions, 10, 10)).is_empty();
+    }
+
+    fn tileset() -> (CV5s, VF4s) {
+        // group 0 is open ground, group 1 has a single view blocking
+        // minitile at (1, 1)
+        let mut cv5 = vec![0; 52 * 2];
+        for reference in cv5[52 + 20..].chunks_mut(2) {
+            reference[0] = 1;
+        }
+        let mut vf4 = [0x0001u16; 32];
+        vf4[16 + 5] = 0x0008;
+        let vf4 = vf4.iter().flat_map(|flags| flags.to_le_bytes().to_vec());
+
+        (
+            CV5s::from_bytes(&cv5).unwrap(),
+            VF4s::from_bytes(&vf4.collect::<Vec<_>>()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn it_is_blocked_by_a_view_blocking_minitile() {
+        let (cv5s, vf4s) = tileset();
+        let tiles = [0, 1, 0]
+            .iter()
+            .map(|&group: &u16| MegaTile::from_raw(group << 4))
+            .collect::<Vec<_>>();
+        let los = |from, to| has_line_of_sight(from, to, &tiles, 3, &cv5s, &vf4s);
+
+        assert_that(&los((0, 1), (11, 1))).is_false();
+        assert_that(&los((11, 1), (0, 1))).is_false();
+        assert_that(&los((0, 2), (11, 2))).is_true();
+        assert_that(&los((5, 1), (11, 1))).is_true();
+        assert_that(&los((0, 2), (12, 2))).is_false();
+    }
+
+    #[test]
+    fn it_finds_a_ramp_between_low_and_high_ground() {
+        // group 0 is low ground, group 1 is a mid ground ramp and group 2 is
+        // high ground
+        let mut cv5 = vec![0; 52 * 3];
+        for group in 1..3 {
+            for reference in cv5[52 * group + 20..52 * (group + 1)].chunks_mut(2) {
+                reference[0] = group as u8;
+            }
+        }
+        let vf4 = [0x0001u16, 0x0013, 0x0005]
+            .iter()
+            .flat_map(|&flags| vec![flags; 16])
+            .flat_map(|flags| flags.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let (cv5s, vf4s) = (
+            CV5s::from_bytes(&cv5).unwrap(),
+            VF4s::from_bytes(&vf4).unwrap(),
+        );
+        // low ground, ramp, high ground and a row of low ground below
+        let tiles = [0, 1, 2, 0, 0, 0]
+            .iter()
+            .map(|&group: &u16| MegaTile::from_raw(group << 4))
+            .collect::<Vec<_>>();
+
+        assert_that(&detect_ramps(&tiles, 3, &cv5s, &vf4s)).is_equal_to(vec![Ramp {
+            top_left: (4, 0),
+            bottom_right: (7, 3),
+            low: Elevation::Low,
+            high: Elevation::High,
+        }]);
+    }
+}
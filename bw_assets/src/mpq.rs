@@ -7,14 +7,16 @@
 //! This module provides an implementation that opens an MPQ Archive and supports
 //!
 
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::{Asset, Handle},
     ecs::DenseVecStorage,
 };
 use ceres_mpq::Archive;
 use snafu::Snafu;
+#[cfg(feature = "amethyst")]
+use std::collections::VecDeque;
 use std::{
-    collections::VecDeque,
     fs::File,
     io::{BufReader, Read, Seek},
     path::Path,
@@ -51,6 +53,24 @@ pub type Result<T> = std::result::Result<T, Error>;
 /// MPQ is an archive that we can read files from.
 pub trait MPQ {
     fn read_file(&self, file_name: &str) -> Result<Vec<u8>>;
+
+    /// Reads a file, calling `on_progress` with the number of bytes
+    /// decompressed so far, e.g. to draw a loading screen while large files
+    /// such as VR4s and GRPs are read.
+    ///
+    /// The counts only ever increase and the last count is the length of the
+    /// file. Archives that decompress a file all at once report a single count
+    /// after the file is read, which is what the default implementation does.
+    fn read_file_with_progress(
+        &self,
+        file_name: &str,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<Vec<u8>> {
+        let file = self.read_file(file_name)?;
+        on_progress(file.len());
+
+        Ok(file)
+    }
 }
 
 /// Newtype that wraps a ceres mpq archive and implements the read file trait.
@@ -112,16 +132,29 @@ impl ArcMPQ {
     pub fn read_file(&self, file_name: &str) -> Result<Vec<u8>> {
         self.0.read_file(file_name)
     }
+
+    /// Reads a file, reporting the bytes decompressed so far, see
+    /// [`MPQ::read_file_with_progress`].
+    pub fn read_file_with_progress(
+        &self,
+        file_name: &str,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<Vec<u8>> {
+        self.0.read_file_with_progress(file_name, on_progress)
+    }
 }
 
+#[cfg(feature = "amethyst")]
 pub type MPQHandle = Handle<ArcMPQ>;
 
+#[cfg(feature = "amethyst")]
 impl Asset for ArcMPQ {
     const NAME: &'static str = "bw_assets_mpq";
     type Data = Self;
     type HandleStorage = DenseVecStorage<MPQHandle>;
 }
 
+#[cfg(feature = "amethyst")]
 /// Amethyst asset source for a queue of MPQ files.
 ///
 /// If multiple MPQ archives contain the same file, the archive at front of the
@@ -130,6 +163,7 @@ pub struct MPQSource {
     queue: VecDeque<ArcMPQ>,
 }
 
+#[cfg(feature = "amethyst")]
 impl MPQSource {
     pub fn new() -> MPQSource {
         MPQSource {
@@ -146,6 +180,21 @@ impl MPQSource {
     }
 }
 
+#[cfg(feature = "amethyst")]
+/// Converts an amethyst asset path into the path format used inside of MPQ
+/// archives.
+///
+/// Amethyst asset paths are usually written with forward slashes, while MPQ
+/// archives store their file names with backslashes. File names inside of an
+/// archive are looked up by hash, which is already case-insensitive, so only
+/// the separators need to be normalized.
+fn to_archive_path(file_name: &str) -> String {
+    file_name
+        .trim_start_matches(|c| c == '/' || c == '\\')
+        .replace('/', "\\")
+}
+
+#[cfg(feature = "amethyst")]
 /// MPQ acts similar to a directory because it is an archive of files. We can
 /// treat it as an Amethyst source.
 impl amethyst::assets::Source for MPQSource {
@@ -156,9 +205,11 @@ impl amethyst::assets::Source for MPQSource {
     }
 
     fn load(&self, file_name: &str) -> std::result::Result<Vec<u8>, amethyst::Error> {
+        let file_name = to_archive_path(file_name);
+
         let mut errors = vec![];
         for mpq in self.queue.iter() {
-            match mpq.read_file(file_name) {
+            match mpq.read_file(&file_name) {
                 Ok(file) => return Ok(file),
                 Err(err) => errors.push(err),
             }
@@ -172,7 +223,7 @@ impl amethyst::assets::Source for MPQSource {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "amethyst"))]
 mod tests {
     use super::*;
     use spectral::prelude::*;
@@ -197,6 +248,55 @@ mod tests {
         }
     }
 
+    /// Decompresses files in 4 byte sectors.
+    struct SectorMPQ(TestMPQ);
+
+    impl MPQ for SectorMPQ {
+        fn read_file(&self, file_name: &str) -> Result<Vec<u8>> {
+            self.read_file_with_progress(file_name, &mut |_| {})
+        }
+
+        fn read_file_with_progress(
+            &self,
+            file_name: &str,
+            on_progress: &mut dyn FnMut(usize),
+        ) -> Result<Vec<u8>> {
+            let compressed = self.0.read_file(file_name)?;
+            let mut file = Vec::with_capacity(compressed.len());
+            for sector in compressed.chunks(4) {
+                file.extend_from_slice(sector);
+                on_progress(file.len());
+            }
+
+            Ok(file)
+        }
+    }
+
+    #[test]
+    fn it_reports_the_bytes_read_so_far() {
+        let files = || {
+            TestMPQ(hashmap! {
+                "unit\\terran\\marine.grp".to_string() => (0..10).collect(),
+            })
+        };
+        let read = |mpq: ArcMPQ| {
+            let mut counts = vec![];
+            let file = mpq
+                .read_file_with_progress("unit\\terran\\marine.grp", &mut |count| {
+                    counts.push(count)
+                })
+                .unwrap();
+            (file, counts)
+        };
+
+        let (file, counts) = read(ArcMPQ::new(SectorMPQ(files())));
+        assert_that(&file).is_equal_to((0..10).collect::<Vec<_>>());
+        assert_that(&counts).is_equal_to(vec![4, 8, 10]);
+
+        let (_, counts) = read(ArcMPQ::new(files()));
+        assert_that(&counts).is_equal_to(vec![10]);
+    }
+
     #[test]
     fn mpq_at_the_front_should_take_precedence() {
         use amethyst::assets::Source;
@@ -216,4 +316,23 @@ mod tests {
             .is_ok()
             .is_equal_to(vec![4, 5, 6])
     }
+
+    #[test]
+    fn it_loads_files_with_forward_slash_paths() {
+        use amethyst::assets::Source;
+
+        let mpq = ArcMPQ::new(TestMPQ(hashmap! {
+            "tileset\\badlands.cv5".to_string() => vec![1,2,3],
+        }));
+
+        let mut mpq_source = MPQSource::new();
+        mpq_source.push_front(mpq);
+
+        assert_that(&mpq_source.load("tileset/badlands.cv5"))
+            .is_ok()
+            .is_equal_to(vec![1, 2, 3]);
+        assert_that(&mpq_source.load("/tileset/badlands.cv5"))
+            .is_ok()
+            .is_equal_to(vec![1, 2, 3]);
+    }
 }
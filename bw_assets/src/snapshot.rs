@@ -0,0 +1,12 @@
+//! Pretty RON snapshots of parsed assets.
+//!
+//! Comparing the output of a parser against a snapshot committed next to its
+//! test catches regressions such as a column read from the wrong offset,
+//! which round trip tests cannot detect.
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::Serialize;
+
+/// Serializes a parsed asset to pretty RON.
+pub fn snapshot<T: Serialize>(value: &T) -> String {
+    to_string_pretty(value, PrettyConfig::default()).expect("parsed assets serialize to RON")
+}
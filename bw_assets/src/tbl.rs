@@ -0,0 +1,110 @@
+//! Types and Parsers for the String Table (TBL) Format
+//!
+//! A TBL file starts with the number of strings and the offset of every
+//! string from the start of the file, as little endian `u16`. Each string is
+//! terminated by a null byte. The dat files reference the strings of
+//! `stat_txt.tbl` by 1-based ids, where 0 means no string.
+use nom::{multi::length_count, number::complete::le_u16, IResult};
+
+use crate::error::{finish_parse, ParseError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StringTable(Vec<String>);
+
+impl StringTable {
+    pub fn new(strings: Vec<String>) -> StringTable {
+        StringTable(strings)
+    }
+
+    pub fn from_bytes(b: &[u8]) -> Result<StringTable, ParseError> {
+        let offsets = finish_parse("tbl", b, parse_offsets(b))?;
+
+        let strings = offsets
+            .iter()
+            .enumerate()
+            .map(|(i, &offset)| {
+                let string = b
+                    .get(offset as usize..)
+                    .ok_or_else(|| ParseError::Malformed {
+                        asset: "tbl",
+                        description: "string starts past the end of the file".to_string(),
+                        position: 2 + i * 2,
+                    })?;
+                let len = string.iter().position(|&c| c == 0).unwrap_or(string.len());
+
+                // the strings are single byte characters of the windows code
+                // page, which match unicode for every letter of the names
+                Ok(string[..len].iter().map(|&c| c as char).collect())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(StringTable(strings))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// String of a 1-based id as it is stored, or `None` if the id is 0 or
+    /// out of range.
+    pub fn get(&self, id: u16) -> Option<&str> {
+        let index = (id as usize).checked_sub(1)?;
+
+        self.0.get(index).map(String::as_str)
+    }
+
+    /// String of a 1-based id without the control bytes it starts with, such
+    /// as the color of the text, or `None` if the id is 0 or out of range.
+    pub fn display_name(&self, id: u16) -> Option<&str> {
+        self.get(id)
+            .map(|string| string.trim_start_matches(|c: char| c.is_control()))
+    }
+
+    /// Whether the display name of a 1-based id is `name`, ignoring case.
+    pub fn is_named(&self, id: u16, name: &str) -> bool {
+        self.display_name(id).map_or(false, |display_name| {
+            display_name.eq_ignore_ascii_case(name)
+        })
+    }
+}
+
+fn parse_offsets(b: &[u8]) -> IResult<&[u8], Vec<u16>> {
+    length_count(le_u16, le_u16)(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_reads_null_terminated_strings_by_id() {
+        let mut b = vec![2, 0, 6, 0, 13, 0];
+        b.extend_from_slice(b"Marine\0\x04Ghost\0");
+
+        let tbl = StringTable::from_bytes(&b).unwrap();
+
+        assert_that(&tbl.len()).is_equal_to(2);
+        assert_that(&tbl.get(0)).is_none();
+        assert_that(&tbl.get(1)).is_equal_to(Some("Marine"));
+        assert_that(&tbl.get(2)).is_equal_to(Some("\u{4}Ghost"));
+        assert_that(&tbl.display_name(2)).is_equal_to(Some("Ghost"));
+        assert_that(&tbl.is_named(2, "GHOST")).is_true();
+        assert_that(&tbl.get(3)).is_none();
+    }
+
+    #[test]
+    fn it_rejects_offsets_past_the_end_of_the_file() {
+        let b = [1, 0, 5, 0];
+
+        assert_that(&StringTable::from_bytes(&b)).is_err_containing(ParseError::Malformed {
+            asset: "tbl",
+            description: "string starts past the end of the file".to_string(),
+            position: 2,
+        });
+    }
+}
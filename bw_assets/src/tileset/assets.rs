@@ -0,0 +1,391 @@
+use nom::{Finish, IResult};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use snafu::{ResultExt, Snafu};
+use std::{fs, io, mem, path::Path, sync::Arc};
+
+use super::super::map::Tileset;
+use super::{
+    cv5::parse_cv5s, vf4::parse_vf4s, vr4::parse_vr4s, vx4::parse_vx4s, wpe::parse_wpes, CV5s,
+    RenderError, VF4s, VR4s, VX4s, WPEs,
+};
+use crate::error::ParseError;
+
+/// Error returned by [`parse_tileset_bytes`], naming the file that failed to
+/// parse.
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum TilesetError {
+    #[snafu(display("invalid cv5 file: {}", source))]
+    InvalidCV5 { source: ParseError },
+
+    #[snafu(display("invalid vx4 file: {}", source))]
+    InvalidVX4 { source: ParseError },
+
+    #[snafu(display("invalid vf4 file: {}", source))]
+    InvalidVF4 { source: ParseError },
+
+    #[snafu(display("invalid vr4 file: {}", source))]
+    InvalidVR4 { source: ParseError },
+
+    #[snafu(display("invalid wpe file: {}", source))]
+    InvalidWPE { source: ParseError },
+}
+
+/// Every file of a tileset.
+///
+/// The files are shared so the same tileset can be handed to the renderer and
+/// to background tasks, such as rendering the minimap, without copying it.
+/// Every file is immutable once loaded, so `TilesetAssets` is `Send + Sync`
+/// and can be read from any number of threads at once.
+#[derive(Clone, Debug)]
+pub struct TilesetAssets {
+    pub cv5s: Arc<CV5s>,
+    pub vf4s: Arc<VF4s>,
+    pub vx4s: Arc<VX4s>,
+    pub vr4s: Arc<VR4s>,
+    pub wpes: Arc<WPEs>,
+}
+
+impl TilesetAssets {
+    /// Clones the handles to the tileset files without copying the files, e.g.
+    /// to move the tileset into a worker thread.
+    pub fn clone_shared(&self) -> TilesetAssets {
+        TilesetAssets {
+            cv5s: Arc::clone(&self.cv5s),
+            vf4s: Arc::clone(&self.vf4s),
+            vx4s: Arc::clone(&self.vx4s),
+            vr4s: Arc::clone(&self.vr4s),
+            wpes: Arc::clone(&self.wpes),
+        }
+    }
+
+    /// Checks that the VX4 and VF4 files have an entry for every megatile, see
+    /// [`vx4_vf4_consistent`], that every minitile of every megatile is in
+    /// range of the VR4 file and that every pixel of every minitile is in range
+    /// of the palette.
+    pub fn validate(&self) -> Result<(), RenderError> {
+        if !vx4_vf4_consistent(&self.vx4s, &self.vf4s) {
+            return Err(RenderError::FlagCountMismatch {
+                vx4s: self.vx4s.len(),
+                vf4s: self.vf4s.len(),
+            });
+        }
+
+        for vx4 in self.vx4s.iter().flatten() {
+            if vx4.index() >= self.vr4s.len() {
+                return Err(RenderError::MinitileOutOfRange {
+                    minitile: vx4.index(),
+                    len: self.vr4s.len(),
+                });
+            }
+        }
+
+        for vr4 in self.vr4s.iter().flatten() {
+            if usize::from(vr4) >= self.wpes.len() {
+                return Err(RenderError::ColorOutOfRange {
+                    color: usize::from(vr4),
+                    len: self.wpes.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bytes the files of the tileset take up on the heap, e.g. to evict
+    /// tilesets from a cache by the memory they use.
+    ///
+    /// Files shared with other tilesets are counted in full, see
+    /// [`TilesetAssets::clone_shared`].
+    pub fn memory_footprint(&self) -> usize {
+        self.cv5s.heap_size()
+            + self.vf4s.heap_size()
+            + self.vx4s.heap_size()
+            + self.vr4s.heap_size()
+            + self.wpes.heap_size()
+    }
+
+    /// Number of colors in the palette of the synthetic tileset.
+    pub const SYNTHETIC_PALETTE_LEN: usize = 4;
+
+    /// Creates a tiny tileset that is consistent with itself, primarily for
+    /// testing code that consumes a tileset without shipping real tileset
+    /// files.
+    ///
+    /// The tileset has a single tile group whose megatiles all point to the
+    /// first megatile. Every minitile of that megatile is the same walkable
+    /// minitile, with the pixels cycling through a palette of
+    /// [`TilesetAssets::SYNTHETIC_PALETTE_LEN`] grays.
+    pub fn synthetic() -> TilesetAssets {
+        let [cv5, vf4, vx4, vr4, wpe] = synthetic_files();
+
+        let parsed = "synthetic tileset is valid";
+        TilesetAssets {
+            cv5s: Arc::new(parse_cv5s(&cv5).expect(parsed).1),
+            vf4s: Arc::new(parse_vf4s(&vf4).expect(parsed).1),
+            vx4s: Arc::new(parse_vx4s(&vx4).expect(parsed).1),
+            vr4s: Arc::new(parse_vr4s(&vr4).expect(parsed).1),
+            wpes: Arc::new(parse_wpes(&wpe).expect(parsed).1),
+        }
+    }
+
+    /// Loads a tileset from a directory of files extracted from the game, e.g.
+    /// `badlands.cv5`, `badlands.vf4`, `badlands.vx4`, `badlands.vr4` and
+    /// `badlands.wpe`.
+    ///
+    /// Errors name the file that is missing or could not be parsed.
+    pub fn from_dir(dir: &Path, tileset: Tileset) -> io::Result<TilesetAssets> {
+        let path = |extension: &str| dir.join(format!("{}.{}", tileset.file_name(), extension));
+
+        Ok(TilesetAssets {
+            cv5s: Arc::new(read_file(&path("cv5"), parse_cv5s)?),
+            vf4s: Arc::new(read_file(&path("vf4"), parse_vf4s)?),
+            vx4s: Arc::new(read_file(&path("vx4"), parse_vx4s)?),
+            vr4s: Arc::new(read_file(&path("vr4"), parse_vr4s)?),
+            wpes: Arc::new(read_file(&path("wpe"), parse_wpes)?),
+        })
+    }
+}
+
+/// Whether the VX4 and VF4 files have the same number of megatiles.
+///
+/// Both files are indexed by the megatile references of the CV5 file, so a
+/// mismatch means they are from different tilesets, which would render the
+/// graphics of one tileset with the walkability of another.
+pub fn vx4_vf4_consistent(vx4s: &VX4s, vf4s: &VF4s) -> bool {
+    vx4s.len() == vf4s.len()
+}
+
+/// Parses the five files of a tileset from memory, without going through an
+/// amethyst `Format`, e.g. to benchmark or profile the parsers.
+pub fn parse_tileset_bytes(
+    cv5: &[u8],
+    vx4: &[u8],
+    vf4: &[u8],
+    vr4: &[u8],
+    wpe: &[u8],
+) -> Result<TilesetAssets, TilesetError> {
+    Ok(TilesetAssets {
+        cv5s: Arc::new(CV5s::from_bytes(cv5).context(InvalidCV5)?),
+        vf4s: Arc::new(VF4s::from_bytes(vf4).context(InvalidVF4)?),
+        vx4s: Arc::new(VX4s::from_bytes(vx4).context(InvalidVX4)?),
+        vr4s: Arc::new(VR4s::from_bytes(vr4).context(InvalidVR4)?),
+        wpes: Arc::new(WPEs::from_bytes(wpe).context(InvalidWPE)?),
+    })
+}
+
+/// Loads several tilesets from a directory of files extracted from the game,
+/// see [`TilesetAssets::from_dir`].
+///
+/// The tilesets are loaded in parallel with the `parallel` feature. Every
+/// tileset has its own result, in the order of `tilesets`, so a tileset that
+/// fails to load does not prevent the others from loading.
+pub fn load_tilesets(dir: &Path, tilesets: &[Tileset]) -> Vec<io::Result<TilesetAssets>> {
+    let load = |tileset: &Tileset| TilesetAssets::from_dir(dir, tileset.clone());
+
+    #[cfg(feature = "parallel")]
+    let tilesets = tilesets.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let tilesets = tilesets.iter();
+
+    tilesets.map(load).collect()
+}
+
+pub(super) fn read_file<T>(path: &Path, parse: fn(&[u8]) -> IResult<&[u8], T>) -> io::Result<T> {
+    let b = fs::read(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+    let (_, parsed) = parse(&b).finish().map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "failed to load {}: {} at position {}",
+                path.display(),
+                err.code.description(),
+                b.len() - err.input.len()
+            ),
+        )
+    })?;
+
+    Ok(parsed)
+}
+
+/// Heap bytes of a list of blocks with room for `capacity` blocks.
+pub(super) fn blocks_heap_size<T>(capacity: usize, blocks: &[Vec<T>]) -> usize {
+    capacity * mem::size_of::<Vec<T>>()
+        + blocks
+            .iter()
+            .map(|block| block.capacity() * mem::size_of::<T>())
+            .sum::<usize>()
+}
+
+/// Bytes of the cv5, vf4, vx4, vr4 and wpe files of the synthetic tileset.
+fn synthetic_files() -> [Vec<u8>; 5] {
+    let cv5 = vec![0; 52];
+    let vf4 = [1u8, 0].repeat(VX4s::BLOCK_SIZE);
+    let vx4 = vec![0; VX4s::BLOCK_SIZE * 2];
+    let vr4 = (0..VR4s::BLOCK_SIZE)
+        .map(|i| (i % TilesetAssets::SYNTHETIC_PALETTE_LEN) as u8)
+        .collect::<Vec<_>>();
+    let wpe = (0..TilesetAssets::SYNTHETIC_PALETTE_LEN)
+        .flat_map(|i| {
+            let gray = (i * 255 / (TilesetAssets::SYNTHETIC_PALETTE_LEN - 1)) as u8;
+            vec![gray, gray, gray, 0]
+        })
+        .collect::<Vec<_>>();
+
+    [cv5, vf4, vx4, vr4, wpe]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{render_tile_id, RenderProgress, TilesetAtlas};
+    use super::*;
+    use spectral::prelude::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn it_can_be_shared_between_threads() {
+        assert_send_sync::<TilesetAssets>();
+        assert_send_sync::<CV5s>();
+        assert_send_sync::<VF4s>();
+        assert_send_sync::<VX4s>();
+        assert_send_sync::<VR4s>();
+        assert_send_sync::<WPEs>();
+        assert_send_sync::<TilesetAtlas>();
+        assert_send_sync::<RenderProgress>();
+    }
+
+    #[test]
+    fn it_reports_the_memory_of_the_files() {
+        let small = TilesetAssets::synthetic();
+        let [cv5, vf4, vx4, _, wpe] = synthetic_files();
+        let vr4 = vec![0; VR4s::BLOCK_SIZE * 10];
+        let large = parse_tileset_bytes(&cv5, &vx4, &vf4, &vr4, &wpe).unwrap();
+
+        assert_that(&small.memory_footprint()).is_greater_than(VR4s::BLOCK_SIZE);
+        assert_that(&large.memory_footprint())
+            .is_greater_than_or_equal_to(small.memory_footprint() + VR4s::BLOCK_SIZE * 9);
+    }
+
+    #[test]
+    fn it_shares_files_when_cloned() {
+        let assets = TilesetAssets::synthetic();
+        let shared = assets.clone_shared();
+
+        assert_that(&Arc::ptr_eq(&assets.vr4s, &shared.vr4s)).is_true();
+        assert_that(&Arc::strong_count(&assets.cv5s)).is_equal_to(2);
+
+        let pixels = thread::spawn(move || render_tile_id(0, &shared).unwrap())
+            .join()
+            .unwrap();
+        assert_that(&pixels).is_equal_to(render_tile_id(0, &assets).unwrap());
+    }
+
+    #[test]
+    fn it_loads_a_tileset_from_a_directory() {
+        let dir = std::env::temp_dir().join("bw_assets_tileset_from_dir_test");
+        fs::create_dir_all(&dir).unwrap();
+        for (extension, b) in ["cv5", "vf4", "vx4", "vr4", "wpe"]
+            .iter()
+            .zip(synthetic_files().iter())
+        {
+            fs::write(dir.join(format!("jungle.{}", extension)), b).unwrap();
+        }
+
+        let assets = TilesetAssets::from_dir(&dir, Tileset::Jungle).unwrap();
+        assert_that(&assets.wpes.len()).is_equal_to(TilesetAssets::SYNTHETIC_PALETTE_LEN);
+
+        fs::remove_file(dir.join("jungle.vx4")).unwrap();
+        let err = TilesetAssets::from_dir(&dir, Tileset::Jungle).unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::NotFound);
+        assert_that(&err.to_string()).contains("jungle.vx4");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_loads_several_tilesets_at_once() {
+        let dir = std::env::temp_dir().join("bw_assets_load_tilesets_test");
+        fs::create_dir_all(&dir).unwrap();
+        for tileset in [Tileset::Jungle, Tileset::Desert].iter() {
+            for (extension, b) in ["cv5", "vf4", "vx4", "vr4", "wpe"]
+                .iter()
+                .zip(synthetic_files().iter())
+            {
+                let file_name = format!("{}.{}", tileset.file_name(), extension);
+                fs::write(dir.join(file_name), b).unwrap();
+            }
+        }
+
+        let loaded = load_tilesets(&dir, &[Tileset::Jungle, Tileset::Arctic, Tileset::Desert]);
+
+        assert_that(&loaded).has_length(3);
+        assert_that(&loaded[0].is_ok()).is_true();
+        assert_that(&loaded[1].as_ref().unwrap_err().to_string()).contains("ice.cv5");
+        assert_that(&loaded[2].is_ok()).is_true();
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_parses_the_five_files_from_memory() {
+        let [cv5, vf4, vx4, vr4, wpe] = synthetic_files();
+
+        let assets = parse_tileset_bytes(&cv5, &vx4, &vf4, &vr4, &wpe).unwrap();
+        assert_that(&assets.validate()).is_ok();
+        assert_that(&assets.wpes.len()).is_equal_to(TilesetAssets::SYNTHETIC_PALETTE_LEN);
+
+        let err = parse_tileset_bytes(&cv5, &vx4, &vf4, &vr4[1..], &wpe).unwrap_err();
+        assert_that(&err.to_string()).starts_with("invalid vr4 file");
+    }
+
+    #[test]
+    fn it_validates_the_references_of_the_tileset() {
+        let assets = TilesetAssets::synthetic();
+        assert_that(&assets.validate()).is_ok();
+
+        let missing_colors = TilesetAssets {
+            wpes: Arc::new(WPEs::empty()),
+            ..assets.clone_shared()
+        };
+        assert_that(&missing_colors.validate())
+            .is_err_containing(RenderError::ColorOutOfRange { color: 0, len: 0 });
+
+        let missing_minitiles = TilesetAssets {
+            vr4s: Arc::new(VR4s::empty()),
+            ..assets
+        };
+        let out_of_range = RenderError::MinitileOutOfRange {
+            minitile: 0,
+            len: 0,
+        };
+        assert_that(&missing_minitiles.validate()).is_err_containing(out_of_range);
+    }
+
+    #[test]
+    fn it_rejects_vx4_and_vf4_files_of_different_tilesets() {
+        let [_, vf4, ..] = synthetic_files();
+        let assets = TilesetAssets {
+            vf4s: Arc::new(VF4s::from_bytes(&vf4.repeat(2)).unwrap()),
+            ..TilesetAssets::synthetic()
+        };
+
+        assert_that(&vx4_vf4_consistent(&assets.vx4s, &assets.vf4s)).is_false();
+        assert_that(&assets.validate())
+            .is_err_containing(RenderError::FlagCountMismatch { vx4s: 1, vf4s: 2 });
+    }
+
+    #[test]
+    fn it_creates_a_consistent_synthetic_tileset() {
+        let assets = TilesetAssets::synthetic();
+
+        assert_that(&assets.cv5s.len()).is_equal_to(1);
+        assert_that(&assets.wpes.len()).is_equal_to(TilesetAssets::SYNTHETIC_PALETTE_LEN);
+
+        let pixels = render_tile_id(0, &assets).unwrap();
+        assert_that(&pixels[0..6].to_vec()).is_equal_to(vec![0, 0, 0, 85, 85, 85]);
+    }
+}
@@ -0,0 +1,496 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::io::{self, Read, Write};
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use super::{RenderOptions, RenderProgress, VR4s, WPEs, VX4};
+
+/// Every minitile of a tileset rendered into a single RGBA image.
+///
+/// Minitiles are packed left to right, top to bottom, in the same order as
+/// they are stored in the VR4 file. Horizontally flipped minitiles are not
+/// stored in the atlas; renderers should flip the texture coordinates instead.
+#[derive(Debug)]
+pub struct TilesetAtlas {
+    len: usize,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl TilesetAtlas {
+    /// RGBA
+    pub const CHANNELS: usize = 4;
+
+    /// Magic at the start of a serialized atlas, followed by the version of
+    /// the layout. Caches written with another version are rejected.
+    pub const CACHE_MAGIC: &'static [u8; 4] = b"BWTA";
+
+    const CACHE_VERSION: u32 = 1;
+
+    /// Number of minitiles in each row of the atlas.
+    pub const COLUMNS: usize = 64;
+
+    pub fn build(vr4s: &VR4s, wpes: &WPEs) -> TilesetAtlas {
+        TilesetAtlas::build_with_progress(vr4s, wpes, &RenderProgress::new())
+    }
+
+    /// Builds the atlas, advancing `progress` once for every minitile.
+    pub fn build_with_progress(
+        vr4s: &VR4s,
+        wpes: &WPEs,
+        progress: &RenderProgress,
+    ) -> TilesetAtlas {
+        TilesetAtlas::build_with_options(vr4s, wpes, &RenderOptions::default(), progress)
+    }
+
+    /// Builds the atlas with the colors of `options.color_output` and the
+    /// transparency of `options.transparent_index`, advancing `progress` once
+    /// for every minitile.
+    pub fn build_with_options(
+        vr4s: &VR4s,
+        wpes: &WPEs,
+        options: &RenderOptions,
+        progress: &RenderProgress,
+    ) -> TilesetAtlas {
+        let side_length = VR4s::MINITILE_SIDE_LENGTH;
+        let rows = (vr4s.len() + TilesetAtlas::COLUMNS - 1) / TilesetAtlas::COLUMNS;
+        let width = TilesetAtlas::COLUMNS * side_length;
+        let height = rows.max(1) * side_length;
+
+        progress.start(vr4s.len());
+        #[cfg(feature = "parallel")]
+        let minitiles = vr4s.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let minitiles = vr4s.iter();
+        let minitiles = minitiles
+            .map(|minitile| {
+                let mut pixels = Vec::with_capacity(minitile.len() * TilesetAtlas::CHANNELS);
+                for vr4 in minitile {
+                    let is_transparent = options
+                        .transparent_index
+                        .map_or(false, |index| usize::from(vr4) == index as usize);
+
+                    pixels.extend_from_slice(&wpes[vr4].color(options.color_output));
+                    pixels.push(if is_transparent { 0 } else { u8::MAX });
+                }
+                progress.advance();
+
+                pixels
+            })
+            .collect::<Vec<_>>();
+
+        let mut pixels = vec![0; width * height * TilesetAtlas::CHANNELS];
+        let row_length = side_length * TilesetAtlas::CHANNELS;
+        for (i, minitile) in minitiles.iter().enumerate() {
+            let x = (i % TilesetAtlas::COLUMNS) * side_length;
+            let y = (i / TilesetAtlas::COLUMNS) * side_length;
+
+            for (yj, row) in minitile.chunks(row_length).enumerate() {
+                let offset = ((y + yj) * width + x) * TilesetAtlas::CHANNELS;
+                pixels[offset..offset + row_length].copy_from_slice(row);
+            }
+        }
+
+        TilesetAtlas {
+            len: vr4s.len(),
+            width: width as u32,
+            height: height as u32,
+            pixels,
+        }
+    }
+
+    /// Number of minitiles in the atlas.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Pixel position of the top left corner of a minitile in the atlas.
+    pub fn minitile_position(&self, index: usize) -> (u32, u32) {
+        let side_length = VR4s::MINITILE_SIDE_LENGTH;
+        let x = (index % TilesetAtlas::COLUMNS) * side_length;
+        let y = (index / TilesetAtlas::COLUMNS) * side_length;
+
+        (x as u32, y as u32)
+    }
+
+    /// Texture coordinates of a minitile as `[left, top, right, bottom]`,
+    /// normalized to 0.0-1.0.
+    pub fn minitile_uv(&self, index: usize) -> [f32; 4] {
+        let (x, y) = self.minitile_position(index);
+        let side_length = VR4s::MINITILE_SIDE_LENGTH as u32;
+        let (width, height) = (self.width as f32, self.height as f32);
+
+        [
+            x as f32 / width,
+            y as f32 / height,
+            (x + side_length) as f32 / width,
+            (y + side_length) as f32 / height,
+        ]
+    }
+
+    /// Width and height of the atlas at a mipmap level.
+    pub fn mipmap_dimensions(&self, level: usize) -> (u32, u32) {
+        (
+            (self.width >> level.min(31)).max(1),
+            (self.height >> level.min(31)).max(1),
+        )
+    }
+
+    /// Generates `levels` mipmaps of the atlas using a box filter.
+    ///
+    /// The first level is the atlas itself and every level after it is half
+    /// the size of the previous level in each dimension. Brood War graphics
+    /// are indexed colors so they cannot be filtered until the palette has
+    /// been applied; the mipmaps are generated from the RGBA pixels.
+    pub fn generate_mipmaps(&self, levels: usize) -> Vec<Vec<u8>> {
+        let mut mipmaps: Vec<Vec<u8>> = Vec::with_capacity(levels);
+
+        for level in 0..levels {
+            let mipmap = match mipmaps.last() {
+                None => self.pixels.clone(),
+                Some(previous) => {
+                    let (previous_width, previous_height) = self.mipmap_dimensions(level - 1);
+                    let (width, height) = self.mipmap_dimensions(level);
+
+                    downsample(
+                        previous,
+                        (previous_width as usize, previous_height as usize),
+                        (width as usize, height as usize),
+                    )
+                }
+            };
+            mipmaps.push(mipmap);
+        }
+
+        mipmaps
+    }
+
+    /// Writes the atlas in a compact binary layout, to be cached on disk and
+    /// read back with [`TilesetAtlas::deserialize`] instead of rendering it
+    /// again.
+    ///
+    /// The layout is [`TilesetAtlas::CACHE_MAGIC`] and the layout version,
+    /// followed by the number of minitiles, the width and the height as little
+    /// endian `u32`, followed by the RGBA pixels. The texture coordinates of
+    /// the minitiles follow from their index and the dimensions of the atlas,
+    /// so they are not stored.
+    pub fn serialize<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(TilesetAtlas::CACHE_MAGIC)?;
+        for value in [
+            TilesetAtlas::CACHE_VERSION,
+            self.len as u32,
+            self.width,
+            self.height,
+        ]
+        .iter()
+        {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        w.write_all(&self.pixels)
+    }
+
+    /// Reads an atlas written by [`TilesetAtlas::serialize`].
+    ///
+    /// Fails with [`io::ErrorKind::InvalidData`] if the magic or the layout
+    /// version do not match, if there are more minitiles than a VX4 can
+    /// reference, or if the dimensions do not fit the minitiles. Fails with
+    /// [`io::ErrorKind::UnexpectedEof`] if pixels are missing.
+    pub fn deserialize<R: Read>(r: &mut R) -> io::Result<TilesetAtlas> {
+        let invalid = |description: &str| io::Error::new(io::ErrorKind::InvalidData, description);
+
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != TilesetAtlas::CACHE_MAGIC {
+            return Err(invalid("not a tileset atlas cache"));
+        }
+
+        let mut read_u32 = || -> io::Result<u32> {
+            let mut b = [0; 4];
+            r.read_exact(&mut b)?;
+            Ok(u32::from_le_bytes(b))
+        };
+        if read_u32()? != TilesetAtlas::CACHE_VERSION {
+            return Err(invalid("tileset atlas cache has an unsupported version"));
+        }
+        let (len, width, height) = (read_u32()? as usize, read_u32()?, read_u32()?);
+        if len > VX4::MAX_INDEX + 1 {
+            return Err(invalid(
+                "tileset atlas cache has more minitiles than a VX4 can reference",
+            ));
+        }
+
+        let side_length = VR4s::MINITILE_SIDE_LENGTH;
+        let rows = (len + TilesetAtlas::COLUMNS - 1) / TilesetAtlas::COLUMNS;
+        if width as usize != TilesetAtlas::COLUMNS * side_length
+            || height as usize != rows.max(1) * side_length
+        {
+            return Err(invalid(
+                "tileset atlas cache dimensions do not fit its minitiles",
+            ));
+        }
+
+        // only allocate the pixels that are actually there
+        let pixels_len = width as usize * height as usize * TilesetAtlas::CHANNELS;
+        let mut pixels = Vec::new();
+        r.take(pixels_len as u64).read_to_end(&mut pixels)?;
+        if pixels.len() != pixels_len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(TilesetAtlas {
+            len,
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+#[cfg(feature = "image")]
+impl TilesetAtlas {
+    /// Writes the atlas to disk as an RGBA PNG.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+        image::save_buffer(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        )
+    }
+
+    /// Writes the atlas to disk as an RGBA PNG, along with a JSON file of the
+    /// texture coordinates of every minitile next to it.
+    ///
+    /// The JSON file has the same name as the PNG with a `json` extension.
+    pub fn save_png_with_uvs<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+        let path = path.as_ref();
+        self.save_png(path)?;
+        std::fs::write(path.with_extension("json"), self.uv_table_json())?;
+
+        Ok(())
+    }
+
+    fn uv_table_json(&self) -> String {
+        let uvs = (0..self.len)
+            .map(|i| {
+                let [left, top, right, bottom] = self.minitile_uv(i);
+                format!("[{}, {}, {}, {}]", left, top, right, bottom)
+            })
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"width\": {}, \"height\": {}, \"uvs\": [{}]}}",
+            self.width,
+            self.height,
+            uvs.join(", ")
+        )
+    }
+}
+
+/// Averages every 2x2 block of pixels into a single pixel.
+fn downsample(pixels: &[u8], from: (usize, usize), to: (usize, usize)) -> Vec<u8> {
+    let (from_width, from_height) = from;
+    let (to_width, to_height) = to;
+    let channels = TilesetAtlas::CHANNELS;
+
+    let mut downsampled = vec![0; to_width * to_height * channels];
+    for y in 0..to_height {
+        for x in 0..to_width {
+            let xs = [(x * 2).min(from_width - 1), (x * 2 + 1).min(from_width - 1)];
+            let ys = [
+                (y * 2).min(from_height - 1),
+                (y * 2 + 1).min(from_height - 1),
+            ];
+
+            for channel in 0..channels {
+                let sum: u32 = ys
+                    .iter()
+                    .flat_map(|&yi| xs.iter().map(move |&xi| (xi, yi)))
+                    .map(|(xi, yi)| pixels[(yi * from_width + xi) * channels + channel] as u32)
+                    .sum();
+
+                downsampled[(y * to_width + x) * channels + channel] = ((sum + 2) / 4) as u8;
+            }
+        }
+    }
+
+    downsampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{vr4::parse_vr4s, wpe::parse_wpes};
+    use super::*;
+    use spectral::prelude::*;
+
+    fn atlas(minitile_count: usize) -> TilesetAtlas {
+        let vr4_bytes = (0..minitile_count * VR4s::BLOCK_SIZE)
+            .map(|i| (i % 2) as u8)
+            .collect::<Vec<_>>();
+        let wpe_bytes = vec![[0, 0, 0, 0], [255, 255, 255, 0]].concat();
+
+        let (_, vr4s) = parse_vr4s(&vr4_bytes).unwrap();
+        let (_, wpes) = parse_wpes(&wpe_bytes).unwrap();
+
+        TilesetAtlas::build(&vr4s, &wpes)
+    }
+
+    #[test]
+    fn it_packs_minitiles_into_rows() {
+        let atlas = atlas(TilesetAtlas::COLUMNS + 1);
+
+        assert_that(&atlas.width()).is_equal_to(512);
+        assert_that(&atlas.height()).is_equal_to(16);
+        assert_that(&atlas.minitile_position(TilesetAtlas::COLUMNS)).is_equal_to((0, 8));
+        assert_that(&atlas.pixels()[0..8].to_vec())
+            .is_equal_to(vec![0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn it_renders_the_transparent_index_with_zero_alpha() {
+        let vr4_bytes = (0..VR4s::BLOCK_SIZE)
+            .map(|i| (i % 3) as u8)
+            .collect::<Vec<_>>();
+        let wpe_bytes = [[0, 0, 0, 0], [10, 10, 10, 0], [20, 20, 20, 0]].concat();
+        let (_, vr4s) = parse_vr4s(&vr4_bytes).unwrap();
+        let (_, wpes) = parse_wpes(&wpe_bytes).unwrap();
+        let options = RenderOptions {
+            transparent_index: Some(2),
+            ..RenderOptions::default()
+        };
+
+        let atlas =
+            TilesetAtlas::build_with_options(&vr4s, &wpes, &options, &RenderProgress::new());
+        let opaque = TilesetAtlas::build(&vr4s, &wpes);
+
+        assert_that(&atlas.pixels()[0..12].to_vec())
+            .is_equal_to(vec![0, 0, 0, 255, 10, 10, 10, 255, 20, 20, 20, 0]);
+        assert_that(&opaque.pixels()[8..12].to_vec()).is_equal_to(vec![20, 20, 20, 255]);
+    }
+
+    #[test]
+    fn it_reports_progress_for_every_minitile() {
+        let vr4_bytes = vec![0; VR4s::BLOCK_SIZE * 10];
+        let (_, vr4s) = parse_vr4s(&vr4_bytes).unwrap();
+        let (_, wpes) = parse_wpes(&[0, 0, 0, 0]).unwrap();
+        let progress = RenderProgress::new();
+
+        assert_that(&progress.fraction()).is_equal_to(0.0);
+        TilesetAtlas::build_with_progress(&vr4s, &wpes, &progress);
+        assert_that(&progress.completed()).is_equal_to(10);
+        assert_that(&progress.fraction()).is_equal_to(1.0);
+        assert_that(&progress.is_done()).is_true();
+    }
+
+    #[test]
+    fn it_normalizes_minitile_uvs() {
+        let atlas = atlas(TilesetAtlas::COLUMNS + 1);
+
+        let right = 1.0 / TilesetAtlas::COLUMNS as f32;
+
+        assert_that(&atlas.minitile_uv(0)).is_equal_to([0.0, 0.0, right, 0.5]);
+        assert_that(&atlas.minitile_uv(TilesetAtlas::COLUMNS)).is_equal_to([0.0, 0.5, right, 1.0]);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn it_saves_the_atlas_as_png() {
+        let atlas = atlas(TilesetAtlas::COLUMNS + 1);
+        let path = std::env::temp_dir().join("bw_assets_tileset_atlas_test.png");
+
+        atlas.save_png_with_uvs(&path).unwrap();
+        let png = image::open(&path).unwrap().to_rgba8();
+
+        assert_that(&png.width()).is_equal_to(atlas.width());
+        assert_that(&png.height()).is_equal_to(atlas.height());
+        assert_that(&png.into_raw()).is_equal_to(atlas.pixels().to_vec());
+        assert_that(&path.with_extension("json").exists()).is_true();
+
+        std::fs::remove_file(path.with_extension("json")).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn it_round_trips_through_the_binary_cache() {
+        let atlas = atlas(TilesetAtlas::COLUMNS + 1);
+        let mut cache = Vec::new();
+        atlas.serialize(&mut cache).unwrap();
+
+        let cached = TilesetAtlas::deserialize(&mut &cache[..]).unwrap();
+
+        assert_that(&cache[0..4]).is_equal_to(&b"BWTA"[..]);
+        assert_that(&cached.len()).is_equal_to(atlas.len());
+        assert_that(&cached.width()).is_equal_to(atlas.width());
+        assert_that(&cached.height()).is_equal_to(atlas.height());
+        assert_that(&cached.pixels()).is_equal_to(atlas.pixels());
+        assert_that(&cached.minitile_uv(TilesetAtlas::COLUMNS))
+            .is_equal_to(atlas.minitile_uv(TilesetAtlas::COLUMNS));
+    }
+
+    #[test]
+    fn it_rejects_stale_caches() {
+        let mut cache = Vec::new();
+        atlas(1).serialize(&mut cache).unwrap();
+        cache[4] = 0;
+
+        let err = TilesetAtlas::deserialize(&mut &cache[..]).unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::InvalidData);
+
+        let err = TilesetAtlas::deserialize(&mut &b"BWTS"[..]).unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_rejects_caches_with_too_many_minitiles() {
+        let len = (VX4::MAX_INDEX + 1) as u32 + TilesetAtlas::COLUMNS as u32;
+        let height = (len / TilesetAtlas::COLUMNS as u32) * VR4s::MINITILE_SIDE_LENGTH as u32;
+        let mut cache = b"BWTA".to_vec();
+        for value in [TilesetAtlas::CACHE_VERSION, len, 512, height].iter() {
+            cache.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let err = TilesetAtlas::deserialize(&mut &cache[..]).unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_rejects_truncated_caches() {
+        let mut cache = Vec::new();
+        atlas(1).serialize(&mut cache).unwrap();
+        cache.pop();
+
+        let err = TilesetAtlas::deserialize(&mut &cache[..]).unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn it_halves_each_dimension_per_mipmap_level() {
+        let atlas = atlas(TilesetAtlas::COLUMNS * 2);
+        let mipmaps = atlas.generate_mipmaps(3);
+
+        assert_that(&mipmaps).has_length(3);
+        assert_that(&mipmaps[0].len()).is_equal_to(atlas.pixels().len());
+        assert_that(&atlas.mipmap_dimensions(1)).is_equal_to((256, 8));
+        assert_that(&mipmaps[1].len()).is_equal_to(256 * 8 * TilesetAtlas::CHANNELS);
+        assert_that(&mipmaps[2].len()).is_equal_to(128 * 4 * TilesetAtlas::CHANNELS);
+    }
+}
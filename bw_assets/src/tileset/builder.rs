@@ -0,0 +1,196 @@
+use snafu::{ResultExt, Snafu};
+use std::sync::Arc;
+
+use super::{vx4_vf4_consistent, CV5s, RenderError, TilesetAssets, VF4s, VR4s, VX4s, WPEs};
+
+/// Error returned by [`TilesetAssetsBuilder`].
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum TilesetBuildError {
+    #[snafu(display("tileset has no {} file", extension))]
+    MissingFile { extension: &'static str },
+
+    #[snafu(display("inconsistent tileset files: {}", source))]
+    Inconsistent { source: RenderError },
+}
+
+/// Assembles a [`TilesetAssets`] one file at a time, checking every file
+/// against the files added before it.
+///
+/// A file that does not fit the others is rejected when it is added and the
+/// builder keeps the files it had, so mixing up the files of two tilesets is
+/// reported by the file that caused it rather than when the tileset is
+/// rendered.
+#[derive(Debug, Default)]
+pub struct TilesetAssetsBuilder {
+    cv5s: Option<Arc<CV5s>>,
+    vf4s: Option<Arc<VF4s>>,
+    vx4s: Option<Arc<VX4s>>,
+    vr4s: Option<Arc<VR4s>>,
+    wpes: Option<Arc<WPEs>>,
+}
+
+impl TilesetAssetsBuilder {
+    pub fn new() -> TilesetAssetsBuilder {
+        TilesetAssetsBuilder::default()
+    }
+
+    pub fn cv5s(&mut self, cv5s: CV5s) -> Result<&mut Self, TilesetBuildError> {
+        self.cv5s = Some(Arc::new(cv5s));
+        Ok(self)
+    }
+
+    /// Adds the VF4 file, which must have as many megatiles as the VX4 file.
+    pub fn vf4s(&mut self, vf4s: VF4s) -> Result<&mut Self, TilesetBuildError> {
+        let previous = self.vf4s.replace(Arc::new(vf4s));
+        self.check(|builder| &mut builder.vf4s, previous)
+    }
+
+    /// Adds the VX4 file, which must have as many megatiles as the VF4 file
+    /// and only reference minitiles of the VR4 file.
+    pub fn vx4s(&mut self, vx4s: VX4s) -> Result<&mut Self, TilesetBuildError> {
+        let previous = self.vx4s.replace(Arc::new(vx4s));
+        self.check(|builder| &mut builder.vx4s, previous)
+    }
+
+    /// Adds the VR4 file, which must have every minitile the VX4 file
+    /// references and only use colors of the palette.
+    pub fn vr4s(&mut self, vr4s: VR4s) -> Result<&mut Self, TilesetBuildError> {
+        let previous = self.vr4s.replace(Arc::new(vr4s));
+        self.check(|builder| &mut builder.vr4s, previous)
+    }
+
+    /// Adds the palette, which must have every color the VR4 file uses.
+    pub fn wpes(&mut self, wpes: WPEs) -> Result<&mut Self, TilesetBuildError> {
+        let previous = self.wpes.replace(Arc::new(wpes));
+        self.check(|builder| &mut builder.wpes, previous)
+    }
+
+    /// The validated tileset, or an error if a file has not been added.
+    pub fn build(&self) -> Result<TilesetAssets, TilesetBuildError> {
+        let assets = TilesetAssets {
+            cv5s: required(&self.cv5s, "cv5")?,
+            vf4s: required(&self.vf4s, "vf4")?,
+            vx4s: required(&self.vx4s, "vx4")?,
+            vr4s: required(&self.vr4s, "vr4")?,
+            wpes: required(&self.wpes, "wpe")?,
+        };
+        assets.validate().context(Inconsistent)?;
+
+        Ok(assets)
+    }
+
+    /// Checks the files added so far against each other, restoring the file
+    /// that was just replaced if they do not fit.
+    fn check<T>(
+        &mut self,
+        file: fn(&mut Self) -> &mut Option<Arc<T>>,
+        previous: Option<Arc<T>>,
+    ) -> Result<&mut Self, TilesetBuildError> {
+        match self.consistency() {
+            Ok(()) => Ok(self),
+            Err(source) => {
+                *file(self) = previous;
+                Err(TilesetBuildError::Inconsistent { source })
+            }
+        }
+    }
+
+    fn consistency(&self) -> Result<(), RenderError> {
+        if let (Some(vx4s), Some(vf4s)) = (&self.vx4s, &self.vf4s) {
+            if !vx4_vf4_consistent(vx4s, vf4s) {
+                return Err(RenderError::FlagCountMismatch {
+                    vx4s: vx4s.len(),
+                    vf4s: vf4s.len(),
+                });
+            }
+        }
+
+        if let (Some(vx4s), Some(vr4s)) = (&self.vx4s, &self.vr4s) {
+            if !vx4s.references_fit(vr4s) {
+                return Err(RenderError::MinitileOutOfRange {
+                    minitile: vx4s.max_vr4_index(),
+                    len: vr4s.len(),
+                });
+            }
+        }
+
+        if let (Some(vr4s), Some(wpes)) = (&self.vr4s, &self.wpes) {
+            let color = vr4s.iter().flatten().map(usize::from).max();
+            if let Some(color) = color.filter(|&color| color >= wpes.len()) {
+                return Err(RenderError::ColorOutOfRange {
+                    color,
+                    len: wpes.len(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn required<T>(
+    file: &Option<Arc<T>>,
+    extension: &'static str,
+) -> Result<Arc<T>, TilesetBuildError> {
+    file.clone().context(MissingFile { extension })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_assembles_a_validated_tileset() {
+        let mut builder = TilesetAssetsBuilder::new();
+
+        builder
+            .vf4s(VF4s::from_bytes(&[1, 0].repeat(16)).unwrap())
+            .unwrap()
+            .vx4s(VX4s::from_bytes(&[0; 32]).unwrap())
+            .unwrap();
+        assert_that(&builder.build())
+            .is_err_containing(TilesetBuildError::MissingFile { extension: "cv5" });
+
+        builder
+            .cv5s(CV5s::from_bytes(&[0; 52]).unwrap())
+            .unwrap()
+            .vr4s(VR4s::from_bytes(&[0; 64]).unwrap())
+            .unwrap()
+            .wpes(WPEs::from_bytes(&[0; 4]).unwrap())
+            .unwrap();
+        let assets = builder.build().unwrap();
+
+        assert_that(&assets.vx4s.len()).is_equal_to(1);
+    }
+
+    #[test]
+    fn it_rejects_a_vf4_file_of_another_tileset() {
+        let mut builder = TilesetAssetsBuilder::new();
+        builder.vx4s(VX4s::from_bytes(&[0; 32]).unwrap()).unwrap();
+
+        let err = builder
+            .vf4s(VF4s::from_bytes(&[0; 64]).unwrap())
+            .unwrap_err();
+
+        assert_that(&err).is_equal_to(TilesetBuildError::Inconsistent {
+            source: RenderError::FlagCountMismatch { vx4s: 1, vf4s: 2 },
+        });
+        assert_that(&err.to_string()).contains("1 vx4 entries do not match 2 vf4 entries");
+        assert_that(&builder.vf4s.is_none()).is_true();
+    }
+
+    #[test]
+    fn it_rejects_minitiles_with_colors_outside_the_palette() {
+        let mut builder = TilesetAssetsBuilder::new();
+        builder.wpes(WPEs::from_bytes(&[0; 8]).unwrap()).unwrap();
+
+        let err = builder
+            .vr4s(VR4s::from_bytes(&[5; 64]).unwrap())
+            .unwrap_err();
+
+        assert_that(&err).is_equal_to(TilesetBuildError::Inconsistent {
+            source: RenderError::ColorOutOfRange { color: 5, len: 2 },
+        });
+    }
+}
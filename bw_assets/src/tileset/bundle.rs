@@ -0,0 +1,155 @@
+//! A single file containing the five files of a tileset.
+//!
+//! The bundle starts with the magic `BWTS`, followed by the offset and length
+//! of the CV5, VF4, VX4, VR4 and WPE files, in that order, as little endian
+//! `u32` pairs. Offsets are counted from the start of the bundle. The files
+//! follow the header, concatenated.
+#[cfg(feature = "amethyst")]
+use amethyst::{
+    assets::{Asset, Format, Handle},
+    ecs::DenseVecStorage,
+};
+use nom::{
+    bytes::complete::tag,
+    multi::count,
+    number::complete::le_u32,
+    sequence::{preceded, tuple},
+    IResult,
+};
+use std::sync::Arc;
+
+use super::{CV5s, TilesetAssets, VF4s, VR4s, VX4s, WPEs};
+use crate::error::{finish_parse, ParseError};
+
+/// Magic at the start of every tileset bundle.
+pub const TILESET_BUNDLE_MAGIC: &[u8; 4] = b"BWTS";
+
+/// Extensions of the files of a bundle, in the order they are stored.
+const FILES: [&str; 5] = ["cv5", "vf4", "vx4", "vr4", "wpe"];
+
+const HEADER_LEN: usize = TILESET_BUNDLE_MAGIC.len() + FILES.len() * 8;
+
+/// Packs the five files of a tileset into a bundle.
+pub fn pack_tileset_bundle(cv5: &[u8], vf4: &[u8], vx4: &[u8], vr4: &[u8], wpe: &[u8]) -> Vec<u8> {
+    let files = [cv5, vf4, vx4, vr4, wpe];
+    let len = HEADER_LEN + files.iter().map(|file| file.len()).sum::<usize>();
+    let mut bundle = Vec::with_capacity(len);
+
+    bundle.extend_from_slice(TILESET_BUNDLE_MAGIC);
+    let mut offset = HEADER_LEN;
+    for file in files.iter() {
+        bundle.extend_from_slice(&(offset as u32).to_le_bytes());
+        bundle.extend_from_slice(&(file.len() as u32).to_le_bytes());
+        offset += file.len();
+    }
+    for file in files.iter() {
+        bundle.extend_from_slice(file);
+    }
+
+    bundle
+}
+
+fn parse_header(b: &[u8]) -> IResult<&[u8], Vec<(u32, u32)>> {
+    preceded(
+        tag(&TILESET_BUNDLE_MAGIC[..]),
+        count(tuple((le_u32, le_u32)), FILES.len()),
+    )(b)
+}
+
+impl TilesetAssets {
+    /// Loads a tileset from a bundle of its five files, see
+    /// [`pack_tileset_bundle`].
+    pub fn from_bundle(b: &[u8]) -> Result<TilesetAssets, ParseError> {
+        let header = finish_parse("tileset bundle", b, parse_header(b))?;
+        let files = header
+            .iter()
+            .zip(FILES.iter())
+            .enumerate()
+            .map(|(i, (&(offset, len), extension))| {
+                let (start, len) = (offset as usize, len as usize);
+                b.get(start..start.saturating_add(len))
+                    .ok_or_else(|| ParseError::Malformed {
+                        asset: "tileset bundle",
+                        description: format!("{} file ends past the end of the bundle", extension),
+                        position: TILESET_BUNDLE_MAGIC.len() + i * 8,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TilesetAssets {
+            cv5s: Arc::new(CV5s::from_bytes(files[0])?),
+            vf4s: Arc::new(VF4s::from_bytes(files[1])?),
+            vx4s: Arc::new(VX4s::from_bytes(files[2])?),
+            vr4s: Arc::new(VR4s::from_bytes(files[3])?),
+            wpes: Arc::new(WPEs::from_bytes(files[4])?),
+        })
+    }
+}
+
+/// Handle to a tileset loaded from a bundle. The files are shared, so the
+/// tileset can be cloned out of the asset storage instead of taken.
+#[cfg(feature = "amethyst")]
+pub type TilesetAssetsHandle = Handle<TilesetAssets>;
+
+#[cfg(feature = "amethyst")]
+impl Asset for TilesetAssets {
+    const NAME: &'static str = "bw_assets::tileset::TilesetAssets";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<TilesetAssetsHandle>;
+}
+
+/// Loads a whole tileset from a single bundle, see [`pack_tileset_bundle`].
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TilesetBundleFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<TilesetAssets> for TilesetBundleFormat {
+    fn name(&self) -> &'static str {
+        "TilesetBundleFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<TilesetAssets> {
+        Ok(TilesetAssets::from_bundle(&b)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_round_trips_the_five_files() {
+        let cv5 = vec![0; 52 * 2];
+        let vf4 = vec![1; 32 * 3];
+        let vx4 = vec![0; 32];
+        let vr4 = vec![2; VR4s::BLOCK_SIZE * 4];
+        let wpe = vec![255; 4 * 5];
+
+        let bundle = pack_tileset_bundle(&cv5, &vf4, &vx4, &vr4, &wpe);
+        let assets = TilesetAssets::from_bundle(&bundle).unwrap();
+
+        assert_that(&bundle[0..4]).is_equal_to(&b"BWTS"[..]);
+        assert_that(&assets.cv5s.len()).is_equal_to(2);
+        assert_that(&assets.vf4s.len()).is_equal_to(3);
+        assert_that(&assets.vx4s.len()).is_equal_to(1);
+        assert_that(&assets.vr4s.len()).is_equal_to(4);
+        assert_that(&assets.wpes.len()).is_equal_to(5);
+    }
+
+    #[test]
+    fn it_rejects_files_past_the_end_of_the_bundle() {
+        let mut bundle = pack_tileset_bundle(&[], &[], &[], &[], &[0; 4]);
+        bundle.truncate(bundle.len() - 1);
+
+        assert_that(&TilesetAssets::from_bundle(&bundle)).is_err_containing(
+            ParseError::Malformed {
+                asset: "tileset bundle",
+                description: "wpe file ends past the end of the bundle".to_string(),
+                position: 36,
+            },
+        );
+        assert_that(&TilesetAssets::from_bundle(b"BWTX")).is_err();
+    }
+}
@@ -1,4 +1,5 @@
-use super::super::map::MegaTile;
+use super::super::map::{Direction, MegaTile};
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::{Asset, Format, Handle},
     ecs::DenseVecStorage,
@@ -8,22 +9,46 @@ use nom::{
     combinator::{all_consuming, map},
     multi::{count, many0},
     number::complete::{le_u8, le_u16},
-    sequence::{preceded, tuple},
+    sequence::{terminated, tuple},
+    IResult,
 };
-use nom::{Finish, IResult};
 
 use std::ops::Index;
 
+use crate::error::{finish_parse, ParseError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BuildFlag {
     Buildable,
     Creep,
     Unbuildable,
 }
 
+/// Terrain type of a tile group, e.g. water, dirt or a ramp. The meaning of
+/// each type depends on the tileset. Doodads are always type 1.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct TerrainType(u16);
+
+impl TerrainType {
+    pub const DOODAD: TerrainType = TerrainType(1);
+
+    pub fn new(terrain_type: u16) -> TerrainType {
+        TerrainType(terrain_type)
+    }
+
+    pub fn raw(&self) -> u16 {
+        self.0
+    }
+}
+
 #[derive(Debug)]
 pub struct TileMetadata(CV5Data);
 
 impl TileMetadata {
+    pub fn terrain_type(&self) -> TerrainType {
+        self.0.terrain_type()
+    }
+
     pub fn build_flag(&self) -> BuildFlag {
         self.0.build_flag()
     }
@@ -42,12 +67,16 @@ pub enum OverlayFlag {
 pub struct Doodad(CV5Data);
 
 impl Doodad {
+    pub fn terrain_type(&self) -> TerrainType {
+        self.0.terrain_type()
+    }
+
     pub fn build_flag(&self) -> BuildFlag {
         self.0.build_flag()
     }
 
     pub fn overlay_flags(&self) -> OverlayFlag {
-        match self.0 .1 >> 4 {
+        match self.0 .2 >> 4 {
             0x0 => OverlayFlag::None,
             0x1 => OverlayFlag::SpriteReference,
             0x2 => OverlayFlag::UnitReference,
@@ -57,23 +86,23 @@ impl Doodad {
     }
 
     pub fn overlay_id(&self) -> u16 {
-        self.0 .2
+        self.0 .3
     }
 
     pub fn doodad_group_str_idx(&self) -> u16 {
-        self.0 .4
+        self.0 .5
     }
 
     pub fn dddata_bin_idx(&self) -> u16 {
-        self.0 .6
+        self.0 .7
     }
 
     pub fn width(&self) -> u16 {
-        self.0 .7
+        self.0 .8
     }
 
     pub fn height(&self) -> u16 {
-        self.0 .8
+        self.0 .9
     }
 }
 
@@ -94,6 +123,7 @@ impl From<&MinitileReference> for usize {
 
 #[derive(Debug)]
 pub struct CV5Data(
+    TerrainType,
     u8,
     u8,
     u16,
@@ -109,12 +139,13 @@ pub struct CV5Data(
 
 impl CV5Data {
     pub fn megatile_references(&self) -> &Vec<MinitileReference> {
-        &self.10
+        &self.11
     }
 }
 
 impl
     From<(
+        TerrainType,
         u8,
         u8,
         u16,
@@ -130,6 +161,7 @@ impl
 {
     fn from(
         t: (
+            TerrainType,
             u8,
             u8,
             u16,
@@ -143,7 +175,7 @@ impl
             Vec<MinitileReference>,
         ),
     ) -> Self {
-        CV5Data(t.0, t.1, t.2, t.3, t.4, t.5, t.6, t.7, t.8, t.9, t.10)
+        CV5Data(t.0, t.1, t.2, t.3, t.4, t.5, t.6, t.7, t.8, t.9, t.10, t.11)
     }
 }
 
@@ -151,7 +183,7 @@ impl Index<usize> for CV5Data {
     type Output = MinitileReference;
 
     fn index(&self, i: usize) -> &Self::Output {
-        &self.10[i]
+        &self.11[i]
     }
 }
 
@@ -159,13 +191,27 @@ impl CV5Data {
     /// Each megatile has 16 (4x4) minitiles.
     const MEGA_TILE_REFERENCE_COUNT: usize = 16;
 
+    /// Bytes of a tile group in the file.
+    const SIZE: usize = 20 + CV5Data::MEGA_TILE_REFERENCE_COUNT * 2;
+
+    pub fn terrain_type(&self) -> TerrainType {
+        self.0
+    }
+
     pub fn build_flag(&self) -> BuildFlag {
-        match self.0 >> 4 {
-            0 => BuildFlag::Buildable,
-            4 => BuildFlag::Creep,
-            8 => BuildFlag::Unbuildable,
-            _ => BuildFlag::Buildable,
-        }
+        build_flag(self.1)
+    }
+
+    /// Every field of the tile group except the megatile references.
+    pub fn header(&self) -> CV5Header {
+        CV5Header(
+            self.0,
+            self.1,
+            self.2,
+            [
+                self.3, self.4, self.5, self.6, self.7, self.8, self.9, self.10,
+            ],
+        )
     }
 
     pub fn as_doodad(self) -> Doodad {
@@ -196,10 +242,20 @@ impl Index<&MegaTile> for CV5 {
     }
 }
 
+fn build_flag(flags: u8) -> BuildFlag {
+    match flags >> 4 {
+        0 => BuildFlag::Buildable,
+        4 => BuildFlag::Creep,
+        8 => BuildFlag::Unbuildable,
+        _ => BuildFlag::Buildable,
+    }
+}
+
 fn parse_cv5(b: &[u8]) -> IResult<&[u8], CV5Data> {
     map(
         tuple((
-            preceded(take(2u8), le_u8),
+            map(le_u16, TerrainType),
+            le_u8,
             le_u8,
             le_u16,
             le_u16,
@@ -224,9 +280,157 @@ pub enum CV5 {
     TileMetadata(TileMetadata),
 }
 
+impl CV5 {
+    pub fn terrain_type(&self) -> TerrainType {
+        match self {
+            CV5::Doodad(doodad) => doodad.terrain_type(),
+            CV5::TileMetadata(tile_metadata) => tile_metadata.terrain_type(),
+        }
+    }
+
+    /// Every field of the tile group except the megatile references.
+    pub fn header(&self) -> CV5Header {
+        match self {
+            CV5::Doodad(doodad) => doodad.0.header(),
+            CV5::TileMetadata(tile_metadata) => tile_metadata.0.header(),
+        }
+    }
+
+    pub fn build_flag(&self) -> BuildFlag {
+        match self {
+            CV5::Doodad(doodad) => doodad.build_flag(),
+            CV5::TileMetadata(tile_metadata) => tile_metadata.build_flag(),
+        }
+    }
+
+    /// Whether the tile group is a placeholder, i.e. every megatile reference
+    /// is 0.
+    ///
+    /// The first tile group of every tileset and the unused groups between
+    /// terrain types are placeholders. Maps may still reference them, so they
+    /// are not invalid, but they have no graphics of their own: they would
+    /// render as the first megatile of the tileset. Renderers can skip them.
+    pub fn is_null(&self) -> bool {
+        let data = match self {
+            CV5::Doodad(doodad) => &doodad.0,
+            CV5::TileMetadata(tile_metadata) => &tile_metadata.0,
+        };
+
+        data.megatile_references()
+            .iter()
+            .all(|reference| reference.0 == 0)
+    }
+}
+
+/// How the megatiles of a map encode the tile group they belong to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CV5Layout {
+    /// The group is the 11 bits above the subtile index, for up to 2048
+    /// groups. Every tileset of the game uses this layout.
+    Classic,
+
+    /// The group is all 12 bits above the subtile index, for up to 4096
+    /// groups, as used by remastered and modded tilesets.
+    Extended,
+}
+
+impl CV5Layout {
+    /// Most tile groups a classic CV5 can have.
+    pub const CLASSIC_MAX_GROUPS: usize = 2048;
+
+    /// Guesses the layout from the size of a CV5 file in bytes: a file with
+    /// more groups than a classic CV5 can address is extended.
+    pub fn detect(len: usize) -> CV5Layout {
+        if len > CV5Layout::CLASSIC_MAX_GROUPS * CV5Data::SIZE {
+            CV5Layout::Extended
+        } else {
+            CV5Layout::Classic
+        }
+    }
+
+    /// Tile group of the megatile in this layout.
+    pub fn group_index(self, megatile: &MegaTile) -> usize {
+        match self {
+            CV5Layout::Classic => megatile.group_index(),
+            CV5Layout::Extended => megatile.extended_group_index(),
+        }
+    }
+}
+
+impl Default for CV5Layout {
+    fn default() -> Self {
+        CV5Layout::Classic
+    }
+}
+
 /// A list of CV5. Each CV5 is referenced by the MXTM field from CHK.
 #[derive(Debug)]
-pub struct CV5s(Vec<CV5>);
+pub struct CV5s(Vec<CV5>, CV5Layout);
+
+impl CV5s {
+    /// Creates an empty list of CV5, mostly useful for testing.
+    pub fn empty() -> CV5s {
+        CV5s(vec![], CV5Layout::Classic)
+    }
+
+    /// Layout the megatiles are looked up with, detected from the size of
+    /// the file unless it was overridden.
+    pub fn layout(&self) -> CV5Layout {
+        self.1
+    }
+
+    /// Looks up megatiles with `layout` instead of the detected layout.
+    pub fn with_layout(self, layout: CV5Layout) -> CV5s {
+        CV5s(self.0, layout)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn heap_size(&self) -> usize {
+        let references = self
+            .0
+            .iter()
+            .map(|cv5| {
+                let data = match cv5 {
+                    CV5::Doodad(doodad) => &doodad.0,
+                    CV5::TileMetadata(tile_metadata) => &tile_metadata.0,
+                };
+                data.11.capacity() * std::mem::size_of::<MinitileReference>()
+            })
+            .sum::<usize>();
+
+        self.0.capacity() * std::mem::size_of::<CV5>() + references
+    }
+
+    /// Tile group of the megatile, or `None` if the group is out of range.
+    pub fn get(&self, megatile: &MegaTile) -> Option<&CV5> {
+        self.0.get(self.1.group_index(megatile))
+    }
+
+    /// Tile group at `index` regardless of the layout, or `None` if it is out
+    /// of range.
+    pub fn group(&self, index: usize) -> Option<&CV5> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<CV5> {
+        self.0.iter()
+    }
+}
+
+impl Index<usize> for CV5s {
+    type Output = CV5;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
 
 impl Index<MegaTile> for CV5s {
     type Output = CV5;
@@ -240,11 +444,12 @@ impl Index<&MegaTile> for CV5s {
     type Output = CV5;
 
     fn index(&self, megatile: &MegaTile) -> &Self::Output {
-        &self.0[megatile.group_index()]
+        &self.0[self.1.group_index(megatile)]
     }
 }
 
-fn parse_cv5s(b: &[u8]) -> IResult<&[u8], CV5s> {
+pub(crate) fn parse_cv5s(b: &[u8]) -> IResult<&[u8], CV5s> {
+    let layout = CV5Layout::detect(b.len());
     let (remaining, cv5s_data) = all_consuming(many0(parse_cv5))(b)?;
 
     let cv5s = cv5s_data
@@ -259,42 +464,269 @@ fn parse_cv5s(b: &[u8]) -> IResult<&[u8], CV5s> {
         })
         .collect::<Vec<_>>();
 
-    Ok((remaining, CV5s(cv5s)))
+    Ok((remaining, CV5s(cv5s, layout)))
 }
 
+/// The fields of a tile group without its 16 megatile references.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CV5Header(TerrainType, u8, u8, [u16; 8]);
+
+impl CV5Header {
+    pub fn terrain_type(&self) -> TerrainType {
+        self.0
+    }
+
+    pub fn build_flag(&self) -> BuildFlag {
+        build_flag(self.1)
+    }
+
+    /// The 8 fields between the flags and the megatile references, whose
+    /// meaning depends on whether the group is a doodad.
+    pub fn fields(&self) -> [u16; 8] {
+        self.3
+    }
+
+    /// Terrain the group connects to on one of its four sides, or `None` for
+    /// diagonal directions. Only meaningful for groups that are not doodads.
+    pub fn edge(&self, direction: Direction) -> Option<u16> {
+        match direction {
+            Direction::West => Some(self.3[0]),
+            Direction::North => Some(self.3[1]),
+            Direction::East => Some(self.3[2]),
+            Direction::South => Some(self.3[3]),
+            _ => None,
+        }
+    }
+}
+
+/// The headers of every tile group of a CV5 file.
+///
+/// Parsing only the headers skips the megatile references, which make up
+/// most of the file. Use it for analyses that only need the terrain types
+/// and flags of the tile groups, such as buildability. Use [`CV5s`] to render
+/// tiles or to look up the minitiles of a megatile.
+#[derive(Debug)]
+pub struct CV5Headers(Vec<CV5Header>, CV5Layout);
+
+impl CV5Headers {
+    pub fn from_bytes(b: &[u8]) -> Result<CV5Headers, ParseError> {
+        finish_parse("cv5", b, parse_cv5_headers(b))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Layout the megatiles are looked up with, see [`CV5s::layout`].
+    pub fn layout(&self) -> CV5Layout {
+        self.1
+    }
+
+    /// Header of the tile group of the megatile, or `None` if the group is
+    /// out of range.
+    pub fn get(&self, megatile: &MegaTile) -> Option<&CV5Header> {
+        self.0.get(self.1.group_index(megatile))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<CV5Header> {
+        self.0.iter()
+    }
+}
+
+fn parse_cv5_header(b: &[u8]) -> IResult<&[u8], CV5Header> {
+    map(
+        terminated(
+            tuple((map(le_u16, TerrainType), le_u8, le_u8, count(le_u16, 8))),
+            take(CV5Data::MEGA_TILE_REFERENCE_COUNT * 2),
+        ),
+        |(terrain_type, build_flags, overlay_flags, fields)| {
+            let mut header_fields = [0; 8];
+            header_fields.copy_from_slice(&fields);
+
+            CV5Header(terrain_type, build_flags, overlay_flags, header_fields)
+        },
+    )(b)
+}
+
+pub(crate) fn parse_cv5_headers(b: &[u8]) -> IResult<&[u8], CV5Headers> {
+    let layout = CV5Layout::detect(b.len());
+
+    all_consuming(map(many0(parse_cv5_header), |headers| {
+        CV5Headers(headers, layout)
+    }))(b)
+}
+
+#[cfg(feature = "amethyst")]
 pub struct CV5sAsset(Option<CV5s>);
 
+#[cfg(feature = "amethyst")]
 impl CV5sAsset {
     pub fn take(&mut self) -> Option<CV5s> {
         self.0.take()
     }
 }
 
+#[cfg(feature = "amethyst")]
 pub type CV5sHandle = Handle<CV5sAsset>;
 
+#[cfg(feature = "amethyst")]
 impl Asset for CV5sAsset {
     const NAME: &'static str = "bw_assets::tileset::CV5sAsset";
     type Data = Self;
     type HandleStorage = DenseVecStorage<CV5sHandle>;
 }
 
+impl CV5s {
+    pub fn from_bytes(b: &[u8]) -> Result<CV5s, ParseError> {
+        finish_parse("cv5", b, parse_cv5s(b))
+    }
+}
+
+/// Loads a CV5, detecting its layout from the size of the file unless it is
+/// overridden, see [`CV5Layout::detect`].
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
-pub struct CV5Format;
+pub struct CV5Format {
+    layout: Option<CV5Layout>,
+}
 
+#[cfg(feature = "amethyst")]
 impl Format<CV5sAsset> for CV5Format {
     fn name(&self) -> &'static str {
         "CV5Format"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<CV5sAsset> {
-        let (_, cv5s) = parse_cv5s(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load cv5 asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
+        let cv5s = CV5s::from_bytes(&b)?;
+        let cv5s = match self.layout {
+            Some(layout) => cv5s.with_layout(layout),
+            None => cv5s,
+        };
 
         Ok(CV5sAsset(Some(cv5s)))
     }
 }
+
+#[cfg(feature = "amethyst")]
+impl CV5Format {
+    /// Format that always uses the extended layout, e.g. for a modded tileset
+    /// whose megatiles address groups past 2048 although the file is small.
+    pub fn extended() -> CV5Format {
+        CV5Format {
+            layout: Some(CV5Layout::Extended),
+        }
+    }
+
+    /// Format that only parses the headers of the tile groups, see
+    /// [`CV5Headers`].
+    pub fn headers_only() -> CV5HeadersFormat {
+        CV5HeadersFormat
+    }
+}
+
+#[cfg(feature = "amethyst")]
+pub struct CV5HeadersAsset(Option<CV5Headers>);
+
+#[cfg(feature = "amethyst")]
+impl CV5HeadersAsset {
+    pub fn take(&mut self) -> Option<CV5Headers> {
+        self.0.take()
+    }
+}
+
+#[cfg(feature = "amethyst")]
+pub type CV5HeadersHandle = Handle<CV5HeadersAsset>;
+
+#[cfg(feature = "amethyst")]
+impl Asset for CV5HeadersAsset {
+    const NAME: &'static str = "bw_assets::tileset::CV5HeadersAsset";
+    type Data = Self;
+    type HandleStorage = DenseVecStorage<CV5HeadersHandle>;
+}
+
+#[cfg(feature = "amethyst")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CV5HeadersFormat;
+
+#[cfg(feature = "amethyst")]
+impl Format<CV5HeadersAsset> for CV5HeadersFormat {
+    fn name(&self) -> &'static str {
+        "CV5HeadersFormat"
+    }
+
+    fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<CV5HeadersAsset> {
+        Ok(CV5HeadersAsset(Some(CV5Headers::from_bytes(&b)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_detects_extended_cv5s_by_their_size() {
+        let groups = CV5Layout::CLASSIC_MAX_GROUPS + 100;
+        let mut cv5 = vec![0; CV5Data::SIZE * groups];
+        // the last group has terrain type 9 and its subtile 3 references 7
+        let last = CV5Data::SIZE * (groups - 1);
+        cv5[last] = 9;
+        cv5[last + 20 + 6] = 7;
+
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+        let megatile = MegaTile::from_raw(((groups - 1) << 4 | 3) as u16);
+
+        assert_that(&cv5s.len()).is_equal_to(groups);
+        assert_that(&cv5s.layout()).is_equal_to(CV5Layout::Extended);
+        assert_that(&cv5s[&megatile].terrain_type()).is_equal_to(TerrainType::new(9));
+        assert_that(&usize::from(&cv5s[&megatile][&megatile])).is_equal_to(7);
+
+        let classic = CV5s::from_bytes(&cv5[..CV5Data::SIZE * 2]).unwrap();
+        assert_that(&classic.layout()).is_equal_to(CV5Layout::Classic);
+        let classic_lookup = cv5s.with_layout(CV5Layout::Classic);
+        assert_that(&classic_lookup.get(&megatile).map(CV5::terrain_type))
+            .is_equal_to(Some(TerrainType::new(0)));
+    }
+
+    #[test]
+    fn it_indexes_groups_by_number() {
+        let mut cv5 = vec![0; CV5Data::SIZE * 3];
+        cv5[0] = 4;
+        cv5[CV5Data::SIZE * 2] = 7;
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+
+        assert_that(&cv5s[0].terrain_type()).is_equal_to(TerrainType::new(4));
+        assert_that(&cv5s[2].terrain_type()).is_equal_to(TerrainType::new(7));
+        assert_that(&cv5s.group(2).is_some()).is_true();
+        assert_that(&cv5s.group(3).is_none()).is_true();
+    }
+
+    #[test]
+    fn it_detects_placeholder_tile_groups() {
+        let mut cv5 = vec![0; 52 * 2];
+        cv5[52 + 20 + 6] = 1;
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+
+        assert_that(&cv5s[MegaTile::from_raw(0)].is_null()).is_true();
+        assert_that(&cv5s[MegaTile::from_raw(1 << 4)].is_null()).is_false();
+    }
+
+    #[test]
+    fn it_parses_the_same_headers_without_the_references() {
+        let b = (0..52 * 1030).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+
+        let cv5s = CV5s::from_bytes(&b).unwrap();
+        let headers = CV5Headers::from_bytes(&b).unwrap();
+
+        assert_that(&headers.len()).is_equal_to(cv5s.len());
+        for (i, header) in headers.iter().enumerate() {
+            let megatile = MegaTile::from_raw((i as u16) << 4);
+            assert_that(header).is_equal_to(cv5s[&megatile].header());
+        }
+        assert_that(&CV5Headers::from_bytes(&b[..51])).is_err();
+    }
+}
@@ -0,0 +1,132 @@
+use nom::{
+    combinator::{all_consuming, map},
+    multi::{count, many0},
+    number::complete::le_u16,
+    IResult,
+};
+
+use super::Doodad;
+use crate::error::{finish_parse, ParseError};
+use crate::map::MegaTile;
+
+/// Placement of a doodad: which megatiles make up the doodad and the sprite
+/// or unit overlaid on top of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DoodadDef {
+    /// Sprite or unit drawn over the doodad, see [`Doodad::overlay_flags`].
+    pub overlay_id: u16,
+
+    /// Width of the doodad in megatiles.
+    pub width: u16,
+
+    /// Height of the doodad in megatiles.
+    pub height: u16,
+
+    /// Megatiles of the doodad in row major order, `width * height` of them.
+    pub tiles: Vec<MegaTile>,
+}
+
+/// Megatile layouts of every doodad of a tileset, read from `dddata.dat`.
+///
+/// Each doodad is stored as a 16x16 grid of megatiles, of which only the
+/// top left `width * height` megatiles of the doodad's CV5 entry are used.
+#[derive(Debug)]
+pub struct DDData(Vec<Vec<MegaTile>>);
+
+impl DDData {
+    /// Width and height of the megatile grid stored for every doodad.
+    pub const GRID_SIDE_LEN: usize = 16;
+
+    const BLOCK_SIZE: usize = DDData::GRID_SIDE_LEN * DDData::GRID_SIDE_LEN;
+
+    pub fn from_bytes(b: &[u8]) -> Result<DDData, ParseError> {
+        finish_parse("dddata", b, parse_dddata(b))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The 16x16 megatile grid of a doodad, or `None` if the index is out of
+    /// range.
+    pub fn get(&self, index: usize) -> Option<&Vec<MegaTile>> {
+        self.0.get(index)
+    }
+
+    /// Placement of the doodad of a CV5 doodad tile group, or `None` if its
+    /// dddata index is out of range or it is larger than the megatile grid.
+    pub fn doodad_def(&self, doodad: &Doodad) -> Option<DoodadDef> {
+        let grid = self.get(doodad.dddata_bin_idx() as usize)?;
+        let (width, height) = (doodad.width() as usize, doodad.height() as usize);
+        if width > DDData::GRID_SIDE_LEN || height > DDData::GRID_SIDE_LEN {
+            return None;
+        }
+
+        let tiles = grid
+            .chunks(DDData::GRID_SIDE_LEN)
+            .take(height)
+            .flat_map(|row| row[..width].iter().cloned())
+            .collect();
+
+        Some(DoodadDef {
+            overlay_id: doodad.overlay_id(),
+            width: doodad.width(),
+            height: doodad.height(),
+            tiles,
+        })
+    }
+}
+
+pub(crate) fn parse_dddata(b: &[u8]) -> IResult<&[u8], DDData> {
+    all_consuming(map(
+        many0(count(map(le_u16, MegaTile::from_raw), DDData::BLOCK_SIZE)),
+        DDData,
+    ))(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::{CV5s, CV5};
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_places_a_doodad_from_its_cv5_entry() {
+        // doodad 1 is a 2x2 doodad of megatiles 0x4000 to 0x4003
+        let mut dddata = vec![0; DDData::BLOCK_SIZE * 2 * 2];
+        let doodad = DDData::BLOCK_SIZE * 2;
+        dddata[doodad..doodad + 4].copy_from_slice(&[0x00, 0x40, 0x01, 0x40]);
+        dddata[doodad + 32..doodad + 36].copy_from_slice(&[0x02, 0x40, 0x03, 0x40]);
+        let dddata = DDData::from_bytes(&dddata).unwrap();
+
+        // the first doodad tile group, with overlay 7 and dddata index 1
+        let mut cv5 = vec![0; 52 * 1025];
+        let group = 52 * 1024;
+        cv5[group + 4] = 7;
+        cv5[group + 12] = 1;
+        cv5[group + 14] = 2;
+        cv5[group + 16] = 2;
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+        let doodad = match &cv5s[MegaTile::from_raw(1024 << 4)] {
+            CV5::Doodad(doodad) => doodad,
+            CV5::TileMetadata(_) => panic!("expected a doodad"),
+        };
+
+        assert_that(&dddata.len()).is_equal_to(2);
+        assert_that(&dddata.doodad_def(doodad))
+            .is_some()
+            .is_equal_to(DoodadDef {
+                overlay_id: 7,
+                width: 2,
+                height: 2,
+                tiles: [0x4000, 0x4001, 0x4002, 0x4003]
+                    .iter()
+                    .map(|&raw| MegaTile::from_raw(raw))
+                    .collect(),
+            });
+    }
+}
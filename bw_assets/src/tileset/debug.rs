@@ -0,0 +1,105 @@
+//! Debug overlays drawn over a rendered RGB map.
+
+use super::render::CHANNELS;
+use crate::map::MegaTile;
+
+/// Color of the lines between megatiles.
+const GRID_COLOR: [u8; CHANNELS] = [255, 0, 255];
+
+/// Color of the tile ids.
+const TEXT_COLOR: [u8; CHANNELS] = [255, 255, 255];
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// Hexadecimal digits in a 3x5 bitmap font, one row per byte with the
+/// leftmost pixel in the highest of the 3 low bits.
+const GLYPHS: [[u8; GLYPH_HEIGHT]; 16] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+    [0b010, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b111, 0b100, 0b100, 0b100, 0b111],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b110, 0b100, 0b111],
+    [0b111, 0b100, 0b110, 0b100, 0b100],
+];
+
+/// Draws a line along the top and left edge of every megatile.
+pub(crate) fn draw_grid(pixels: &mut [u8], pixel_width: usize, tile_px: usize) {
+    for (i, pixel) in pixels.chunks_mut(CHANNELS).enumerate() {
+        let (x, y) = (i % pixel_width, i / pixel_width);
+        if x % tile_px == 0 || y % tile_px == 0 {
+            pixel.copy_from_slice(&GRID_COLOR);
+        }
+    }
+}
+
+/// Writes the raw id of every megatile in hexadecimal into the top left
+/// corner of its tile. Nothing is written when the tiles are too small to
+/// fit the 4 digits.
+pub(crate) fn draw_tile_ids(pixels: &mut [u8], tiles: &[MegaTile], width: usize, tile_px: usize) {
+    let text_width = 4 * (GLYPH_WIDTH + 1);
+    if tile_px < text_width + 1 || tile_px < GLYPH_HEIGHT + 1 {
+        return;
+    }
+
+    let pixel_width = width * tile_px;
+    for (i, megatile) in tiles.iter().enumerate() {
+        let x = (i % width) * tile_px + 1;
+        let y = (i / width) * tile_px + 1;
+
+        for digit in 0..4 {
+            let glyph = &GLYPHS[(megatile.raw() >> (12 - digit * 4)) as usize & 0xf];
+            let glyph_x = x + digit * (GLYPH_WIDTH + 1);
+
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        let offset = ((y + row) * pixel_width + glyph_x + column) * CHANNELS;
+                        pixels[offset..offset + CHANNELS].copy_from_slice(&TEXT_COLOR);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_writes_tile_ids_in_hexadecimal() {
+        let tile_px = 32;
+        let mut pixels = vec![0; tile_px * tile_px * CHANNELS];
+
+        draw_tile_ids(&mut pixels, &[MegaTile::from_raw(0x1f00)], 1, tile_px);
+        let is_lit = |x: usize, y: usize| pixels[(y * tile_px + x) * CHANNELS] == 255;
+
+        // the 1 starts with a single pixel in the middle of its top row
+        assert_that(&is_lit(1, 1)).is_false();
+        assert_that(&is_lit(2, 1)).is_true();
+        // the F is lit along its whole left edge
+        assert_that(&(1..6).all(|y| is_lit(5, y))).is_true();
+        assert_that(&is_lit(0, 0)).is_false();
+    }
+
+    #[test]
+    fn it_skips_tile_ids_that_do_not_fit() {
+        let mut pixels = vec![0; 8 * 8 * CHANNELS];
+
+        draw_tile_ids(&mut pixels, &[MegaTile::from_raw(0xffff)], 1, 8);
+
+        assert_that(&pixels.iter().all(|&channel| channel == 0)).is_true();
+    }
+}
@@ -0,0 +1,90 @@
+use std::{io, path::Path};
+
+use super::super::map::Tileset;
+use super::{assets::read_file, wpe::parse_wpes, WPEs};
+
+const STOCK_TILESETS: [Tileset; 8] = [
+    Tileset::Badlands,
+    Tileset::SpacePlatform,
+    Tileset::Installation,
+    Tileset::Ashworld,
+    Tileset::Jungle,
+    Tileset::Desert,
+    Tileset::Arctic,
+    Tileset::Twilight,
+];
+
+impl Tileset {
+    /// Names the tileset of `known` a palette belongs to, or `None` if its
+    /// fingerprint is not known, see [`WPEs::fingerprint`]. The stock
+    /// fingerprints are read from an installed game with
+    /// [`palette_fingerprints_from_dir`].
+    pub fn identify_by_palette_in(wpes: &WPEs, known: &[(Tileset, u64)]) -> Option<Tileset> {
+        let fingerprint = wpes.fingerprint();
+
+        known
+            .iter()
+            .find(|(_, known_fingerprint)| *known_fingerprint == fingerprint)
+            .map(|(tileset, _)| tileset.clone())
+    }
+}
+
+/// Palette fingerprints of the eight stock tilesets, read from a directory of
+/// files extracted from the game, e.g. `badlands.wpe` and `jungle.wpe`.
+///
+/// Errors name the palette that is missing or could not be parsed.
+pub fn palette_fingerprints_from_dir(dir: &Path) -> io::Result<Vec<(Tileset, u64)>> {
+    STOCK_TILESETS
+        .iter()
+        .map(|tileset| {
+            let path = dir.join(format!("{}.wpe", tileset.file_name()));
+            let wpes = read_file(&path, parse_wpes)?;
+
+            Ok((tileset.clone(), wpes.fingerprint()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn it_identifies_a_known_palette() {
+        let (_, jungle) = parse_wpes(&[0, 0, 0, 0, 24, 36, 44, 0]).unwrap();
+        let (_, other) = parse_wpes(&[0, 0, 0, 0, 24, 36, 45, 0]).unwrap();
+        let known = [
+            (Tileset::Badlands, WPEs::empty().fingerprint()),
+            (Tileset::Jungle, jungle.fingerprint()),
+        ];
+
+        assert_that(&Tileset::identify_by_palette_in(&jungle, &known))
+            .is_equal_to(Some(Tileset::Jungle));
+        assert_that(&Tileset::identify_by_palette_in(&other, &known)).is_none();
+    }
+
+    #[test]
+    fn it_fingerprints_the_palettes_of_a_directory() {
+        let dir = std::env::temp_dir().join("bw_assets_palette_fingerprints_test");
+        fs::create_dir_all(&dir).unwrap();
+        for (i, tileset) in STOCK_TILESETS.iter().enumerate() {
+            let file_name = format!("{}.wpe", tileset.file_name());
+            fs::write(dir.join(file_name), [i as u8, 0, 0, 0]).unwrap();
+        }
+
+        let known = palette_fingerprints_from_dir(&dir).unwrap();
+        let (_, desert) = parse_wpes(&[5, 0, 0, 0]).unwrap();
+
+        assert_that(&known).has_length(8);
+        assert_that(&Tileset::identify_by_palette_in(&desert, &known))
+            .is_equal_to(Some(Tileset::Desert));
+
+        fs::remove_file(dir.join("ice.wpe")).unwrap();
+        let err = palette_fingerprints_from_dir(&dir).unwrap_err();
+        assert_that(&err.to_string()).contains("ice.wpe");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
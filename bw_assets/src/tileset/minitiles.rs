@@ -0,0 +1,119 @@
+use super::{CV5s, Elevation, VF4s, VX4s, CV5, VF4, VX4};
+use crate::map::MegaTile;
+
+/// Graphics and gameplay flags of the 16 minitiles of a megatile, paired up
+/// in row major order.
+///
+/// A tile group in the CV5 references a VX4 and VF4 block for each of its
+/// megatiles, so `megatile` picks which of the 16 blocks of `cv5` to pair.
+/// Yields nothing if the megatile references a block outside of `vx4s` or
+/// `vf4s`.
+pub fn tilegroup_minitiles<'a>(
+    cv5: &CV5,
+    megatile: &MegaTile,
+    vx4s: &'a VX4s,
+    vf4s: &'a VF4s,
+) -> impl Iterator<Item = (&'a VX4, &'a VF4)> {
+    let reference = &cv5[megatile];
+    let minitiles = vx4s.get(reference).zip(vf4s.get(reference));
+
+    minitiles
+        .into_iter()
+        .flat_map(|(vx4s, vf4s)| vx4s.iter().zip(vf4s.iter()))
+}
+
+/// Describes a megatile for debugging, e.g. to find out why a tile is not
+/// walkable.
+///
+/// The first line holds the group and subtile index, the terrain type and how
+/// many of the 16 minitiles are walkable. It is followed by the minitiles as a
+/// 4x4 grid, where walkable minitiles are `L`, `M` or `H` after their
+/// elevation and unwalkable minitiles are `#`. Missing tile groups and VF4
+/// blocks are reported instead of the grid.
+pub fn describe_tile(megatile: &MegaTile, cv5s: &CV5s, vf4s: &VF4s) -> String {
+    let mut description = format!(
+        "megatile {:#06x}: group {}, subtile {}",
+        megatile.raw(),
+        cv5s.layout().group_index(megatile),
+        megatile.subtile_index()
+    );
+
+    let cv5 = match cv5s.get(megatile) {
+        Some(cv5) => cv5,
+        None => return description + ", tile group out of range",
+    };
+    description += &format!(", terrain type {}", cv5.terrain_type().raw());
+
+    let reference = &cv5[megatile];
+    let minitiles = match vf4s.get(reference) {
+        Some(minitiles) => minitiles,
+        None => return description + &format!(", vf4 {} out of range", usize::from(reference)),
+    };
+    let walkable = minitiles.iter().filter(|vf4| vf4.is_walkable()).count();
+    description += &format!(", {}/{} minitiles walkable", walkable, minitiles.len());
+
+    for row in minitiles.chunks(4) {
+        description.push('\n');
+        description.extend(
+            row.iter()
+                .map(|vf4| match (vf4.is_walkable(), vf4.elevation()) {
+                    (false, _) => '#',
+                    (true, Elevation::Low) => 'L',
+                    (true, Elevation::Mid) => 'M',
+                    (true, Elevation::High) => 'H',
+                }),
+        );
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn le_bytes(values: impl Iterator<Item = u16>) -> Vec<u8> {
+        values
+            .flat_map(|value| value.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn it_pairs_the_16_minitiles_of_a_megatile() {
+        // subtile 1 of the tile group references block 1
+        let mut cv5 = vec![0; 52];
+        cv5[22] = 1;
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+        let vx4s = VX4s::from_bytes(&le_bytes((0..32).map(|i| i << 1))).unwrap();
+        let vf4s = VF4s::from_bytes(&le_bytes((0..32).map(|i| i % 2))).unwrap();
+        let megatile = MegaTile::from_raw(1);
+
+        let pairs = tilegroup_minitiles(&cv5s[&megatile], &megatile, &vx4s, &vf4s)
+            .map(|(vx4, vf4)| (vx4.index(), vf4.is_walkable()))
+            .collect::<Vec<_>>();
+
+        assert_that(&pairs).has_length(16);
+        assert_that(&pairs[0]).is_equal_to((16, false));
+        assert_that(&pairs[15]).is_equal_to((31, true));
+    }
+
+    #[test]
+    fn it_describes_a_megatile() {
+        // group 1 has terrain type 5 and its subtile 2 references block 1
+        let mut cv5 = vec![0; 52 * 2];
+        cv5[52] = 5;
+        cv5[52 + 24] = 1;
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+        let vf4s = VF4s::from_bytes(&le_bytes((0..32).map(|i| (i % 2) | 0x4))).unwrap();
+
+        let description = describe_tile(&MegaTile::from_raw(1 << 4 | 2), &cv5s, &vf4s);
+
+        assert_that(&description.as_str()).is_equal_to(
+            "megatile 0x0012: group 1, subtile 2, terrain type 5, 8/16 minitiles walkable\n\
+             #H#H\n#H#H\n#H#H\n#H#H",
+        );
+        assert_that(&describe_tile(&MegaTile::from_raw(2 << 4), &cv5s, &vf4s))
+            .contains("tile group out of range");
+    }
+}
@@ -8,17 +8,76 @@
 //! the color of the pixel. VF4 on the other hand show the gameplay flags such as
 //! walkable, elevation, blocks view, etc...
 
+mod assets;
+mod atlas;
+mod builder;
+mod bundle;
 mod cv5;
+mod dddata;
+mod debug;
+mod identify;
+mod minitiles;
+mod overrides;
+#[cfg(feature = "amethyst")]
+mod prefab;
+mod progress;
+#[cfg(feature = "amethyst")]
+mod reload;
+mod render;
+mod stream;
+#[cfg(feature = "image")]
+mod tiled;
+mod transition;
 mod vf4;
 mod vr4;
 mod vx4;
 mod wpe;
 
+pub use self::assets::{
+    load_tilesets, parse_tileset_bytes, vx4_vf4_consistent, TilesetAssets, TilesetError,
+};
+pub use self::atlas::TilesetAtlas;
+pub use self::builder::{TilesetAssetsBuilder, TilesetBuildError};
+pub use self::bundle::{pack_tileset_bundle, TILESET_BUNDLE_MAGIC};
 pub use self::cv5::{
-    BuildFlag, CV5Data, CV5Format, CV5s, CV5sAsset, CV5sHandle, Doodad, MinitileReference,
-    OverlayFlag, TileMetadata, CV5,
+    BuildFlag, CV5Data, CV5Header, CV5Headers, CV5Layout, CV5s, Doodad, MinitileReference,
+    OverlayFlag, TerrainType, TileMetadata, CV5,
+};
+pub use self::dddata::{DDData, DoodadDef};
+pub use self::identify::palette_fingerprints_from_dir;
+pub use self::minitiles::{describe_tile, tilegroup_minitiles};
+pub use self::overrides::TilesetOverrides;
+pub use self::progress::RenderProgress;
+pub use self::render::{
+    render_map, render_map_scaled, render_map_with_options,
+    render_map_with_progress, render_megatile, render_megatile_with_options, render_tile_id,
+    RenderError, RenderOptions,
 };
-pub use self::vf4::{VF4Format, VF4s, VF4sAsset, VF4sHandle, VF4};
-pub use self::vr4::{VR4Format, VR4s, VR4sAsset, VR4sHandle, VR4sIterator, VR4};
-pub use self::vx4::{VX4s, VX4sAsset, VX4sAssetFormat, VX4sHandle, VX4};
-pub use self::wpe::{WPEFormat, WPEs, WPEsAsset, WPEsHandle, WPE};
+pub use self::transition::transition_tile;
+pub use self::vf4::{Elevation, VF4s, VF4};
+pub use self::vr4::{VR4s, VR4sIterator, VR4sStreamParser, VR4};
+pub use self::vx4::{VX4Error, VX4s, VX4};
+pub use self::wpe::{
+    ColorOutput, PlayerColor, WPEFormat, WPEStride, WPEs, WPEsStreamParser, WPE,
+};
+
+#[cfg(feature = "amethyst")]
+pub use self::{
+    bundle::{TilesetAssetsHandle, TilesetBundleFormat},
+    cv5::{
+        CV5Format, CV5HeadersAsset, CV5HeadersFormat, CV5HeadersHandle, CV5sAsset, CV5sHandle,
+    },
+    reload::{CurrentTileset, TilesetReloadSystem},
+    vf4::{VF4Format, VF4sAsset, VF4sHandle},
+    vr4::{VR4Format, VR4sAsset, VR4sHandle},
+    vx4::{VX4sAsset, VX4sAssetFormat, VX4sHandle},
+    wpe::{WPEsAsset, WPEsHandle},
+};
+
+#[cfg(feature = "image")]
+pub use self::tiled::{export_tiled_tileset, TiledExportError, TILED_COLUMNS};
+#[cfg(feature = "image")]
+pub use self::vr4::minitile_index_image;
+
+#[cfg(feature = "amethyst")]
+pub use self::prefab::{TilesetPrefab, TILESET_SOURCE};
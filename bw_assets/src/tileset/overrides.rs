@@ -0,0 +1,118 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use super::{RenderError, TilesetAssets, VR4s};
+
+/// Replacements for individual minitiles and palette colors of a tileset, so
+/// that a mod only has to ship what it changed instead of a whole tileset.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TilesetOverrides {
+    minitiles: BTreeMap<usize, [u8; VR4s::BLOCK_SIZE]>,
+    colors: BTreeMap<usize, [u8; 3]>,
+}
+
+impl TilesetOverrides {
+    pub fn new() -> TilesetOverrides {
+        TilesetOverrides::default()
+    }
+
+    /// Replaces the 64 palette indices of the VR4 block at `index`.
+    pub fn minitile(&mut self, index: usize, pixels: [u8; VR4s::BLOCK_SIZE]) -> &mut Self {
+        self.minitiles.insert(index, pixels);
+        self
+    }
+
+    /// Replaces the rgb values of the palette color at `index`.
+    pub fn color(&mut self, index: usize, rgb: [u8; 3]) -> &mut Self {
+        self.colors.insert(index, rgb);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.minitiles.is_empty() && self.colors.is_empty()
+    }
+}
+
+impl TilesetAssets {
+    /// Replaces the minitiles and palette colors of `overrides`.
+    ///
+    /// The files are copied on write: a file that is still shared with a clone
+    /// of the tileset, e.g. from [`TilesetAssets::clone_shared`], is copied
+    /// before it is changed, so the clone keeps the original file. Files
+    /// without overrides stay shared.
+    ///
+    /// Fails without changing the tileset if an override is out of range of
+    /// the VR4 or WPE file.
+    pub fn apply_overrides(&mut self, overrides: &TilesetOverrides) -> Result<(), RenderError> {
+        if let Some((&minitile, _)) = overrides.minitiles.range(self.vr4s.len()..).next() {
+            return Err(RenderError::MinitileOutOfRange {
+                minitile,
+                len: self.vr4s.len(),
+            });
+        }
+        if let Some((&color, _)) = overrides.colors.range(self.wpes.len()..).next() {
+            return Err(RenderError::ColorOutOfRange {
+                color,
+                len: self.wpes.len(),
+            });
+        }
+
+        if !overrides.minitiles.is_empty() {
+            let vr4s = Arc::make_mut(&mut self.vr4s);
+            for (&index, pixels) in &overrides.minitiles {
+                vr4s.replace_block(index, pixels);
+            }
+        }
+        if !overrides.colors.is_empty() {
+            let wpes = Arc::make_mut(&mut self.wpes);
+            for (&index, &rgb) in &overrides.colors {
+                wpes.replace_color(index, rgb);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_overrides_a_single_palette_color() {
+        let original = TilesetAssets::synthetic();
+        let mut assets = original.clone_shared();
+
+        assets
+            .apply_overrides(TilesetOverrides::new().color(1, [255, 0, 0]))
+            .unwrap();
+        let colors =
+            |assets: &TilesetAssets| assets.wpes.iter().map(|wpe| wpe.rgb()).collect::<Vec<_>>();
+
+        assert_that(&colors(&assets)).is_equal_to(vec![
+            [0, 0, 0],
+            [255, 0, 0],
+            [170, 170, 170],
+            [255, 255, 255],
+        ]);
+        assert_that(&colors(&original)[1]).is_equal_to([85, 85, 85]);
+        assert_that(&Arc::ptr_eq(&assets.vr4s, &original.vr4s)).is_true();
+    }
+
+    #[test]
+    fn it_rejects_overrides_out_of_range() {
+        let mut assets = TilesetAssets::synthetic();
+        let mut overrides = TilesetOverrides::new();
+        overrides
+            .color(0, [1, 2, 3])
+            .minitile(1, [0; VR4s::BLOCK_SIZE]);
+
+        assert_that(&assets.apply_overrides(&overrides)).is_err_containing(
+            RenderError::MinitileOutOfRange {
+                minitile: 1,
+                len: 1,
+            },
+        );
+        assert_that(&assets.wpes.iter().next().unwrap().rgb()).is_equal_to([0, 0, 0]);
+    }
+}
@@ -0,0 +1,179 @@
+use amethyst::{
+    assets::{Asset, AssetStorage, Handle, Loader, PrefabData, ProgressCounter},
+    ecs::{Component, Entity, HashMapStorage, ReadExpect, Write, WriteStorage},
+    Error,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::super::map::Tileset;
+use super::{
+    CV5Format, CV5sAsset, TilesetAssets, VF4Format, VF4sAsset, VR4Format, VR4sAsset, VX4sAsset,
+    VX4sAssetFormat, WPEFormat, WPEsAsset,
+};
+
+/// Name of the amethyst source the tileset files are loaded from, e.g. an
+/// [`MPQSource`](crate::mpq::MPQSource) of the game archives.
+pub const TILESET_SOURCE: &str = "bw_assets";
+
+/// Declares a tileset in an amethyst prefab.
+///
+/// Loading the prefab loads the five files of the tileset from
+/// [`TILESET_SOURCE`], and spawning it inserts the loaded [`TilesetAssets`]
+/// into the entity. The tileset is named after the variants of [`Tileset`]:
+///
+/// ```ron
+/// Prefab(
+///     entities: [
+///         PrefabEntity(
+///             data: (
+///                 tileset: Badlands,
+///             ),
+///         ),
+///     ],
+/// )
+/// ```
+///
+/// The files are taken out of their assets when the prefab is spawned, so
+/// each loaded prefab can only be spawned once.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TilesetPrefab {
+    pub tileset: Tileset,
+
+    #[serde(skip)]
+    handles: Option<TilesetPrefabHandles>,
+}
+
+#[derive(Clone, Debug)]
+struct TilesetPrefabHandles {
+    cv5s: Handle<CV5sAsset>,
+    vf4s: Handle<VF4sAsset>,
+    vx4s: Handle<VX4sAsset>,
+    vr4s: Handle<VR4sAsset>,
+    wpes: Handle<WPEsAsset>,
+}
+
+impl TilesetPrefab {
+    pub fn new(tileset: Tileset) -> TilesetPrefab {
+        TilesetPrefab {
+            tileset,
+            handles: None,
+        }
+    }
+}
+
+impl Component for TilesetAssets {
+    type Storage = HashMapStorage<Self>;
+}
+
+impl<'a> PrefabData<'a> for TilesetPrefab {
+    type SystemData = (
+        ReadExpect<'a, Loader>,
+        WriteStorage<'a, TilesetAssets>,
+        Write<'a, AssetStorage<CV5sAsset>>,
+        Write<'a, AssetStorage<VF4sAsset>>,
+        Write<'a, AssetStorage<VX4sAsset>>,
+        Write<'a, AssetStorage<VR4sAsset>>,
+        Write<'a, AssetStorage<WPEsAsset>>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        system_data: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        let (_, tilesets, cv5s, vf4s, vx4s, vr4s, wpes) = system_data;
+        let handles = self.handles.as_ref().ok_or_else(|| {
+            Error::from_string(format!(
+                "{:?} tileset was spawned before it was loaded",
+                self.tileset
+            ))
+        })?;
+
+        let assets = TilesetAssets {
+            cv5s: take_file(cv5s, &handles.cv5s, CV5sAsset::take, "cv5")?,
+            vf4s: take_file(vf4s, &handles.vf4s, VF4sAsset::take, "vf4")?,
+            vx4s: take_file(vx4s, &handles.vx4s, VX4sAsset::take, "vx4")?,
+            vr4s: take_file(vr4s, &handles.vr4s, VR4sAsset::take, "vr4")?,
+            wpes: take_file(wpes, &handles.wpes, WPEsAsset::take, "wpe")?,
+        };
+        tilesets.insert(entity, assets)?;
+
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        system_data: &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        let (loader, _, cv5s, vf4s, vx4s, vr4s, wpes) = system_data;
+        let file_name = self.tileset.file_name();
+        let path = |extension: &str| format!("tileset\\{}.{}", file_name, extension);
+
+        self.handles = Some(TilesetPrefabHandles {
+            cv5s: loader.load_from(
+                path("cv5"),
+                CV5Format::default(),
+                TILESET_SOURCE,
+                &mut *progress,
+                cv5s,
+            ),
+            vf4s: loader.load_from(path("vf4"), VF4Format, TILESET_SOURCE, &mut *progress, vf4s),
+            vx4s: loader.load_from(
+                path("vx4"),
+                VX4sAssetFormat,
+                TILESET_SOURCE,
+                &mut *progress,
+                vx4s,
+            ),
+            vr4s: loader.load_from(path("vr4"), VR4Format, TILESET_SOURCE, &mut *progress, vr4s),
+            wpes: loader.load_from(
+                path("wpe"),
+                WPEFormat::default(),
+                TILESET_SOURCE,
+                &mut *progress,
+                wpes,
+            ),
+        });
+
+        Ok(true)
+    }
+}
+
+fn take_file<A: Asset, T>(
+    storage: &mut AssetStorage<A>,
+    handle: &Handle<A>,
+    take: fn(&mut A) -> Option<T>,
+    extension: &str,
+) -> Result<Arc<T>, Error> {
+    storage
+        .get_mut(handle)
+        .and_then(take)
+        .map(Arc::new)
+        .ok_or_else(|| {
+            Error::from_string(format!(
+                "{} file of the tileset was not loaded or was already taken",
+                extension
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_declares_a_tileset_in_ron() {
+        let prefab: TilesetPrefab = ron::de::from_str("(tileset: Badlands)").unwrap();
+
+        assert_that(&prefab.tileset).is_equal_to(Tileset::Badlands);
+        assert_that(&prefab.handles.is_none()).is_true();
+        assert_that(&ron::ser::to_string(&TilesetPrefab::new(Tileset::Jungle)).unwrap())
+            .is_equal_to("(tileset:Jungle)".to_string());
+    }
+}
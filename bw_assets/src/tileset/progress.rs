@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Progress of a long running render, such as building a tileset atlas.
+///
+/// Renders update the progress from every worker thread, so it can be shared
+/// with another thread (e.g. inside an `Arc`) and polled to draw a progress
+/// bar while the render is running.
+#[derive(Debug, Default)]
+pub struct RenderProgress {
+    completed: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl RenderProgress {
+    pub fn new() -> RenderProgress {
+        RenderProgress::default()
+    }
+
+    /// Number of work items finished so far.
+    pub fn completed(&self) -> usize {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    /// Number of work items in the render, or 0 if it has not started.
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of the render that is complete, from 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        match self.total() {
+            0 => 0.0,
+            total => (self.completed() as f32 / total as f32).min(1.0),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        let total = self.total();
+        total != 0 && self.completed() >= total
+    }
+
+    pub(crate) fn start(&self, total: usize) {
+        self.completed.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub(crate) fn advance(&self) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,70 @@
+use amethyst::{
+    assets::AssetStorage,
+    ecs::{Read, System, Write},
+};
+
+use super::{TilesetAssets, TilesetAssetsHandle};
+
+/// The tileset currently in use, kept up to date by [`TilesetReloadSystem`].
+#[derive(Debug, Default)]
+pub struct CurrentTileset {
+    assets: Option<TilesetAssets>,
+    reloads: usize,
+}
+
+impl CurrentTileset {
+    /// The tileset, or `None` until its bundle has finished loading.
+    pub fn assets(&self) -> Option<&TilesetAssets> {
+        self.assets.as_ref()
+    }
+
+    /// Number of times the tileset was loaded, starting at 1 for the first
+    /// load. Systems that build data from the tileset, such as textures, can
+    /// compare it to the count they last built from to know when to rebuild.
+    pub fn reloads(&self) -> usize {
+        self.reloads
+    }
+}
+
+/// Hot swaps the [`CurrentTileset`] resource when a tileset bundle is
+/// reloaded, see [`TilesetBundleFormat`](super::TilesetBundleFormat).
+///
+/// Amethyst reloads an asset when its file changes on disk if hot reloading
+/// is enabled with `HotReloadBundle`. Files in an
+/// [`MPQSource`](crate::mpq::MPQSource) never change, so the bundle has to
+/// be loaded from a directory source. The resource is replaced once the new
+/// bundle has parsed; if it fails to parse, amethyst logs the error and keeps
+/// the previous version, so the tileset in use is never left half loaded.
+pub struct TilesetReloadSystem {
+    handle: TilesetAssetsHandle,
+    version: Option<u32>,
+}
+
+impl TilesetReloadSystem {
+    pub fn new(handle: TilesetAssetsHandle) -> TilesetReloadSystem {
+        TilesetReloadSystem {
+            handle,
+            version: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for TilesetReloadSystem {
+    type SystemData = (
+        Read<'a, AssetStorage<TilesetAssets>>,
+        Write<'a, CurrentTileset>,
+    );
+
+    fn run(&mut self, (storage, mut current): Self::SystemData) {
+        let version = storage.get_version(&self.handle);
+        if version.is_none() || version == self.version {
+            return;
+        }
+
+        if let Some(assets) = storage.get(&self.handle) {
+            current.assets = Some(assets.clone_shared());
+            current.reloads += 1;
+            self.version = version;
+        }
+    }
+}
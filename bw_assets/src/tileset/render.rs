@@ -0,0 +1,429 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use snafu::{ensure, OptionExt, Snafu};
+
+use super::debug::{draw_grid, draw_tile_ids};
+use super::{ColorOutput, RenderProgress, TilesetAssets, VR4s};
+use crate::map::{MegaTile, MEGATILE_PX_SIDE_LEN, MEGATILE_SIDE_LEN};
+
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum RenderError {
+    #[snafu(display("tile group {} is out of range of {} cv5 entries", group, len))]
+    TileGroupOutOfRange { group: usize, len: usize },
+
+    #[snafu(display("megatile {} is out of range of {} vx4 entries", megatile, len))]
+    MegatileOutOfRange { megatile: usize, len: usize },
+
+    #[snafu(display("minitile {} is out of range of {} vr4 entries", minitile, len))]
+    MinitileOutOfRange { minitile: usize, len: usize },
+
+    #[snafu(display("color {} is out of range of {} wpe entries", color, len))]
+    ColorOutOfRange { color: usize, len: usize },
+
+    #[snafu(display(
+        "{} vx4 entries do not match {} vf4 entries, are they from the same tileset?",
+        vx4s,
+        vf4s
+    ))]
+    FlagCountMismatch { vx4s: usize, vf4s: usize },
+
+    #[snafu(display("expected {}x{} tiles but found {} tiles", width, height, tile_count))]
+    DimensionMismatch {
+        width: usize,
+        height: usize,
+        tile_count: usize,
+    },
+
+    #[snafu(display("tile size must be between 1 and 32 pixels but was {}", tile_px))]
+    InvalidTileSize { tile_px: u32 },
+}
+
+/// RGB
+pub const CHANNELS: usize = 3;
+
+/// Options shared by the map and megatile renderers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Color pipeline of the rendered pixels. Defaults to raw palette colors.
+    pub color_output: ColorOutput,
+
+    /// Size of every megatile in the rendered map, from 1 to 32 pixels.
+    /// Defaults to 32. Ignored when rendering a single megatile.
+    pub tile_px: u32,
+
+    /// Palette index whose pixels are fully transparent in RGBA output, such
+    /// as a [`TilesetAtlas`](super::TilesetAtlas). Usually 0, but some effects
+    /// and overlays use another index. Defaults to `None`, which renders every
+    /// pixel opaque. Ignored by the RGB renderers.
+    pub transparent_index: Option<u8>,
+
+    /// Draws a line along the top and left edge of every megatile of a
+    /// rendered map. Defaults to `false`.
+    pub debug_grid: bool,
+
+    /// Writes the raw id of every megatile of a rendered map in hexadecimal
+    /// into its top left corner, when the tiles are at least 17 pixels.
+    /// Defaults to `false`.
+    pub debug_ids: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            color_output: ColorOutput::default(),
+            tile_px: MEGATILE_PX_SIDE_LEN,
+            transparent_index: None,
+            debug_grid: false,
+            debug_ids: false,
+        }
+    }
+}
+
+/// Renders a megatile into a 32x32 RGB buffer.
+pub fn render_megatile(
+    megatile: &MegaTile,
+    assets: &TilesetAssets,
+) -> Result<Vec<u8>, RenderError> {
+    render_megatile_with_options(megatile, assets, &RenderOptions::default())
+}
+
+/// Renders a megatile into a 32x32 RGB buffer with the colors of
+/// `options.color_output`.
+pub fn render_megatile_with_options(
+    megatile: &MegaTile,
+    assets: &TilesetAssets,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, RenderError> {
+    let cv5 = assets.cv5s.get(megatile).context(TileGroupOutOfRange {
+        group: assets.cv5s.layout().group_index(megatile),
+        len: assets.cv5s.len(),
+    })?;
+    let megatile_reference = &cv5[megatile];
+
+    render_vx4_block(usize::from(megatile_reference), assets, options)
+}
+
+/// Renders the megatile at `index` of the VX4 file, i.e. regardless of the
+/// tile groups that use it.
+pub(crate) fn render_vx4_block(
+    index: usize,
+    assets: &TilesetAssets,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, RenderError> {
+    let minitiles = assets.vx4s.block(index).context(MegatileOutOfRange {
+        megatile: index,
+        len: assets.vx4s.len(),
+    })?;
+
+    let side_length = VR4s::MINITILE_SIDE_LENGTH;
+    let megatile_side_length = MEGATILE_PX_SIDE_LEN as usize;
+
+    let mut pixels = vec![0; megatile_side_length * megatile_side_length * CHANNELS];
+    for (i, minitile) in minitiles.iter().enumerate() {
+        let vr4s = assets.vr4s.get(minitile).context(MinitileOutOfRange {
+            minitile: minitile.index(),
+            len: assets.vr4s.len(),
+        })?;
+
+        let x = (i % MEGATILE_SIDE_LEN as usize) * side_length;
+        let y = (i / MEGATILE_SIDE_LEN as usize) * side_length;
+
+        for (j, vr4) in vr4s.iter().enumerate() {
+            let wpe = assets.wpes.get(vr4).context(ColorOutOfRange {
+                color: usize::from(vr4),
+                len: assets.wpes.len(),
+            })?;
+
+            let xj = if minitile.is_horizontally_flipped() {
+                side_length - 1 - j % side_length
+            } else {
+                j % side_length
+            };
+            let yj = j / side_length;
+
+            let offset = ((y + yj) * megatile_side_length + x + xj) * CHANNELS;
+            pixels[offset..offset + CHANNELS].copy_from_slice(&wpe.color(options.color_output));
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Renders a raw tile id, as stored in the MTXM chunk, into a 32x32 RGB
+/// buffer.
+pub fn render_tile_id(tile_id: u16, assets: &TilesetAssets) -> Result<Vec<u8>, RenderError> {
+    render_megatile(&MegaTile::from_raw(tile_id), assets)
+}
+
+/// Renders every megatile of a map into a single RGB buffer that is 32
+/// pixels for every tile in each dimension.
+pub fn render_map(
+    tiles: &[MegaTile],
+    width: usize,
+    height: usize,
+    assets: &TilesetAssets,
+) -> Result<Vec<u8>, RenderError> {
+    render_map_with_progress(tiles, width, height, assets, &RenderProgress::new())
+}
+
+/// Renders the map, advancing `progress` once for every megatile.
+pub fn render_map_with_progress(
+    tiles: &[MegaTile],
+    width: usize,
+    height: usize,
+    assets: &TilesetAssets,
+    progress: &RenderProgress,
+) -> Result<Vec<u8>, RenderError> {
+    render_map_with_options(
+        tiles,
+        width,
+        height,
+        assets,
+        &RenderOptions::default(),
+        progress,
+    )
+}
+
+/// Renders the map with every megatile scaled down to `tile_px` x `tile_px`
+/// pixels.
+///
+/// Megatiles are downsampled with a box filter, so a `tile_px` of 8 gives an
+/// overview of the map at 1/16 of the memory of a full render.
+pub fn render_map_scaled(
+    tiles: &[MegaTile],
+    width: usize,
+    height: usize,
+    assets: &TilesetAssets,
+    tile_px: u32,
+) -> Result<Vec<u8>, RenderError> {
+    let options = RenderOptions {
+        tile_px,
+        ..RenderOptions::default()
+    };
+
+    render_map_with_options(
+        tiles,
+        width,
+        height,
+        assets,
+        &options,
+        &RenderProgress::new(),
+    )
+}
+
+/// Renders the map with every option, advancing `progress` once for every
+/// megatile.
+pub fn render_map_with_options(
+    tiles: &[MegaTile],
+    width: usize,
+    height: usize,
+    assets: &TilesetAssets,
+    options: &RenderOptions,
+    progress: &RenderProgress,
+) -> Result<Vec<u8>, RenderError> {
+    let tile_px = options.tile_px;
+    ensure!(
+        (1..=MEGATILE_PX_SIDE_LEN).contains(&tile_px),
+        InvalidTileSize { tile_px }
+    );
+    ensure!(
+        tiles.len() == width * height,
+        DimensionMismatch {
+            width,
+            height,
+            tile_count: tiles.len()
+        }
+    );
+
+    progress.start(tiles.len());
+    let tile_px = tile_px as usize;
+    #[cfg(feature = "parallel")]
+    let megatiles = tiles.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let megatiles = tiles.iter();
+    let rendered_tiles = megatiles
+        .map(|megatile| {
+            let pixels = render_megatile_with_options(megatile, assets, options)?;
+            progress.advance();
+
+            Ok(downsample(&pixels, tile_px))
+        })
+        .collect::<Result<Vec<_>, RenderError>>()?;
+
+    let row_length = tile_px * CHANNELS;
+    let pixel_width = width * tile_px;
+    let mut pixels = vec![0; pixel_width * height * tile_px * CHANNELS];
+    for (i, tile) in rendered_tiles.iter().enumerate() {
+        let x = (i % width) * tile_px;
+        let y = (i / width) * tile_px;
+
+        for (yj, row) in tile.chunks(row_length).enumerate() {
+            let offset = ((y + yj) * pixel_width + x) * CHANNELS;
+            pixels[offset..offset + row_length].copy_from_slice(row);
+        }
+    }
+
+    if options.debug_grid {
+        draw_grid(&mut pixels, pixel_width, tile_px);
+    }
+    if options.debug_ids {
+        draw_tile_ids(&mut pixels, tiles, width, tile_px);
+    }
+
+    Ok(pixels)
+}
+
+/// Scales a rendered megatile down to `tile_px` x `tile_px` pixels by
+/// averaging the block of pixels that each scaled pixel covers.
+fn downsample(pixels: &[u8], tile_px: usize) -> Vec<u8> {
+    let side_length = MEGATILE_PX_SIDE_LEN as usize;
+    if tile_px == side_length {
+        return pixels.to_vec();
+    }
+
+    let bounds = |i: usize| (i * side_length / tile_px, (i + 1) * side_length / tile_px);
+
+    let mut downsampled = Vec::with_capacity(tile_px * tile_px * CHANNELS);
+    for y in 0..tile_px {
+        let (y0, y1) = bounds(y);
+        for x in 0..tile_px {
+            let (x0, x1) = bounds(x);
+            let area = ((y1 - y0) * (x1 - x0)) as u32;
+
+            for channel in 0..CHANNELS {
+                let sum: u32 = (y0..y1)
+                    .flat_map(|yi| (x0..x1).map(move |xi| (xi, yi)))
+                    .map(|(xi, yi)| pixels[(yi * side_length + xi) * CHANNELS + channel] as u32)
+                    .sum();
+
+                downsampled.push(((sum + area / 2) / area) as u8);
+            }
+        }
+    }
+
+    downsampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        cv5::parse_cv5s, vf4::parse_vf4s, vr4::parse_vr4s, vx4::parse_vx4s, wpe::parse_wpes,
+    };
+    use super::*;
+    use spectral::prelude::*;
+    use std::sync::Arc;
+
+    /// Two tile groups, where subtile 1 of group 1 is a megatile whose first
+    /// minitile is flipped and every minitile is a gradient of 8 colors.
+    fn assets() -> TilesetAssets {
+        let mut cv5 = vec![0; 52 * 2];
+        cv5[52 + 20 + 2] = 1;
+
+        let mut vx4 = vec![0; 32 * 2];
+        for i in 0..16 {
+            vx4[32 + i * 2] = 1 << 1;
+        }
+        vx4[32] |= 1;
+
+        let vr4 = (0..64 * 2).map(|i| (i % 8) as u8).collect::<Vec<_>>();
+        let wpe = (0..8u8)
+            .flat_map(|i| vec![i * 10, 0, 0, 0])
+            .collect::<Vec<_>>();
+
+        TilesetAssets {
+            cv5s: Arc::new(parse_cv5s(&cv5).unwrap().1),
+            vf4s: Arc::new(parse_vf4s(&[0; 32 * 2]).unwrap().1),
+            vx4s: Arc::new(parse_vx4s(&vx4).unwrap().1),
+            vr4s: Arc::new(parse_vr4s(&vr4).unwrap().1),
+            wpes: Arc::new(parse_wpes(&wpe).unwrap().1),
+        }
+    }
+
+    #[test]
+    fn it_renders_a_tile_id() {
+        let pixels = render_tile_id((1 << 4) | 1, &assets()).unwrap();
+
+        assert_that(&pixels).has_length(32 * 32 * CHANNELS);
+        // the first minitile is flipped
+        assert_that(&pixels[0..3].to_vec()).is_equal_to(vec![70, 0, 0]);
+        assert_that(&pixels[7 * 3..8 * 3].to_vec()).is_equal_to(vec![0, 0, 0]);
+        assert_that(&pixels[8 * 3..9 * 3].to_vec()).is_equal_to(vec![0, 0, 0]);
+        assert_that(&pixels[9 * 3..10 * 3].to_vec()).is_equal_to(vec![10, 0, 0]);
+    }
+
+    #[test]
+    fn it_renders_the_map_at_a_reduced_scale() {
+        let tiles = vec![MegaTile::from_raw((1 << 4) | 1); 6];
+        let pixels = render_map_scaled(&tiles, 3, 2, &assets(), 8).unwrap();
+
+        assert_that(&pixels).has_length(3 * 8 * 2 * 8 * CHANNELS);
+        // every scaled pixel averages a 4x4 block of the gradient, which
+        // averages to the same color on both halves of the flipped minitile
+        assert_that(&pixels[0..3].to_vec()).is_equal_to(vec![55, 0, 0]);
+        assert_that(&pixels[3..6].to_vec()).is_equal_to(vec![15, 0, 0]);
+        assert_that(&pixels[6..9].to_vec()).is_equal_to(vec![15, 0, 0]);
+    }
+
+    #[test]
+    fn it_renders_with_each_color_output() {
+        let megatile = MegaTile::from_raw((1 << 4) | 1);
+        let render = |color_output| {
+            let options = RenderOptions {
+                color_output,
+                ..RenderOptions::default()
+            };
+            render_megatile_with_options(&megatile, &assets(), &options).unwrap()
+        };
+
+        // the first pixel is color 7 of the gradient, (70, 0, 0)
+        assert_that(&render(ColorOutput::Raw)[0..3].to_vec()).is_equal_to(vec![70, 0, 0]);
+        assert_that(&render(ColorOutput::Srgb)[0..3].to_vec()).is_equal_to(vec![142, 0, 0]);
+        assert_that(&render(ColorOutput::Linear)[0..3].to_vec()).is_equal_to(vec![16, 0, 0]);
+    }
+
+    #[test]
+    fn it_draws_a_debug_grid_between_megatiles() {
+        let tiles = vec![MegaTile::from_raw((1 << 4) | 1); 4];
+        let options = RenderOptions {
+            debug_grid: true,
+            ..RenderOptions::default()
+        };
+
+        let plain = render_map(&tiles, 2, 2, &assets()).unwrap();
+        let grid =
+            render_map_with_options(&tiles, 2, 2, &assets(), &options, &RenderProgress::new())
+                .unwrap();
+        let pixel = |pixels: &[u8], x: usize, y: usize| {
+            let offset = (y * 64 + x) * CHANNELS;
+            pixels[offset..offset + CHANNELS].to_vec()
+        };
+
+        assert_that(&pixel(&grid, 32, 5)).is_equal_to(vec![255, 0, 255]);
+        assert_that(&pixel(&grid, 5, 32)).is_equal_to(vec![255, 0, 255]);
+        assert_that(&pixel(&plain, 32, 5)).is_not_equal_to(vec![255, 0, 255]);
+        assert_that(&pixel(&grid, 33, 5)).is_equal_to(pixel(&plain, 33, 5));
+    }
+
+    #[test]
+    fn it_rejects_invalid_tile_sizes() {
+        let tiles = vec![MegaTile::from_raw(0); 4];
+
+        assert_that(&render_map_scaled(&tiles, 2, 2, &assets(), 0))
+            .is_err_containing(RenderError::InvalidTileSize { tile_px: 0 });
+        assert_that(&render_map_scaled(&tiles, 2, 2, &assets(), 33))
+            .is_err_containing(RenderError::InvalidTileSize { tile_px: 33 });
+        assert_that(&render_map(&tiles, 3, 2, &assets())).is_err_containing(
+            RenderError::DimensionMismatch {
+                width: 3,
+                height: 2,
+                tile_count: 4,
+            },
+        );
+    }
+
+    #[test]
+    fn it_errors_on_out_of_range_tile_groups() {
+        assert_that(&render_tile_id(5 << 4, &assets()))
+            .is_err_containing(RenderError::TileGroupOutOfRange { group: 5, len: 2 });
+    }
+}
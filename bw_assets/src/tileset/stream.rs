@@ -0,0 +1,26 @@
+use std::io::{self, Read};
+
+/// Fills `block` from the reader.
+///
+/// Returns `Ok(false)` if the reader was already at the end of its input, and
+/// an `UnexpectedEof` error if the input ended partway through the block.
+pub(super) fn read_block<R: Read>(reader: &mut R, block: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match reader.read(&mut block[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    match filled {
+        0 => Ok(false),
+        n if n == block.len() => Ok(true),
+        n => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("partial block of {} out of {} bytes", n, block.len()),
+        )),
+    }
+}
@@ -0,0 +1,149 @@
+//! Export of a tileset to the [Tiled](https://www.mapeditor.org) map editor.
+use snafu::{ResultExt, Snafu};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::render::{render_vx4_block, CHANNELS};
+use super::{RenderError, RenderOptions, TilesetAssets};
+use crate::map::MEGATILE_PX_SIDE_LEN;
+
+/// Number of megatiles in each row of the sprite sheet.
+pub const TILED_COLUMNS: usize = 16;
+
+#[derive(Debug, Snafu)]
+pub enum TiledExportError {
+    #[snafu(display("failed to render megatile {}: {}", megatile, source))]
+    RenderMegatile {
+        megatile: usize,
+        source: RenderError,
+    },
+
+    #[snafu(display("failed to write {}: {}", path.display(), source))]
+    WriteImage {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    #[snafu(display("failed to write {}: {}", path.display(), source))]
+    WriteTileset { path: PathBuf, source: io::Error },
+}
+
+/// Writes every megatile of the tileset as a Tiled tileset: a PNG sprite
+/// sheet next to `tsx_path`, with the same name and a `png` extension, and the
+/// TSX file at `tsx_path` that references it.
+///
+/// The sheet is [`TILED_COLUMNS`] megatiles wide and the tiles of the TSX are
+/// numbered like the megatiles of the VX4 file, so a layer exported from
+/// Tiled maps straight back to megatile indices.
+pub fn export_tiled_tileset(
+    assets: &TilesetAssets,
+    tsx_path: &Path,
+) -> Result<(), TiledExportError> {
+    let side_length = MEGATILE_PX_SIDE_LEN as usize;
+    let tile_count = assets.vx4s.len();
+    let rows = (tile_count + TILED_COLUMNS - 1) / TILED_COLUMNS;
+    let (width, height) = (TILED_COLUMNS * side_length, rows.max(1) * side_length);
+
+    let options = RenderOptions::default();
+    let row_length = side_length * CHANNELS;
+    let mut pixels = vec![0; width * height * CHANNELS];
+    for megatile in 0..tile_count {
+        let tile =
+            render_vx4_block(megatile, assets, &options).context(RenderMegatile { megatile })?;
+        let x = (megatile % TILED_COLUMNS) * side_length;
+        let y = (megatile / TILED_COLUMNS) * side_length;
+
+        for (yj, row) in tile.chunks(row_length).enumerate() {
+            let offset = ((y + yj) * width + x) * CHANNELS;
+            pixels[offset..offset + row_length].copy_from_slice(row);
+        }
+    }
+
+    let png_path = tsx_path.with_extension("png");
+    image::save_buffer(
+        &png_path,
+        &pixels,
+        width as u32,
+        height as u32,
+        image::ColorType::Rgb8,
+    )
+    .context(WriteImage { path: &png_path })?;
+
+    let name = tsx_path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let image_source = png_path.file_name().map_or_else(String::new, |file_name| {
+        file_name.to_string_lossy().into_owned()
+    });
+    let tsx = tsx(&name, &image_source, tile_count, (width, height));
+    fs::write(tsx_path, tsx).context(WriteTileset { path: tsx_path })?;
+
+    Ok(())
+}
+
+fn tsx(
+    name: &str,
+    image_source: &str,
+    tile_count: usize,
+    (width, height): (usize, usize),
+) -> String {
+    let side_length = MEGATILE_PX_SIDE_LEN;
+
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<tileset version=\"1.2\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" ",
+            "tilecount=\"{}\" columns=\"{}\">\n",
+            " <image source=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+            "</tileset>\n"
+        ),
+        escape_xml(name),
+        side_length,
+        side_length,
+        tile_count,
+        TILED_COLUMNS,
+        escape_xml(image_source),
+        width,
+        height
+    )
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_exports_a_tiled_tileset() {
+        let assets = TilesetAssets::synthetic();
+        let dir = std::env::temp_dir().join("bw_assets_tiled_export_test");
+        fs::create_dir_all(&dir).unwrap();
+        let tsx_path = dir.join("synthetic.tsx");
+
+        export_tiled_tileset(&assets, &tsx_path).unwrap();
+        let tsx = fs::read_to_string(&tsx_path).unwrap();
+        let png = image::open(dir.join("synthetic.png")).unwrap().to_rgb8();
+
+        assert_that(&tsx).contains(&*format!("tilecount=\"{}\"", assets.vx4s.len()));
+        assert_that(&tsx).contains("<image source=\"synthetic.png\" width=\"512\" height=\"32\"/>");
+        assert_that(&png.width()).is_equal_to(512);
+        assert_that(&png.height()).is_equal_to(32);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_escapes_names_in_the_tsx() {
+        assert_that(&escape_xml("a<b>&\"c\""))
+            .is_equal_to("a&lt;b&gt;&amp;&quot;c&quot;".to_string());
+    }
+}
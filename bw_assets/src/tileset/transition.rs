@@ -0,0 +1,77 @@
+use super::{CV5s, TerrainType, CV5};
+use crate::map::{Direction, MegaTile};
+
+/// First megatile of a tile group that blends `from` into `to` towards
+/// `direction`, e.g. dirt on the west side fading into grass on the east side.
+///
+/// A group matches when its edge towards `direction` is `to` and its edge on
+/// the opposite side is `from`, with the edges compared against the raw
+/// terrain types. Doodads never match. Returns `None` for diagonal directions,
+/// since tile groups only have edges on their four sides.
+pub fn transition_tile(
+    from: TerrainType,
+    to: TerrainType,
+    direction: Direction,
+    cv5s: &CV5s,
+) -> Option<MegaTile> {
+    if direction.is_diagonal() {
+        return None;
+    }
+    let opposite = direction.opposite();
+
+    cv5s.iter()
+        .position(|cv5| match cv5 {
+            CV5::Doodad(_) => false,
+            CV5::TileMetadata(_) => {
+                let header = cv5.header();
+                header.edge(direction) == Some(to.raw())
+                    && header.edge(opposite) == Some(from.raw())
+            }
+        })
+        .map(|group| MegaTile::from_raw((group as u16) << 4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    // stand-ins for the dirt and grass of the badlands tileset
+    const DIRT: u16 = 2;
+    const GRASS: u16 = 4;
+
+    fn tile_group(edges: [u16; 4]) -> Vec<u8> {
+        let mut group = vec![0; 52];
+        for (i, edge) in edges.iter().enumerate() {
+            group[4 + i * 2..6 + i * 2].copy_from_slice(&edge.to_le_bytes());
+        }
+
+        group
+    }
+
+    #[test]
+    fn it_finds_the_group_blending_dirt_into_grass() {
+        // left, top, right and bottom edges
+        let cv5 = [
+            tile_group([DIRT, DIRT, DIRT, DIRT]),
+            tile_group([GRASS, DIRT, DIRT, DIRT]),
+            tile_group([DIRT, GRASS, DIRT, GRASS]),
+            tile_group([DIRT, DIRT, GRASS, DIRT]),
+        ]
+        .concat();
+        let cv5s = CV5s::from_bytes(&cv5).unwrap();
+        let transition = |direction| {
+            transition_tile(
+                TerrainType::new(DIRT),
+                TerrainType::new(GRASS),
+                direction,
+                &cv5s,
+            )
+        };
+
+        assert_that(&transition(Direction::East)).is_equal_to(Some(MegaTile::from_raw(3 << 4)));
+        assert_that(&transition(Direction::West)).is_equal_to(Some(MegaTile::from_raw(1 << 4)));
+        assert_that(&transition(Direction::North)).is_none();
+        assert_that(&transition(Direction::NorthEast)).is_none();
+    }
+}
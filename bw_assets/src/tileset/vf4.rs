@@ -1,3 +1,4 @@
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::{Asset, Format, Handle},
     ecs::DenseVecStorage,
@@ -6,12 +7,24 @@ use nom::{
     combinator::{all_consuming, map},
     multi::{count, many0},
     number::complete::le_u16,
+    IResult,
 };
-use nom::{Finish, IResult};
 use std::ops::Index;
 
+use super::assets::blocks_heap_size;
 use super::MinitileReference;
 
+use crate::error::{finish_parse, ParseError};
+
+/// Ground level of a minitile. Units can only see up to higher ground if
+/// they are on a ramp or have vision from above.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Elevation {
+    Low,
+    Mid,
+    High,
+}
+
 /// MiniTile graphic references for each MegaTile. Referenced by CV5.
 #[derive(Debug)]
 pub struct VF4(u16);
@@ -41,9 +54,14 @@ impl VF4 {
     const WALKABLE: u16 = 0x0001;
     const MID: u16 = 0x0002;
     const HIGH: u16 = 0x0004;
-    const LOW: u16 = 0x0004 | 0x0002;
     const BLOCKS_VIEW: u16 = 0x0008;
     const RAMP: u16 = 0x0010;
+    /// Set on the minitiles along the edge of a cliff. The wiki lists it as
+    /// temporary because the editor uses it while placing terrain.
+    const CLIFF_EDGE: u16 = 0x0020;
+    /// Bits the terrain format does not define. They are kept so that tools
+    /// can round trip modded tilesets.
+    const UNKNOWN: u16 = 0xffc0;
 
     pub fn is_walkable(&self) -> bool {
         return self.0 & VF4::WALKABLE == VF4::WALKABLE;
@@ -57,8 +75,21 @@ impl VF4 {
         return self.0 & VF4::HIGH == VF4::HIGH;
     }
 
+    /// Low ground has neither the mid nor the high elevation flag.
     pub fn is_elevation_low(&self) -> bool {
-        return self.0 & VF4::LOW == VF4::LOW;
+        return self.0 & (VF4::MID | VF4::HIGH) == 0;
+    }
+
+    /// Decodes the elevation flags. A minitile with both the mid and high
+    /// flags set is high ground.
+    pub fn elevation(&self) -> Elevation {
+        if self.is_elevation_high() {
+            Elevation::High
+        } else if self.is_elevation_mid() {
+            Elevation::Mid
+        } else {
+            Elevation::Low
+        }
     }
 
     pub fn blocks_view(&self) -> bool {
@@ -68,50 +99,149 @@ impl VF4 {
     pub fn is_ramp(&self) -> bool {
         return self.0 & VF4::RAMP == VF4::RAMP;
     }
+
+    pub fn is_cliff_edge(&self) -> bool {
+        return self.0 & VF4::CLIFF_EDGE == VF4::CLIFF_EDGE;
+    }
+
+    /// The bits of the flag word that have no known meaning.
+    pub fn unknown_flags(&self) -> u16 {
+        return self.0 & VF4::UNKNOWN;
+    }
+
+    /// The flag word as it is stored in the VF4 file.
+    pub fn raw(&self) -> u16 {
+        return self.0;
+    }
 }
 
 impl VF4s {
     /// Each megatile has 16 (4x4) minitiles.
     const BLOCK_SIZE: usize = 16;
+
+    /// Creates an empty list of VF4, mostly useful for testing.
+    pub fn empty() -> VF4s {
+        VF4s(vec![])
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn heap_size(&self) -> usize {
+        blocks_heap_size(self.0.capacity(), &self.0)
+    }
+
+    /// Flags of the minitiles of a megatile, or `None` if the reference is
+    /// out of range.
+    pub fn get(&self, minitile_reference: &MinitileReference) -> Option<&Vec<VF4>> {
+        self.0.get(usize::from(minitile_reference))
+    }
+
+    /// Flags of the block at `index`, or `None` if it is out of range.
+    pub fn block(&self, index: usize) -> Option<&Vec<VF4>> {
+        self.0.get(index)
+    }
+}
+
+impl Index<usize> for VF4s {
+    type Output = Vec<VF4>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
 }
 
-fn parse_vf4s(b: &[u8]) -> IResult<&[u8], VF4s> {
+pub(crate) fn parse_vf4s(b: &[u8]) -> IResult<&[u8], VF4s> {
     all_consuming(map(many0(count(map(le_u16, VF4), VF4s::BLOCK_SIZE)), VF4s))(b)
 }
 
+#[cfg(feature = "amethyst")]
 pub struct VF4sAsset(Option<VF4s>);
 
+#[cfg(feature = "amethyst")]
 impl VF4sAsset {
     pub fn take(&mut self) -> Option<VF4s> {
         self.0.take()
     }
 }
 
+#[cfg(feature = "amethyst")]
 pub type VF4sHandle = Handle<VF4sAsset>;
 
+#[cfg(feature = "amethyst")]
 impl Asset for VF4sAsset {
     const NAME: &'static str = "bw_assets::tileset::VF4sAsset";
     type Data = Self;
     type HandleStorage = DenseVecStorage<VF4sHandle>;
 }
 
+impl VF4s {
+    pub fn from_bytes(b: &[u8]) -> Result<VF4s, ParseError> {
+        finish_parse("vf4", b, parse_vf4s(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct VF4Format;
 
+#[cfg(feature = "amethyst")]
 impl Format<VF4sAsset> for VF4Format {
     fn name(&self) -> &'static str {
         "VF4Format"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<VF4sAsset> {
-        let (_, vf4s) = parse_vf4s(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load vf4 asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(VF4sAsset(Some(vf4s)))
+        Ok(VF4sAsset(Some(VF4s::from_bytes(&b)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn it_indexes_blocks_by_number() {
+        let b = [[1u8, 0], [0, 0]].repeat(VF4s::BLOCK_SIZE).concat();
+        let vf4s = VF4s::from_bytes(&b).unwrap();
+
+        assert_that(&vf4s[0][0].is_walkable()).is_true();
+        assert_that(&vf4s[1][15].is_walkable()).is_false();
+        assert_that(&vf4s.block(1).map(Vec::len)).is_equal_to(Some(16));
+        assert_that(&vf4s.block(2)).is_none();
+    }
+
+    #[test]
+    fn it_reads_the_cliff_edge_flag() {
+        assert_that(&VF4(0x0020).is_cliff_edge()).is_true();
+        assert_that(&VF4(0x0020).is_ramp()).is_false();
+        assert_that(&VF4(0x0010).is_cliff_edge()).is_false();
+    }
+
+    #[test]
+    fn it_decodes_the_elevation() {
+        assert_that(&VF4(0x0001).elevation()).is_equal_to(Elevation::Low);
+        assert_that(&VF4(0x0001).is_elevation_low()).is_true();
+        assert_that(&VF4(0x0003).elevation()).is_equal_to(Elevation::Mid);
+        assert_that(&VF4(0x0003).is_elevation_low()).is_false();
+        assert_that(&VF4(0x0005).elevation()).is_equal_to(Elevation::High);
+        assert_that(&VF4(0x0007).elevation()).is_equal_to(Elevation::High);
+    }
+
+    #[test]
+    fn it_keeps_every_unknown_flag_bit() {
+        for bit in 6..16 {
+            let vf4 = VF4((1 << bit) | VF4::WALKABLE);
+
+            assert_that(&vf4.unknown_flags()).is_equal_to(1 << bit);
+            assert_that(&vf4.raw()).is_equal_to((1 << bit) | VF4::WALKABLE);
+        }
+        assert_that(&VF4(0x003f).unknown_flags()).is_equal_to(0);
     }
 }
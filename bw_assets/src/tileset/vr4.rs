@@ -1,3 +1,4 @@
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::{Asset, Format, Handle},
     ecs::DenseVecStorage,
@@ -6,18 +7,27 @@ use nom::{
     combinator::{all_consuming, map},
     multi::{count, many0},
     number::complete::le_u8,
+    IResult,
 };
-use nom::{Finish, IResult};
 
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::ops::Index;
+use std::{io, io::BufRead, ops::Index};
 
-use super::VX4;
+use super::{assets::blocks_heap_size, stream::read_block, VX4};
+
+use crate::error::{finish_parse, ParseError};
 
 /// Index to WPE (pixel color)
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct VR4(u8);
 
+impl VR4 {
+    pub fn new(palette_index: u8) -> VR4 {
+        VR4(palette_index)
+    }
+}
+
 impl From<VR4> for usize {
     fn from(vr4: VR4) -> Self {
         usize::from(&vr4)
@@ -34,7 +44,7 @@ fn parse_vr4(b: &[u8]) -> IResult<&[u8], VR4> {
     map(le_u8, VR4)(b)
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct VR4s(Vec<Vec<VR4>>);
 
 impl VR4s {
@@ -43,17 +53,94 @@ impl VR4s {
     /// 8x8 = 64 pixels
     pub const BLOCK_SIZE: usize = 64;
 
+    /// Creates an empty list of VR4, mostly useful for testing.
+    pub fn empty() -> VR4s {
+        VR4s(vec![])
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn heap_size(&self) -> usize {
+        blocks_heap_size(self.0.capacity(), &self.0)
+    }
+
+    /// Pixels of the block at `index`, or `None` if it is out of range.
+    pub fn block(&self, index: usize) -> Option<&Vec<VR4>> {
+        self.0.get(index)
+    }
+
+    /// Replaces the pixels of the block at `index`, which must be in range.
+    pub(crate) fn replace_block(&mut self, index: usize, pixels: &[u8; VR4s::BLOCK_SIZE]) {
+        self.0[index] = pixels.iter().copied().map(VR4).collect();
+    }
+
+    /// Pixels of the minitile, or `None` if the minitile is out of range.
+    pub fn get(&self, vx4: &VX4) -> Option<&Vec<VR4>> {
+        self.0.get(vx4.index())
+    }
+
+    /// Pixels of the minitile in the bytes of a VR4 file, without parsing the
+    /// rest of the file, or `None` if the minitile is out of range.
+    pub fn block_bytes<'a>(b: &'a [u8], vx4: &VX4) -> Option<&'a [u8]> {
+        let start = vx4.try_index(VR4s::BLOCK_SIZE)?;
+        let end = start.checked_add(VR4s::BLOCK_SIZE)?;
+
+        b.get(start..end)
+    }
+
     pub fn iter(&self) -> VR4sIterator {
         VR4sIterator(self.0.iter())
     }
 
+    #[cfg(feature = "parallel")]
     pub fn par_iter(&self) -> rayon::slice::Iter<Vec<VR4>> {
         self.0.par_iter()
     }
+
+    /// Which of the 256 palette indices are referenced by any pixel of any
+    /// minitile, e.g. to find palette slots that are free for custom colors.
+    pub fn used_palette_indices(&self) -> [bool; 256] {
+        used_palette_indices(&self.0)
+    }
+}
+
+#[cfg(feature = "parallel")]
+fn used_palette_indices(minitiles: &[Vec<VR4>]) -> [bool; 256] {
+    minitiles
+        .par_iter()
+        .fold(
+            || [false; 256],
+            |used, minitile| mark_used_palette_indices(used, minitile),
+        )
+        .reduce(
+            || [false; 256],
+            |mut used, other| {
+                for (used, other) in used.iter_mut().zip(other.iter()) {
+                    *used |= other;
+                }
+                used
+            },
+        )
+}
+
+#[cfg(not(feature = "parallel"))]
+fn used_palette_indices(minitiles: &[Vec<VR4>]) -> [bool; 256] {
+    minitiles.iter().fold([false; 256], |used, minitile| {
+        mark_used_palette_indices(used, minitile)
+    })
+}
+
+fn mark_used_palette_indices(mut used: [bool; 256], minitile: &[VR4]) -> [bool; 256] {
+    for vr4 in minitile {
+        used[vr4.0 as usize] = true;
+    }
+    used
 }
 
 impl Index<VX4> for VR4s {
@@ -72,6 +159,29 @@ impl Index<&VX4> for VR4s {
     }
 }
 
+impl Index<usize> for VR4s {
+    type Output = Vec<VR4>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+/// Grayscale image of a minitile, where the gray value of each pixel is its
+/// palette index rather than its color.
+///
+/// Useful to see which palette ranges a minitile uses, e.g. the team colors or
+/// the indices that are cycled. Pixels past the end of a short block are 0.
+#[cfg(feature = "image")]
+pub fn minitile_index_image(vr4_block: &[VR4]) -> image::GrayImage {
+    let side_len = VR4s::MINITILE_SIDE_LENGTH as u32;
+
+    image::GrayImage::from_fn(side_len, side_len, |x, y| {
+        let i = (y * side_len + x) as usize;
+        image::Luma([vr4_block.get(i).map_or(0, |vr4| vr4.0)])
+    })
+}
+
 pub struct VR4sIterator<'a>(std::slice::Iter<'a, Vec<VR4>>);
 
 impl<'a> Iterator for VR4sIterator<'a> {
@@ -82,43 +192,183 @@ impl<'a> Iterator for VR4sIterator<'a> {
     }
 }
 
-fn parse_vr4s(b: &[u8]) -> IResult<&[u8], VR4s> {
+/// Parses VR4 blocks one at a time from a reader.
+///
+/// Each item is the 64 pixels of a single minitile, so the whole file never
+/// has to be held in memory. A final block that is shorter than 64 bytes is
+/// yielded as an `UnexpectedEof` error, after which the parser is exhausted.
+pub struct VR4sStreamParser<R> {
+    reader: R,
+    finished: bool,
+}
+
+impl<R: BufRead> VR4sStreamParser<R> {
+    pub fn new(reader: R) -> VR4sStreamParser<R> {
+        VR4sStreamParser {
+            reader,
+            finished: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for VR4sStreamParser<R> {
+    type Item = io::Result<Vec<VR4>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut block = [0; VR4s::BLOCK_SIZE];
+        match read_block(&mut self.reader, &mut block) {
+            Ok(true) => Some(Ok(block.iter().copied().map(VR4).collect())),
+            Ok(false) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_vr4s(b: &[u8]) -> IResult<&[u8], VR4s> {
     all_consuming(map(many0(count(parse_vr4, VR4s::BLOCK_SIZE)), VR4s))(b)
 }
 
+#[cfg(feature = "amethyst")]
 pub struct VR4sAsset(Option<VR4s>);
 
+#[cfg(feature = "amethyst")]
 impl VR4sAsset {
     pub fn take(&mut self) -> Option<VR4s> {
         self.0.take()
     }
 }
 
+#[cfg(feature = "amethyst")]
 pub type VR4sHandle = Handle<VR4sAsset>;
 
+#[cfg(feature = "amethyst")]
 impl Asset for VR4sAsset {
     const NAME: &'static str = "bw_assets:tileset::VR4sAsset";
     type Data = Self;
     type HandleStorage = DenseVecStorage<VR4sHandle>;
 }
 
+impl VR4s {
+    pub fn from_bytes(b: &[u8]) -> Result<VR4s, ParseError> {
+        finish_parse("vr4", b, parse_vr4s(b))
+    }
+}
+
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct VR4Format;
 
+#[cfg(feature = "amethyst")]
 impl Format<VR4sAsset> for VR4Format {
     fn name(&self) -> &'static str {
         "VR4Format"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<VR4sAsset> {
-        let (_, vr4s) = parse_vr4s(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load vr4 asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(VR4sAsset(Some(vr4s)))
+        Ok(VR4sAsset(Some(VR4s::from_bytes(&b)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tileset::VX4s;
+    use spectral::prelude::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn it_streams_vr4_blocks() {
+        let b = (0..VR4s::BLOCK_SIZE * 3)
+            .map(|i| (i / VR4s::BLOCK_SIZE) as u8)
+            .collect::<Vec<_>>();
+
+        let blocks = VR4sStreamParser::new(Cursor::new(b))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_that(&blocks).has_length(3);
+        for (i, block) in blocks.iter().enumerate() {
+            assert_that(block).has_length(VR4s::BLOCK_SIZE);
+            assert_that(&block.iter().all(|vr4| usize::from(vr4) == i)).is_true();
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn it_maps_palette_indices_to_gray_values() {
+        let b = (0..VR4s::BLOCK_SIZE)
+            .map(|i| (i * 3) as u8)
+            .collect::<Vec<_>>();
+        let vr4s = VR4s::from_bytes(&b).unwrap();
+
+        let image = minitile_index_image(vr4s.iter().next().unwrap());
+
+        assert_that(&image.dimensions()).is_equal_to((8, 8));
+        assert_that(&image.get_pixel(0, 0).0).is_equal_to([0]);
+        assert_that(&image.get_pixel(7, 0).0).is_equal_to([21]);
+        assert_that(&image.get_pixel(1, 2).0).is_equal_to([51]);
+        assert_that(&image.into_raw()).is_equal_to(b);
+    }
+
+    #[test]
+    fn it_finds_the_palette_indices_in_use() {
+        let b = (0..VR4s::BLOCK_SIZE * 5)
+            .map(|i| (i % 4) as u8)
+            .collect::<Vec<_>>();
+        let vr4s = VR4s::from_bytes(&b).unwrap();
+
+        let used = vr4s.used_palette_indices();
+
+        assert_that(&used[..4].iter().all(|&used| used)).is_true();
+        assert_that(&used[4..].iter().any(|&used| used)).is_false();
+        assert_that(&VR4s::empty().used_palette_indices().contains(&true)).is_false();
+    }
+
+    #[test]
+    fn it_indexes_blocks_by_number() {
+        let b = (0..VR4s::BLOCK_SIZE * 3)
+            .map(|i| i as u8)
+            .collect::<Vec<_>>();
+        let vr4s = VR4s::from_bytes(&b).unwrap();
+
+        assert_that(&usize::from(&vr4s[0][0])).is_equal_to(0);
+        assert_that(&usize::from(&vr4s[2][63])).is_equal_to(191);
+        assert_that(&vr4s.block(2).map(Vec::len)).is_equal_to(Some(64));
+        assert_that(&vr4s.block(3)).is_none();
+    }
+
+    #[test]
+    fn it_slices_a_block_out_of_the_file() {
+        let b = (0..VR4s::BLOCK_SIZE * 2)
+            .map(|i| (i / VR4s::BLOCK_SIZE) as u8)
+            .collect::<Vec<_>>();
+        // minitiles 1 and 0x7fff, the largest a VX4 can reference
+        let vx4s = VX4s::from_bytes(&[[2u8, 0], [0xff, 0xff]].repeat(8).concat()).unwrap();
+        let vx4s = vx4s.iter().next().unwrap();
+
+        assert_that(&VR4s::block_bytes(&b, &vx4s[0])).is_equal_to(Some(&b[64..]));
+        assert_that(&vx4s[1].try_index(usize::MAX)).is_none();
+        assert_that(&VR4s::block_bytes(&b, &vx4s[1])).is_none();
+    }
+
+    #[test]
+    fn it_errors_on_a_partial_vr4_block() {
+        let b = vec![0; VR4s::BLOCK_SIZE + 10];
+        let mut parser = VR4sStreamParser::new(Cursor::new(b));
+
+        assert_that(&parser.next().unwrap().is_ok()).is_true();
+        let err = parser.next().unwrap().unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::UnexpectedEof);
+        assert_that(&parser.next().is_none()).is_true();
     }
 }
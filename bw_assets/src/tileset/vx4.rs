@@ -1,5 +1,6 @@
 use std::ops::Index;
 
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::Format,
     assets::{Asset, Handle},
@@ -10,10 +11,25 @@ use nom::{
     multi::count,
     multi::many0,
     number::complete::le_u16,
-    Finish, IResult,
+    IResult,
 };
-
-use super::MinitileReference;
+use snafu::{ensure, Snafu};
+
+use super::assets::blocks_heap_size;
+use super::{MinitileReference, VR4s};
+
+use crate::error::{finish_parse, ParseError};
+
+/// Error returned by [`VX4::new`].
+#[derive(Debug, Snafu, PartialEq, Eq)]
+pub enum VX4Error {
+    #[snafu(display(
+        "vr4 index {} does not fit in a vx4, the largest is {}",
+        index,
+        VX4::MAX_INDEX
+    ))]
+    IndexOutOfRange { index: usize },
+}
 
 /// Mini-tile image pointer. Referenced by CV5.
 ///
@@ -23,6 +39,20 @@ use super::MinitileReference;
 pub struct VX4(u16);
 
 impl VX4 {
+    /// Largest VR4 index a VX4 can reference, in its 15 high bits.
+    pub const MAX_INDEX: usize = 0x7fff;
+
+    /// Packs a reference to the VR4 at `vr4_index`, e.g. to encode a modded
+    /// tileset. Fails if the index does not fit in 15 bits.
+    pub fn new(vr4_index: usize, flipped: bool) -> Result<VX4, VX4Error> {
+        ensure!(
+            vr4_index <= VX4::MAX_INDEX,
+            IndexOutOfRange { index: vr4_index }
+        );
+
+        Ok(VX4((vr4_index as u16) << 1 | flipped as u16))
+    }
+
     pub fn is_horizontally_flipped(&self) -> bool {
         return self.0 & 1 == 1;
     }
@@ -30,6 +60,13 @@ impl VX4 {
     pub fn index(&self) -> usize {
         return (self.0 >> 1) as usize;
     }
+
+    /// Offset of the minitile's VR4 block in a flat buffer of blocks that are
+    /// `block_size` long each, or `None` if the offset overflows `usize`, e.g.
+    /// for a corrupt VX4 on a 16 bit target.
+    pub fn try_index(&self, block_size: usize) -> Option<usize> {
+        self.index().checked_mul(block_size)
+    }
 }
 
 fn parse_vx4(b: &[u8]) -> IResult<&[u8], VX4> {
@@ -43,9 +80,48 @@ impl VX4s {
     /// Each megatile has 16 (4x4) minitiles.
     pub const BLOCK_SIZE: usize = 16;
 
+    /// Creates an empty list of VX4, mostly useful for testing.
+    pub fn empty() -> VX4s {
+        VX4s(vec![])
+    }
+
     pub fn len(&self) -> usize {
         self.0.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn heap_size(&self) -> usize {
+        blocks_heap_size(self.0.capacity(), &self.0)
+    }
+
+    /// Minitiles of the megatile, or `None` if the reference is out of range.
+    pub fn get(&self, megatile_reference: &MinitileReference) -> Option<&Vec<VX4>> {
+        self.0.get(usize::from(megatile_reference))
+    }
+
+    /// Minitiles of the block at `index`, or `None` if it is out of range.
+    pub fn block(&self, index: usize) -> Option<&Vec<VX4>> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<Vec<VX4>> {
+        self.0.iter()
+    }
+
+    /// Highest VR4 index referenced by any minitile, or 0 if there are no
+    /// minitiles.
+    pub fn max_vr4_index(&self) -> usize {
+        self.0.iter().flatten().map(VX4::index).max().unwrap_or(0)
+    }
+
+    /// Whether every minitile references a VR4 that exists in `vr4s`, i.e.
+    /// the VX4 and VR4 files belong to the same tileset.
+    pub fn references_fit(&self, vr4s: &VR4s) -> bool {
+        self.0.iter().flatten().all(|vx4| vx4.index() < vr4s.len())
+    }
 }
 
 impl Index<MinitileReference> for VX4s {
@@ -64,45 +140,105 @@ impl Index<&MinitileReference> for VX4s {
     }
 }
 
-fn parse_vx4s(b: &[u8]) -> IResult<&[u8], VX4s> {
+impl Index<usize> for VX4s {
+    type Output = Vec<VX4>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+pub(crate) fn parse_vx4s(b: &[u8]) -> IResult<&[u8], VX4s> {
     all_consuming(map(many0(count(parse_vx4, VX4s::BLOCK_SIZE)), VX4s))(b)
 }
 
+impl VX4s {
+    pub fn from_bytes(b: &[u8]) -> Result<VX4s, ParseError> {
+        finish_parse("vx4", b, parse_vx4s(b))
+    }
+}
+
 /// This asset is a singleton so we will load it and then take it out of the option.
 /// Amethyst will then drop the handle.
+#[cfg(feature = "amethyst")]
 pub struct VX4sAsset(Option<VX4s>);
 
+#[cfg(feature = "amethyst")]
 impl VX4sAsset {
     pub fn take(&mut self) -> Option<VX4s> {
         self.0.take()
     }
 }
 
+#[cfg(feature = "amethyst")]
 pub type VX4sHandle = Handle<VX4sAsset>;
 
+#[cfg(feature = "amethyst")]
 impl Asset for VX4sAsset {
     const NAME: &'static str = "bw_assets::tileset::VX4sAsset";
     type Data = Self;
     type HandleStorage = DenseVecStorage<VX4sHandle>;
 }
 
+#[cfg(feature = "amethyst")]
 #[derive(Clone, Copy, Debug, Default)]
 pub struct VX4sAssetFormat;
 
+#[cfg(feature = "amethyst")]
 impl Format<VX4sAsset> for VX4sAssetFormat {
     fn name(&self) -> &'static str {
         "VX4Format"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<VX4sAsset> {
-        let (_, vx4s) = parse_vx4s(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load vx4 asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(VX4sAsset(Some(vx4s)))
+        Ok(VX4sAsset(Some(VX4s::from_bytes(&b)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn vx4s(indices: &[u16]) -> VX4s {
+        let b = (0..VX4s::BLOCK_SIZE)
+            .flat_map(|i| (indices[i % indices.len()] << 1).to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+
+        VX4s::from_bytes(&b).unwrap()
+    }
+
+    #[test]
+    fn it_packs_the_vr4_index_and_flip() {
+        let vx4 = VX4::new(5, true).unwrap();
+
+        assert_that(&vx4.index()).is_equal_to(5);
+        assert_that(&vx4.is_horizontally_flipped()).is_true();
+        assert_that(&VX4::new(VX4::MAX_INDEX, false).unwrap().index()).is_equal_to(VX4::MAX_INDEX);
+        assert_that(&VX4::new(VX4::MAX_INDEX + 1, false))
+            .is_err_containing(VX4Error::IndexOutOfRange { index: 0x8000 });
+    }
+
+    #[test]
+    fn it_indexes_blocks_by_number() {
+        let vx4s = VX4s::from_bytes(&(0..64u8).collect::<Vec<_>>()).unwrap();
+
+        assert_that(&vx4s[0][0].index()).is_equal_to(0x0080);
+        assert_that(&vx4s[1][15].index()).is_equal_to(0x1f9f);
+        assert_that(&vx4s.block(1).map(Vec::len)).is_equal_to(Some(16));
+        assert_that(&vx4s.block(2)).is_none();
+    }
+
+    #[test]
+    fn it_checks_that_the_minitiles_reference_existing_vr4s() {
+        let vr4s = VR4s::from_bytes(&vec![0; VR4s::BLOCK_SIZE * 3]).unwrap();
+        let in_range = vx4s(&[0, 2, 1]);
+        let out_of_range = vx4s(&[0, 3]);
+
+        assert_that(&in_range.max_vr4_index()).is_equal_to(2);
+        assert_that(&in_range.references_fit(&vr4s)).is_true();
+        assert_that(&out_of_range.max_vr4_index()).is_equal_to(3);
+        assert_that(&out_of_range.references_fit(&vr4s)).is_false();
+        assert_that(&VX4s::empty().references_fit(&VR4s::empty())).is_true();
     }
 }
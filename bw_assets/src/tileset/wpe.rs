@@ -1,3 +1,4 @@
+#[cfg(feature = "amethyst")]
 use amethyst::{
     assets::{Asset, Format, Handle},
     ecs::DenseVecStorage,
@@ -7,27 +8,86 @@ use nom::{
     multi::many0,
     number::complete::le_u8,
     sequence::tuple,
+    IResult,
 };
-use nom::{Finish, IResult};
 
-use std::ops::Index;
+use std::{io, io::BufRead, ops::Index};
 
-use super::VR4;
+use super::{stream::read_block, VR4};
+
+use crate::error::{finish_parse, ParseError};
 
 /// 256-color RGB Palette.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct WPE([u8; WPE::BLOCK_SIZE]);
 
-/// Gamma correction function
+/// Display gamma used by gamma correction.
+const GAMMA: f32 = 2.2;
+
+/// Gamma correction function, from a color channel to 0.0-1.0.
 ///
 /// see: https://www.cambridgeincolour.com/tutorials/gamma-correction.htm
 fn srgb(x: u8) -> f32 {
-    (x as f32).powf(1.0 / 2.2)
+    (x as f32 / 255.0).powf(1.0 / GAMMA)
+}
+
+/// The sRGB transfer function, from a color channel to linear light in
+/// 0.0-1.0. This is the same conversion as `WPE::linear` of the `palette`
+/// feature.
+///
+/// see: https://www.w3.org/Graphics/Color/srgb
+fn linear(x: u8) -> f32 {
+    let x = x as f32 / 255.0;
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn requantize(x: f32) -> u8 {
+    (x * 255.0).round().max(0.0).min(255.0) as u8
+}
+
+/// Color pipeline used when rendering palette colors to pixels.
+///
+/// Every mode outputs 8 bits per channel, so the output buffers are the same
+/// regardless of the mode. The default is [`ColorOutput::Raw`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorOutput {
+    /// Palette colors as they are stored in the WPE.
+    Raw,
+
+    /// Palette colors after gamma correction, requantized to 8 bits.
+    Srgb,
+
+    /// Palette colors converted to linear light, requantized to 8 bits.
+    Linear,
+}
+
+impl Default for ColorOutput {
+    fn default() -> Self {
+        ColorOutput::Raw
+    }
 }
 
 impl WPE {
     const BLOCK_SIZE: usize = 3;
 
+    pub fn new(r: u8, g: u8, b: u8) -> WPE {
+        WPE([r, g, b])
+    }
+
+    /// Color of the first 3 bytes of `b` as rgb, or `None` if `b` is shorter.
+    /// Any further bytes, such as the padding byte of a `.wpe` entry, are
+    /// ignored.
+    pub fn from_slice(b: &[u8]) -> Option<WPE> {
+        match b {
+            [r, g, b, ..] => Some(WPE::new(*r, *g, *b)),
+            _ => None,
+        }
+    }
+
     pub fn r(&self) -> u8 {
         self.0[0]
     }
@@ -45,10 +105,73 @@ impl WPE {
         [self.0[0], self.0[1], self.0[2]]
     }
 
-    /// Color in srgb after gamma correction
+    /// Color in srgb after gamma correction, with the gamma applied to the
+    /// 0-255 channels, so each channel is in 0.0-12.4.
+    #[deprecated(note = "channels are not normalized, use `srgb_normalized` instead")]
     pub fn srgb(&self) -> [f32; 3] {
+        let gamma = |x: u8| (x as f32).powf(1.0 / GAMMA);
+        [gamma(self.0[0]), gamma(self.0[1]), gamma(self.0[2])]
+    }
+
+    /// Color in srgb after gamma correction, with each channel in 0.0-1.0
+    pub fn srgb_normalized(&self) -> [f32; 3] {
         [srgb(self.0[0]), srgb(self.0[1]), srgb(self.0[2])]
     }
+
+    /// Color in srgb after gamma correction, requantized to 8 bits per
+    /// channel, e.g. to upload into an srgb texture.
+    pub fn srgb_u8(&self) -> [u8; 3] {
+        self.color(ColorOutput::Srgb)
+    }
+
+    /// Color with each channel in 0.0-1.0 after converting from sRGB to linear
+    /// light
+    pub fn linear_rgb(&self) -> [f32; 3] {
+        [linear(self.0[0]), linear(self.0[1]), linear(self.0[2])]
+    }
+
+    /// Euclidean distance between the raw rgb values of two colors.
+    pub fn distance(&self, other: &WPE) -> f32 {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(&a, &b)| (a as f32 - b as f32).powi(2))
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    /// 8 bit color for a color pipeline
+    pub fn color(&self, output: ColorOutput) -> [u8; 3] {
+        match output {
+            ColorOutput::Raw => self.rgb(),
+            ColorOutput::Srgb => {
+                let [r, g, b] = self.srgb_normalized();
+                [requantize(r), requantize(g), requantize(b)]
+            }
+            ColorOutput::Linear => {
+                let [r, g, b] = self.linear_rgb();
+                [requantize(r), requantize(g), requantize(b)]
+            }
+        }
+    }
+}
+
+#[cfg(feature = "palette")]
+impl From<&WPE> for palette::Srgb<u8> {
+    fn from(wpe: &WPE) -> Self {
+        palette::Srgb::new(wpe.r(), wpe.g(), wpe.b())
+    }
+}
+
+#[cfg(feature = "palette")]
+impl WPE {
+    /// Color converted into linear space.
+    ///
+    /// Colors should be blended in linear space and converted back to srgb
+    /// afterwards.
+    pub fn linear(&self) -> palette::LinSrgb<f32> {
+        palette::Srgb::from(self).into_format::<f32>().into_linear()
+    }
 }
 
 fn parse_wpe(b: &[u8]) -> IResult<&[u8], WPE> {
@@ -57,7 +180,53 @@ fn parse_wpe(b: &[u8]) -> IResult<&[u8], WPE> {
     })(b)
 }
 
-#[derive(Debug)]
+fn parse_pal_entry(b: &[u8]) -> IResult<&[u8], WPE> {
+    map(tuple((le_u8, le_u8, le_u8)), |(r, g, b)| WPE([r, g, b]))(b)
+}
+
+/// Parses palette entries one at a time from a reader.
+///
+/// A final entry that is shorter than 4 bytes is yielded as an
+/// `UnexpectedEof` error, after which the parser is exhausted.
+pub struct WPEsStreamParser<R> {
+    reader: R,
+    finished: bool,
+}
+
+impl<R: BufRead> WPEsStreamParser<R> {
+    pub fn new(reader: R) -> WPEsStreamParser<R> {
+        WPEsStreamParser {
+            reader,
+            finished: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for WPEsStreamParser<R> {
+    type Item = io::Result<WPE>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        // rgb followed by an unused padding byte
+        let mut block = [0; 4];
+        match read_block(&mut self.reader, &mut block) {
+            Ok(true) => Some(Ok(WPE([block[0], block[1], block[2]]))),
+            Ok(false) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct WPEs(Vec<WPE>);
 
 impl Index<VR4> for WPEs {
@@ -76,44 +245,585 @@ impl Index<&VR4> for WPEs {
     }
 }
 
-fn parse_wpes(b: &[u8]) -> IResult<&[u8], WPEs> {
+/// The 8 shades of a player's color, from brightest to darkest, as stored in
+/// the player color range of a palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerColor([[u8; 3]; PlayerColor::LEN]);
+
+impl PlayerColor {
+    /// Number of palette indices recolored for each player.
+    pub const LEN: usize = 8;
+
+    /// First palette index of the player color range.
+    pub const FIRST_INDEX: usize = 8;
+
+    pub fn new(shades: [[u8; 3]; PlayerColor::LEN]) -> PlayerColor {
+        PlayerColor(shades)
+    }
+
+    pub fn shades(&self) -> &[[u8; 3]; PlayerColor::LEN] {
+        &self.0
+    }
+}
+
+impl WPEs {
+    /// Midpoint of a color channel that contrast is scaled around.
+    const CONTRAST_MIDPOINT: f32 = 127.5;
+
+    /// Maximum number of k-means iterations run by [`WPEs::cluster`].
+    const MAX_CLUSTER_ITERATIONS: usize = 64;
+
+    /// Brightness of terrain that has been explored but is not currently in
+    /// vision of any unit. This is an approximation chosen for this renderer
+    /// by eye, not a value read from the game.
+    pub const EXPLORED_BRIGHTNESS: f32 = 0.5;
+
+    /// Brightness of the fog covering terrain that has not been explored yet.
+    /// Like [`WPEs::EXPLORED_BRIGHTNESS`], this is an approximation chosen for
+    /// this renderer. The game fades unexplored terrain towards black, which a
+    /// single palette cannot show, so a dark shade stands in for it.
+    pub const FOG_BRIGHTNESS: f32 = 0.25;
+
+    /// Creates an empty palette, mostly useful for testing.
+    pub fn empty() -> WPEs {
+        WPEs(vec![])
+    }
+
+    /// Creates a palette of `colors`, e.g. to encode a palette built in code.
+    pub fn from_colors(colors: Vec<WPE>) -> WPEs {
+        WPEs(colors)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn heap_size(&self) -> usize {
+        self.0.capacity() * std::mem::size_of::<WPE>()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<WPE> {
+        self.0.iter()
+    }
+
+    /// Replaces the color at `index`, which must be in range.
+    pub(crate) fn replace_color(&mut self, index: usize, rgb: [u8; 3]) {
+        self.0[index] = WPE(rgb);
+    }
+
+    /// Color of the pixel, or `None` if the palette has fewer colors.
+    pub fn get(&self, vr4: &VR4) -> Option<&WPE> {
+        self.0.get(usize::from(vr4))
+    }
+
+    /// 64 bit FNV-1a hash of the colors of the palette. The unused padding
+    /// byte is not hashed, so a `.wpe` and `.pal` of the same colors have the
+    /// same fingerprint. The hash is stable across builds and platforms.
+    pub fn fingerprint(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        self.0
+            .iter()
+            .flat_map(|wpe| wpe.0.iter())
+            .fold(OFFSET_BASIS, |hash, &byte| {
+                (hash ^ byte as u64).wrapping_mul(PRIME)
+            })
+    }
+
+    /// Creates a new palette with the brightness and contrast of every color
+    /// adjusted.
+    ///
+    /// Contrast scales each channel around the channel midpoint, and brightness
+    /// then scales the result. A brightness and contrast of 1.0 leaves the
+    /// palette unchanged. Channels are clamped to 0-255.
+    pub fn adjusted(&self, brightness: f32, contrast: f32) -> WPEs {
+        let adjust = |x: u8| {
+            let x = ((x as f32 - WPEs::CONTRAST_MIDPOINT) * contrast + WPEs::CONTRAST_MIDPOINT)
+                * brightness;
+
+            x.round().max(0.0).min(255.0) as u8
+        };
+
+        WPEs(
+            self.0
+                .iter()
+                .map(|wpe| WPE([adjust(wpe.r()), adjust(wpe.g()), adjust(wpe.b())]))
+                .collect(),
+        )
+    }
+
+    /// Groups similar colors of the palette with k-means, returning the
+    /// cluster of every color.
+    ///
+    /// The clusters are seeded deterministically: the first color of the
+    /// palette, then repeatedly the color furthest from every seed so far. At
+    /// most 256 clusters are formed, and never more than there are colors.
+    pub fn cluster(&self, k: usize) -> Vec<u8> {
+        let k = k.min(self.0.len()).min(256);
+        if k == 0 {
+            return vec![0; self.0.len()];
+        }
+
+        let colors = self
+            .0
+            .iter()
+            .map(|wpe| {
+                let [r, g, b] = wpe.rgb();
+                [r as f32, g as f32, b as f32]
+            })
+            .collect::<Vec<_>>();
+        let distance = |a: &[f32; 3], b: &[f32; 3]| -> f32 {
+            a.iter().zip(b.iter()).map(|(a, b)| (a - b).powi(2)).sum()
+        };
+        let nearest = |centroids: &[[f32; 3]], color: &[f32; 3]| {
+            (0..centroids.len())
+                .min_by(|&i, &j| {
+                    distance(&centroids[i], color)
+                        .partial_cmp(&distance(&centroids[j], color))
+                        .unwrap()
+                })
+                .unwrap()
+        };
+
+        let mut centroids = vec![colors[0]];
+        while centroids.len() < k {
+            let furthest = colors
+                .iter()
+                .max_by(|a, b| {
+                    let a = distance(&centroids[nearest(&centroids, a)], a);
+                    let b = distance(&centroids[nearest(&centroids, b)], b);
+                    a.partial_cmp(&b).unwrap()
+                })
+                .unwrap();
+            centroids.push(*furthest);
+        }
+
+        let mut clusters = vec![0; colors.len()];
+        for _ in 0..WPEs::MAX_CLUSTER_ITERATIONS {
+            let assigned = colors
+                .iter()
+                .map(|color| nearest(&centroids, color))
+                .collect::<Vec<_>>();
+            let converged = assigned == clusters;
+            clusters = assigned;
+            if converged {
+                break;
+            }
+
+            // clusters that lost every color keep their previous centroid
+            for (i, centroid) in centroids.iter_mut().enumerate() {
+                let members = colors
+                    .iter()
+                    .zip(clusters.iter())
+                    .filter(|(_, &cluster)| cluster == i)
+                    .map(|(color, _)| color)
+                    .collect::<Vec<_>>();
+                if members.is_empty() {
+                    continue;
+                }
+
+                for (channel, value) in centroid.iter_mut().enumerate() {
+                    *value = members.iter().map(|color| color[channel]).sum::<f32>()
+                        / members.len() as f32;
+                }
+            }
+        }
+
+        clusters.into_iter().map(|cluster| cluster as u8).collect()
+    }
+
+    /// Creates a new palette with the player color range replaced by the
+    /// shades of `color`. Every other color is unchanged, and a palette too
+    /// short to hold the whole range only has the colors it holds replaced.
+    pub fn apply_team_color(&self, color: PlayerColor) -> WPEs {
+        let range = PlayerColor::FIRST_INDEX..PlayerColor::FIRST_INDEX + PlayerColor::LEN;
+
+        WPEs(
+            self.0
+                .iter()
+                .enumerate()
+                .map(|(i, wpe)| {
+                    if range.contains(&i) {
+                        WPE(color.0[i - PlayerColor::FIRST_INDEX])
+                    } else {
+                        WPE(wpe.0)
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Exports the palette as a GIMP palette (`.gpl`), with a line for every
+    /// color labelled with its index.
+    pub fn to_gpl(&self, name: &str) -> String {
+        let mut gpl = format!("GIMP Palette\nName: {}\nColumns: 16\n#\n", name);
+        for (i, wpe) in self.0.iter().enumerate() {
+            gpl += &format!("{:3} {:3} {:3}\tIndex {}\n", wpe.r(), wpe.g(), wpe.b(), i);
+        }
+
+        gpl
+    }
+
+    /// Palette used to render terrain hidden under the fog of war.
+    pub fn fog_variant(&self) -> WPEs {
+        self.adjusted(WPEs::FOG_BRIGHTNESS, 1.0)
+    }
+
+    /// Palette used to render explored terrain that is not currently visible.
+    pub fn explored_variant(&self) -> WPEs {
+        self.adjusted(WPEs::EXPLORED_BRIGHTNESS, 1.0)
+    }
+
+    /// Parses a `.wpe` palette, with a padding byte after every color. Use
+    /// [`WPEFormat::parse`] for other strides.
+    pub fn from_bytes(b: &[u8]) -> Result<WPEs, ParseError> {
+        finish_parse("wpe", b, parse_wpes(b))
+    }
+}
+
+pub(crate) fn parse_wpes(b: &[u8]) -> IResult<&[u8], WPEs> {
     all_consuming(map(many0(parse_wpe), WPEs))(b)
 }
 
+/// Parses a palette of packed 3 byte RGB colors, such as a `.pal` file.
+pub(crate) fn parse_pal(b: &[u8]) -> IResult<&[u8], WPEs> {
+    all_consuming(map(many0(parse_pal_entry), WPEs))(b)
+}
+
+/// Number of bytes used by each color of a palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WPEStride {
+    /// 3 byte RGB, as found in `.pal` files.
+    Packed,
+
+    /// 3 byte RGB followed by an unused byte, as found in `.wpe` files.
+    Padded,
+}
+
+impl WPEStride {
+    /// Detects the stride of a 256 color palette from its size in bytes.
+    pub fn detect(len: usize) -> Option<WPEStride> {
+        match len {
+            768 => Some(WPEStride::Packed),
+            1024 => Some(WPEStride::Padded),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "amethyst")]
 #[derive(Debug)]
 pub struct WPEsAsset(Option<WPEs>);
 
+#[cfg(feature = "amethyst")]
 impl WPEsAsset {
     pub fn take(&mut self) -> Option<WPEs> {
         self.0.take()
     }
 }
 
+#[cfg(feature = "amethyst")]
 pub type WPEsHandle = Handle<WPEsAsset>;
 
+#[cfg(feature = "amethyst")]
 impl Asset for WPEsAsset {
     const NAME: &'static str = "bw_assets::tileset::WPEsAsset";
     type Data = Self;
     type HandleStorage = DenseVecStorage<WPEsHandle>;
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct WPEFormat;
+/// Format of a palette asset.
+///
+/// The default format reads `.wpe` files with a 4 byte stride. Use
+/// [`WPEFormat::auto`] when the stride is not known ahead of time.
+#[derive(Clone, Copy, Debug)]
+pub struct WPEFormat {
+    stride: Option<WPEStride>,
+}
+
+impl WPEFormat {
+    pub fn new(stride: WPEStride) -> WPEFormat {
+        WPEFormat {
+            stride: Some(stride),
+        }
+    }
 
+    /// Detects the stride from the size of the palette. Only 256 color
+    /// palettes can be detected, anything else fails to load.
+    pub fn auto() -> WPEFormat {
+        WPEFormat { stride: None }
+    }
+
+    /// Parses the palette in `b` with the stride of this format.
+    pub fn parse(&self, b: &[u8]) -> Result<WPEs, ParseError> {
+        let stride = self.stride.or_else(|| WPEStride::detect(b.len())).ok_or(
+            ParseError::UnexpectedLength {
+                asset: "wpe",
+                expected: "768 or 1024",
+                len: b.len(),
+            },
+        )?;
+
+        let parse = match stride {
+            WPEStride::Packed => parse_pal,
+            WPEStride::Padded => parse_wpes,
+        };
+
+        finish_parse("wpe", b, parse(b))
+    }
+}
+
+impl Default for WPEFormat {
+    fn default() -> Self {
+        WPEFormat::new(WPEStride::Padded)
+    }
+}
+
+#[cfg(feature = "amethyst")]
 impl Format<WPEsAsset> for WPEFormat {
     fn name(&self) -> &'static str {
         "WPEFormat"
     }
 
     fn import_simple(&self, b: Vec<u8>) -> amethyst::Result<WPEsAsset> {
-        let (_, wpes) = parse_wpes(&b).finish().map_err(|err| {
-            amethyst::error::format_err!(
-                "failed to load wpe asset: {} at position {}",
-                err.code.description(),
-                b.len() - err.input.len()
-            )
-        })?;
-
-        Ok(WPEsAsset(Some(wpes)))
+        Ok(WPEsAsset(Some(self.parse(&b)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn palette() -> WPEs {
+        WPEs(vec![
+            WPE([0, 0, 0]),
+            WPE([10, 20, 30]),
+            WPE([128, 64, 32]),
+            WPE([254, 254, 254]),
+        ])
+    }
+
+    #[test]
+    fn it_builds_a_palette_in_code() {
+        let wpes = WPEs::from_colors(vec![
+            WPE::new(10, 20, 30),
+            WPE::from_slice(&[128, 64, 32, 0]).unwrap(),
+        ]);
+
+        let colors = wpes.iter().map(WPE::rgb).collect::<Vec<_>>();
+
+        assert_that(&colors).is_equal_to(vec![[10, 20, 30], [128, 64, 32]]);
+        assert_that(&WPE::from_slice(&[1, 2]).is_none()).is_true();
+    }
+
+    #[test]
+    fn it_fingerprints_palettes_by_their_colors() {
+        let (_, packed) = parse_pal(&[0, 0, 0, 10, 20, 30]).unwrap();
+        let (_, padded) = parse_wpes(&[0, 0, 0, 0, 10, 20, 30, 255]).unwrap();
+
+        assert_that(&WPEs::empty().fingerprint()).is_equal_to(0xcbf2_9ce4_8422_2325);
+        assert_that(&packed.fingerprint()).is_equal_to(padded.fingerprint());
+        assert_that(&palette().fingerprint()).is_not_equal_to(packed.fingerprint());
+    }
+
+    #[test]
+    fn it_only_recolors_the_player_color_range() {
+        let wpes = WPEs((0..20).map(|i| WPE([i, i, i])).collect());
+        let mut shades = [[0; 3]; PlayerColor::LEN];
+        for (i, shade) in shades.iter_mut().enumerate() {
+            *shade = [200 - i as u8, 0, 0];
+        }
+
+        let recolored = wpes.apply_team_color(PlayerColor::new(shades));
+
+        assert_that(&recolored.len()).is_equal_to(20);
+        for (i, wpe) in recolored.0.iter().enumerate() {
+            let expected = match i {
+                8..=15 => shades[i - 8],
+                _ => wpes.0[i].rgb(),
+            };
+            assert_that(&wpe.rgb())
+                .named(&i.to_string())
+                .is_equal_to(expected);
+        }
+        assert_that(&palette().apply_team_color(PlayerColor::new(shades)).len()).is_equal_to(4);
+    }
+
+    #[test]
+    fn it_exports_a_gimp_palette() {
+        let wpes = WPEs((0..=255).map(|i| WPE([i, 255 - i, 7])).collect());
+
+        let gpl = wpes.to_gpl("badlands");
+        let lines = gpl.lines().collect::<Vec<_>>();
+
+        assert_that(&lines[0..4].to_vec()).is_equal_to(vec![
+            "GIMP Palette",
+            "Name: badlands",
+            "Columns: 16",
+            "#",
+        ]);
+        assert_that(&lines[4..].to_vec()).has_length(256);
+        assert_that(&lines[5]).is_equal_to("  1 254   7\tIndex 1");
+    }
+
+    #[test]
+    fn it_halves_colors_at_half_brightness() {
+        let wpes = palette();
+        let adjusted = wpes.adjusted(0.5, 1.0);
+
+        assert_that(&adjusted.len()).is_equal_to(wpes.len());
+        for (wpe, adjusted_wpe) in wpes.0.iter().zip(adjusted.0.iter()) {
+            assert_that(&adjusted_wpe.rgb()).is_equal_to([wpe.r() / 2, wpe.g() / 2, wpe.b() / 2]);
+        }
+    }
+
+    #[test]
+    fn it_does_not_change_colors_at_identity() {
+        let wpes = palette();
+        let adjusted = wpes.adjusted(1.0, 1.0);
+
+        for (wpe, adjusted_wpe) in wpes.0.iter().zip(adjusted.0.iter()) {
+            assert_that(&adjusted_wpe.rgb()).is_equal_to(wpe.rgb());
+        }
+    }
+
+    #[test]
+    fn it_darkens_fog_more_than_explored_terrain() {
+        let wpes = palette();
+        let fog = wpes.fog_variant();
+        let explored = wpes.explored_variant();
+
+        assert_that(&explored.0[2].rgb()).is_equal_to([64, 32, 16]);
+        assert_that(&fog.0[2].rgb()).is_equal_to([32, 16, 8]);
+    }
+
+    #[test]
+    fn it_streams_wpes() {
+        let b = vec![1, 2, 3, 0, 4, 5, 6, 0, 7];
+        let mut parser = WPEsStreamParser::new(std::io::Cursor::new(b));
+
+        assert_that(&parser.next().unwrap().unwrap().rgb()).is_equal_to([1, 2, 3]);
+        assert_that(&parser.next().unwrap().unwrap().rgb()).is_equal_to([4, 5, 6]);
+        let err = parser.next().unwrap().unwrap_err();
+        assert_that(&err.kind()).is_equal_to(io::ErrorKind::UnexpectedEof);
+        assert_that(&parser.next().is_none()).is_true();
+    }
+
+    #[test]
+    fn it_detects_packed_palettes() {
+        let b = (0..256 * 3).map(|i| (i / 3) as u8).collect::<Vec<_>>();
+        let wpes = WPEFormat::auto().parse(&b).unwrap();
+
+        assert_that(&wpes.len()).is_equal_to(256);
+        assert_that(&wpes.0[1].rgb()).is_equal_to([1, 1, 1]);
+        assert_that(&wpes.0[255].rgb()).is_equal_to([255, 255, 255]);
+    }
+
+    #[test]
+    fn it_detects_padded_palettes() {
+        let b = (0..256 * 4).map(|i| (i / 4) as u8).collect::<Vec<_>>();
+        let wpes = WPEFormat::auto().parse(&b).unwrap();
+
+        assert_that(&wpes.len()).is_equal_to(256);
+        assert_that(&wpes.0[1].rgb()).is_equal_to([1, 1, 1]);
+        assert_that(&wpes.0[255].rgb()).is_equal_to([255, 255, 255]);
+    }
+
+    #[test]
+    fn it_rejects_palettes_of_unknown_size() {
+        assert_that(&WPEFormat::auto().parse(&[0; 100])).is_err_containing(
+            ParseError::UnexpectedLength {
+                asset: "wpe",
+                expected: "768 or 1024",
+                len: 100,
+            },
+        );
+    }
+
+    #[test]
+    fn it_outputs_raw_colors() {
+        assert_that(&palette().0[2].color(ColorOutput::Raw)).is_equal_to([128, 64, 32]);
+        assert_that(&ColorOutput::default()).is_equal_to(ColorOutput::Raw);
+    }
+
+    #[test]
+    fn it_outputs_gamma_corrected_colors() {
+        let wpe = WPE([0, 64, 255]);
+
+        assert_that(&wpe.color(ColorOutput::Srgb)).is_equal_to([0, 136, 255]);
+    }
+
+    #[test]
+    fn it_requantizes_gamma_corrected_colors() {
+        assert_that(&WPE::new(255, 255, 255).srgb_u8()).is_equal_to([255, 255, 255]);
+        assert_that(&WPE::new(128, 128, 128).srgb_u8()).is_equal_to([186, 186, 186]);
+        assert_that(&WPE::new(0, 0, 0).srgb_u8()).is_equal_to([0, 0, 0]);
+    }
+
+    #[test]
+    fn it_outputs_linear_colors() {
+        let wpe = WPE([0, 128, 255]);
+
+        assert_that(&wpe.color(ColorOutput::Linear)).is_equal_to([0, 55, 255]);
+    }
+
+    #[cfg(feature = "palette")]
+    #[test]
+    fn it_converts_to_linear_light_like_palette() {
+        for x in 0..=255 {
+            let wpe = WPE([x, x / 2, 255 - x]);
+            let expected = wpe.linear();
+
+            let [r, g, b] = wpe.linear_rgb();
+            assert_that(&r).is_close_to(expected.red, 1e-6);
+            assert_that(&g).is_close_to(expected.green, 1e-6);
+            assert_that(&b).is_close_to(expected.blue, 1e-6);
+        }
+    }
+
+    #[test]
+    fn it_clamps_adjusted_colors() {
+        let adjusted = palette().adjusted(2.0, 1.0);
+
+        assert_that(&adjusted.0[3].rgb()).is_equal_to([255, 255, 255]);
+    }
+
+    #[test]
+    fn it_measures_the_distance_between_colors() {
+        assert_that(&WPE([0, 0, 0]).distance(&WPE([3, 4, 0]))).is_equal_to(5.0);
+        assert_that(&WPE([10, 20, 30]).distance(&WPE([10, 20, 30]))).is_equal_to(0.0);
+    }
+
+    #[test]
+    fn it_clusters_grayscale_into_contiguous_shades() {
+        let grays = WPEs((0..=255).map(|i| WPE([i, i, i])).collect());
+
+        let clusters = grays.cluster(4);
+        let boundaries = clusters
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .count();
+        let mut distinct = clusters.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+
+        assert_that(&clusters).has_length(256);
+        assert_that(&distinct).has_length(4);
+        // every cluster is a single range of shades
+        assert_that(&boundaries).is_equal_to(3);
+        assert_that(&clusters[0]).is_not_equal_to(clusters[255]);
+    }
+
+    #[test]
+    fn it_never_forms_more_clusters_than_colors() {
+        // seeded with black, then white, then the colors furthest from those
+        assert_that(&palette().cluster(10)).is_equal_to(vec![0, 3, 2, 1]);
+        assert_that(&WPEs::empty().cluster(4)).is_empty();
     }
 }
@@ -62,6 +62,29 @@ pub struct Unit {
 }
 
 impl Unit {
+    /// Horizontal pixel position of the center of the unit.
+    pub fn x(&self) -> u16 {
+        self.x
+    }
+
+    /// Vertical pixel position of the center of the unit.
+    pub fn y(&self) -> u16 {
+        self.y
+    }
+
+    /// `None` if the map references a unit that does not exist.
+    pub fn unit_id(&self) -> Option<&UnitId> {
+        self.unit_id.as_ref()
+    }
+
+    pub fn is_start_location(&self) -> bool {
+        self.unit_id == Some(UnitId::SpecialStartLocation)
+    }
+
+    pub fn is_vespene_geyser(&self) -> bool {
+        self.unit_id == Some(UnitId::ResourceVespeneGeyser)
+    }
+
     pub fn is_mineral_field(&self) -> bool {
         self.unit_id == Some(UnitId::ResourceMineralField)
             || self.unit_id == Some(UnitId::ResourceMineralFieldType2)
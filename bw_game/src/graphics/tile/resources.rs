@@ -58,7 +58,7 @@ pub fn load(
 
     let wpe_handle = loader.load_from(
         format!("tileset\\{}.wpe", tileset_file_name),
-        WPEFormat,
+        WPEFormat::default(),
         "bw_assets",
         &mut progress_counter_newtype,
         &world.read_resource::<AssetStorage<WPEsAsset>>(),
@@ -66,7 +66,7 @@ pub fn load(
 
     let cv5_handle = loader.load_from(
         format!("tileset\\{}.cv5", tileset_file_name),
-        CV5Format,
+        CV5Format::default(),
         "bw_assets",
         &mut progress_counter_newtype,
         &world.read_resource::<AssetStorage<CV5sAsset>>(),
@@ -21,8 +21,9 @@ use amethyst::{
 use bw_assets::{
     dat::UnitsDat,
     dat::{
-        FlingyDat, FlingyDatAsset, SpritesDat, SpritesDatAsset, TechDataDat, TechDataDatAsset,
-        UnitsDatAsset, UpgradesDat, UpgradesDatAsset, WeaponsDat, WeaponsDatAsset,
+        DatAsset, FlingyDat, FlingyDatAsset, SpritesDat, SpritesDatAsset, TechDataDat,
+        TechDataDatAsset, UnitsDatAsset, UpgradesDat, UpgradesDatAsset, WeaponsDat,
+        WeaponsDatAsset,
     },
     map::{Map, MapFormat, MapHandle},
     mpq::MPQHandle,